@@ -0,0 +1,295 @@
+// access_control.rs - 访问控制
+// 这个模块实现基于角色的权限系统 (RBAC)，角色分配保存在稳定内存中
+
+use crate::{Error, Result, ic_api};
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::{
+    StableBTreeMap, Storable,
+    memory_manager::MemoryId,
+    storable::Bound,
+};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+// 权限类型
+#[derive(CandidType, Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Permission {
+    Deposit,                     // 存款/借款
+    Withdraw,                    // 还款/提款
+    Liquidate,                   // 清算
+    UpdateCollateralRatio,       // 更新抵押率
+    UpdateLiquidationThreshold,  // 更新清算阈值
+    ManagePool,                  // 管理资金池
+    EmergencyPause,              // 紧急暂停
+    EmergencyResume,             // 紧急恢复
+    EmergencyOperator,           // 紧急操作员
+    ViewMetrics,                 // 查看指标
+    ViewAllPositions,            // 查看所有头寸
+    SystemMaintenance,           // 系统维护
+    SystemAdmin,                 // 系统管理
+    SuperAdmin,                  // 超级管理员
+    CancelProposal,              // 取消治理提案
+    ManageBlocklist,             // 管理黑名单
+}
+
+impl Permission {
+    // 所有权限
+    pub fn all() -> Vec<Permission> {
+        vec![
+            Permission::Deposit,
+            Permission::Withdraw,
+            Permission::Liquidate,
+            Permission::UpdateCollateralRatio,
+            Permission::UpdateLiquidationThreshold,
+            Permission::ManagePool,
+            Permission::EmergencyPause,
+            Permission::EmergencyResume,
+            Permission::EmergencyOperator,
+            Permission::ViewMetrics,
+            Permission::ViewAllPositions,
+            Permission::SystemMaintenance,
+            Permission::SystemAdmin,
+            Permission::SuperAdmin,
+            Permission::CancelProposal,
+            Permission::ManageBlocklist,
+        ]
+    }
+}
+
+// 角色类型
+#[derive(CandidType, Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Role {
+    User,                        // 普通用户
+    Liquidator,                  // 清算员
+    PoolManager,                 // 池管理员
+    EmergencyOperator,           // 紧急操作员
+    SystemAdmin,                 // 系统管理员
+    SuperAdmin,                  // 超级管理员
+    Guardian,                    // 守护者 (可取消治理提案)
+}
+
+impl Role {
+    // 角色包含的权限 (高级角色继承低级角色的权限)
+    pub fn permissions(&self) -> BTreeSet<Permission> {
+        let mut permissions = BTreeSet::new();
+
+        match self {
+            Role::User => {
+                permissions.extend([Permission::Deposit, Permission::Withdraw, Permission::ViewMetrics]);
+            }
+            Role::Liquidator => {
+                permissions.extend(Role::User.permissions());
+                permissions.insert(Permission::Liquidate);
+            }
+            Role::PoolManager => {
+                permissions.extend(Role::Liquidator.permissions());
+                permissions.extend([
+                    Permission::UpdateCollateralRatio,
+                    Permission::UpdateLiquidationThreshold,
+                    Permission::ManagePool,
+                ]);
+            }
+            Role::EmergencyOperator => {
+                permissions.extend(Role::User.permissions());
+                permissions.extend([
+                    Permission::EmergencyPause,
+                    Permission::EmergencyResume,
+                    Permission::EmergencyOperator,
+                    Permission::ViewAllPositions,
+                ]);
+            }
+            Role::SystemAdmin => {
+                permissions.extend(Role::PoolManager.permissions());
+                permissions.extend(Role::EmergencyOperator.permissions());
+                permissions.extend([
+                    Permission::SystemMaintenance,
+                    Permission::SystemAdmin,
+                    Permission::ManageBlocklist,
+                ]);
+            }
+            Role::SuperAdmin => {
+                permissions.extend(Permission::all());
+            }
+            Role::Guardian => {
+                permissions.extend(Role::User.permissions());
+                permissions.insert(Permission::CancelProposal);
+            }
+        }
+
+        permissions
+    }
+}
+
+// 角色分配记录
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, Default)]
+pub struct RoleAssignment {
+    pub roles: BTreeSet<Role>,
+    pub updated_at: u64,
+    pub updated_by: String,
+}
+
+impl Storable for RoleAssignment {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode RoleAssignment")
+    }
+}
+
+thread_local! {
+    // 角色分配存储 (principal -> 角色集合)
+    static ROLE_ASSIGNMENTS: RefCell<StableBTreeMap<Principal, RoleAssignment, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(7))),
+        )
+    );
+}
+
+// 获取 principal 的所有角色
+// 非匿名 principal 默认拥有 User 角色，控制者默认拥有 SuperAdmin 角色
+fn roles_of(principal: Principal) -> BTreeSet<Role> {
+    let mut roles = ROLE_ASSIGNMENTS.with_borrow(|assignments| {
+        assignments.get(&principal).map(|a| a.roles).unwrap_or_default()
+    });
+
+    if principal != Principal::anonymous() {
+        roles.insert(Role::User);
+    }
+
+    if ic_api::is_controller(&principal) {
+        roles.insert(Role::SuperAdmin);
+    }
+
+    roles
+}
+
+// 获取 principal 的有效权限
+fn effective_permissions(principal: Principal) -> BTreeSet<Permission> {
+    roles_of(principal).iter()
+        .flat_map(|role| role.permissions())
+        .collect()
+}
+
+#[query]
+// 检查 principal 是否拥有指定权限
+pub fn has_permission(principal: Principal, permission: Permission) -> bool {
+    effective_permissions(principal).contains(&permission)
+}
+
+// 检查权限，没有权限时返回错误
+pub fn check_permission(principal: Principal, permission: Permission) -> Result<()> {
+    if has_permission(principal, permission) {
+        Ok(())
+    } else {
+        Err(Error::PermissionDenied(format!("Permission {:?} required", permission)))
+    }
+}
+
+#[query]
+// 获取 principal 的角色
+pub fn get_roles(principal: Principal) -> Vec<Role> {
+    roles_of(principal).into_iter().collect()
+}
+
+#[query]
+// 获取 principal 的有效权限列表
+pub fn get_effective_permissions(principal: Principal) -> Vec<Permission> {
+    effective_permissions(principal).into_iter().collect()
+}
+
+#[update]
+// 授予角色 (仅超级管理员)
+pub fn grant_role(principal: Principal, role: Role, reason: String) -> Result<()> {
+    let caller = ic_api::caller();
+    check_permission(caller, Permission::SuperAdmin)?;
+
+    validate_param!(principal != Principal::anonymous(), "不能为匿名 principal 授予角色");
+    validate_param!(!reason.trim().is_empty(), "授权原因不能为空");
+
+    ROLE_ASSIGNMENTS.with_borrow_mut(|assignments| {
+        let mut assignment = assignments.get(&principal).unwrap_or_default();
+        assignment.roles.insert(role);
+        assignment.updated_at = ic_api::time();
+        assignment.updated_by = caller.to_string();
+        assignments.insert(principal, assignment);
+    });
+
+    crate::error::audit_log(
+        "grant_role",
+        &caller.to_string(),
+        &format!("principal={}, role={:?}, reason={}", principal, role, reason)
+    );
+
+    Ok(())
+}
+
+#[update]
+// 撤销角色 (仅超级管理员)
+pub fn revoke_role(principal: Principal, role: Role, reason: String) -> Result<()> {
+    let caller = ic_api::caller();
+    check_permission(caller, Permission::SuperAdmin)?;
+
+    validate_param!(!reason.trim().is_empty(), "撤销原因不能为空");
+
+    let revoked = ROLE_ASSIGNMENTS.with_borrow_mut(|assignments| {
+        let mut assignment = match assignments.get(&principal) {
+            Some(assignment) => assignment,
+            None => return false,
+        };
+
+        if !assignment.roles.remove(&role) {
+            return false;
+        }
+
+        if assignment.roles.is_empty() {
+            assignments.remove(&principal);
+        } else {
+            assignment.updated_at = ic_api::time();
+            assignment.updated_by = caller.to_string();
+            assignments.insert(principal, assignment);
+        }
+        true
+    });
+
+    if !revoked {
+        return Err(Error::InvalidArgument(format!("{} 没有被授予角色 {:?}", principal, role)));
+    }
+
+    crate::error::audit_log(
+        "revoke_role",
+        &caller.to_string(),
+        &format!("principal={}, role={:?}, reason={}", principal, role, reason)
+    );
+
+    Ok(())
+}
+
+#[update]
+// 初始化默认权限: 将调用者 (控制者) 登记为超级管理员
+pub fn initialize_default_permissions() -> Result<()> {
+    let caller = ic_api::caller();
+    if !ic_api::is_controller(&caller) {
+        return Err(Error::PermissionDenied("Only controllers can initialize permissions".to_string()));
+    }
+
+    ROLE_ASSIGNMENTS.with_borrow_mut(|assignments| {
+        let mut assignment = assignments.get(&caller).unwrap_or_default();
+        assignment.roles.insert(Role::SuperAdmin);
+        assignment.updated_at = ic_api::time();
+        assignment.updated_by = caller.to_string();
+        assignments.insert(caller, assignment);
+    });
+
+    crate::error::audit_log("initialize_default_permissions", &caller.to_string(), "SuperAdmin granted");
+
+    Ok(())
+}
@@ -0,0 +1,327 @@
+// alert_notifier.rs - 告警推送
+// 这个模块实现告警订阅者注册表，通过单向 canister 调用推送告警，投递失败的告警按指数退避重试
+//
+// 单向调用 (notify) 不等待回复，只能发现同步入队失败 (如输出队列已满)，订阅者处理失败时
+// 本 canister 无法感知。ic-cdk 0.17 没有限时等待的调用接口，而无限等待回复的调用会阻止
+// canister 停止和升级，因此只对入队失败重试: 失败的投递进入重试队列，由 AlertRetry 定时任务
+// 按指数退避重新投递，连续失败 MAX_DELIVERY_ATTEMPTS 次后进入死信列表，
+// 由管理员通过 retry_dead_letters 手动重新投递。
+
+use crate::{Error, Result, ic_api};
+use crate::access_control::Permission;
+use crate::monitoring::{AlertEvent, AlertSeverity};
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::{
+    StableBTreeMap, Storable,
+    memory_manager::MemoryId,
+    storable::Bound,
+};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+// 最大订阅者数量
+const MAX_SUBSCRIBERS: u64 = 20;
+
+// 死信列表上限
+const MAX_DEAD_LETTERS: usize = 1_000;
+
+// 重试队列上限，超出时最早的投递直接进入死信列表
+const MAX_PENDING_RETRIES: usize = 1_000;
+
+// 进入死信列表前的最大投递次数 (含首次投递)
+const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+
+// 重试退避: 首次重试等待 30 秒，之后每次翻倍，最长 1 小时
+const RETRY_BASE_DELAY_NS: u64 = 30 * 1_000_000_000;
+const RETRY_MAX_DELAY_NS: u64 = 60 * 60 * 1_000_000_000;
+
+// 告警订阅
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct AlertSubscription {
+    pub subscriber: Principal,         // 订阅者 canister
+    pub method: String,                // 回调方法，参数为 AlertEvent
+    pub min_severity: AlertSeverity,   // 最低推送级别
+    pub created_by: String,
+    pub created_at: u64,
+}
+
+impl Storable for AlertSubscription {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode AlertSubscription")
+    }
+}
+
+// 投递失败的告警 (重试队列和死信列表共用)
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct DeadLetter {
+    pub alert: AlertEvent,
+    pub subscriber: Principal,
+    pub method: String,
+    pub attempts: u32,
+    pub last_error: String,
+    pub failed_at: u64,
+}
+
+// 重新投递的结果 (手动重投死信或定时重试)
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, Default, PartialEq)]
+pub struct DeadLetterRetryResult {
+    pub delivered: u64,                // 重新投递成功
+    pub failed: u64,                   // 再次失败，留在重试队列或死信列表
+    pub dropped: u64,                  // 订阅已取消，被丢弃
+}
+
+impl DeadLetter {
+    // 下一次重试的时间: 按已失败次数指数退避
+    fn next_retry_at(&self) -> u64 {
+        let exponent = self.attempts.saturating_sub(1).min(16);
+        let delay = RETRY_BASE_DELAY_NS.saturating_mul(1u64 << exponent).min(RETRY_MAX_DELAY_NS);
+        self.failed_at.saturating_add(delay)
+    }
+}
+
+thread_local! {
+    // 订阅者注册表，存放在稳定内存中以便跨升级保留
+    static SUBSCRIPTIONS: RefCell<StableBTreeMap<Principal, AlertSubscription, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(26))),
+        )
+    );
+
+    // 等待定时重试的投递
+    static PENDING_RETRIES: RefCell<VecDeque<DeadLetter>> = RefCell::new(VecDeque::new());
+
+    // 死信列表
+    static DEAD_LETTERS: RefCell<VecDeque<DeadLetter>> = RefCell::new(VecDeque::new());
+}
+
+// 向所有匹配订阅者推送告警，入队失败的进入重试队列
+pub fn enqueue_alert(alert: &AlertEvent) {
+    let subscriptions: Vec<AlertSubscription> = SUBSCRIPTIONS.with_borrow(|subscriptions| {
+        subscriptions.iter()
+            .map(|(_, s)| s)
+            .filter(|s| alert.severity >= s.min_severity)
+            .collect()
+    });
+
+    for subscription in subscriptions {
+        if let Err(e) = deliver(alert, subscription.subscriber, &subscription.method) {
+            push_retry(DeadLetter {
+                alert: alert.clone(),
+                subscriber: subscription.subscriber,
+                method: subscription.method,
+                attempts: 1,
+                last_error: e.to_string(),
+                failed_at: ic_api::time(),
+            });
+        }
+    }
+}
+
+// 单向调用订阅者的回调方法 (只能发现同步入队失败)
+fn deliver(alert: &AlertEvent, subscriber: Principal, method: &str) -> Result<()> {
+    let args = candid::encode_one(alert)
+        .map_err(|e| Error::SystemError(format!("Failed to encode alert: {}", e)))?;
+    ic_api::notify_raw(subscriber, method, &args)
+}
+
+// 写入重试队列，达到最大投递次数时进入死信列表
+fn push_retry(letter: DeadLetter) {
+    if letter.attempts >= MAX_DELIVERY_ATTEMPTS {
+        push_dead_letter(letter);
+        return;
+    }
+
+    let overflow = PENDING_RETRIES.with_borrow_mut(|retries| {
+        retries.push_back(letter);
+        if retries.len() > MAX_PENDING_RETRIES { retries.pop_front() } else { None }
+    });
+
+    if let Some(oldest) = overflow {
+        push_dead_letter(oldest);
+    }
+}
+
+// 定时任务: 重新投递已到退避时间的告警，订阅已取消的被丢弃
+pub fn process_alert_retries() -> DeadLetterRetryResult {
+    let now = ic_api::time();
+    let due: Vec<DeadLetter> = PENDING_RETRIES.with_borrow_mut(|retries| {
+        let (due, waiting): (Vec<_>, Vec<_>) = retries.drain(..).partition(|letter| letter.next_retry_at() <= now);
+        retries.extend(waiting);
+        due
+    });
+
+    let mut result = DeadLetterRetryResult::default();
+    for mut letter in due {
+        let method = SUBSCRIPTIONS.with_borrow(|subscriptions| {
+            subscriptions.get(&letter.subscriber).map(|s| s.method)
+        });
+        let Some(method) = method else {
+            result.dropped += 1;
+            continue;
+        };
+
+        match deliver(&letter.alert, letter.subscriber, &method) {
+            Ok(()) => result.delivered += 1,
+            Err(e) => {
+                letter.method = method;
+                letter.attempts += 1;
+                letter.last_error = e.to_string();
+                letter.failed_at = now;
+                push_retry(letter);
+                result.failed += 1;
+            }
+        }
+    }
+
+    result
+}
+
+// 写入死信列表
+fn push_dead_letter(letter: DeadLetter) {
+    crate::secure_logging::secure_log(
+        crate::secure_logging::SecureLogLevel::Warning,
+        crate::secure_logging::LogCategory::System,
+        format!("Alert {} to {} moved to dead letters", letter.alert.id, letter.subscriber),
+        Some(letter.last_error.clone()),
+        None,
+    );
+
+    DEAD_LETTERS.with_borrow_mut(|letters| {
+        letters.push_back(letter);
+
+        while letters.len() > MAX_DEAD_LETTERS {
+            letters.pop_front();
+        }
+    });
+}
+
+#[update]
+// 注册或更新告警订阅者 (需要系统管理员权限)
+pub fn subscribe_alerts(subscriber: Principal, method: String, min_severity: AlertSeverity) -> Result<()> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, Permission::SystemAdmin)?;
+
+    validate_param!(subscriber != Principal::anonymous(), "订阅者不能是匿名 principal");
+    validate_param!(
+        !method.is_empty() && method.len() <= 64
+            && method.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        format!("无效的回调方法名: {}", method)
+    );
+
+    let is_new = SUBSCRIPTIONS.with_borrow(|subscriptions| !subscriptions.contains_key(&subscriber));
+    let count = SUBSCRIPTIONS.with_borrow(|subscriptions| subscriptions.len());
+    validate_param!(!is_new || count < MAX_SUBSCRIBERS, format!("订阅者数量不能超过 {}", MAX_SUBSCRIBERS));
+
+    SUBSCRIPTIONS.with_borrow_mut(|subscriptions| {
+        subscriptions.insert(subscriber, AlertSubscription {
+            subscriber,
+            method: method.clone(),
+            min_severity: min_severity.clone(),
+            created_by: caller.to_string(),
+            created_at: ic_api::time(),
+        });
+    });
+
+    crate::error::audit_log(
+        "subscribe_alerts",
+        &caller.to_string(),
+        &format!("subscriber={}, method={}, min_severity={:?}", subscriber, method, min_severity)
+    );
+
+    Ok(())
+}
+
+#[update]
+// 取消告警订阅 (系统管理员或订阅者本身)
+pub fn unsubscribe_alerts(subscriber: Principal) -> Result<()> {
+    let caller = ic_api::caller();
+    if caller != subscriber {
+        crate::access_control::check_permission(caller, Permission::SystemAdmin)?;
+    }
+
+    let removed = SUBSCRIPTIONS.with_borrow_mut(|subscriptions| subscriptions.remove(&subscriber));
+    if removed.is_none() {
+        return Err(Error::InvalidArgument(format!("{} 没有订阅告警", subscriber)));
+    }
+
+    crate::error::audit_log("unsubscribe_alerts", &caller.to_string(), &format!("subscriber={}", subscriber));
+
+    Ok(())
+}
+
+#[query]
+// 获取告警订阅者列表
+pub fn get_alert_subscriptions() -> Result<Vec<AlertSubscription>> {
+    crate::access_control::check_permission(ic_api::caller(), Permission::ViewMetrics)?;
+
+    Ok(SUBSCRIPTIONS.with_borrow(|subscriptions| subscriptions.iter().map(|(_, s)| s).collect()))
+}
+
+#[query]
+// 获取等待重试的投递 (最新的在前)
+pub fn get_pending_alert_retries(limit: Option<u64>) -> Result<Vec<DeadLetter>> {
+    crate::access_control::check_permission(ic_api::caller(), Permission::ViewMetrics)?;
+
+    let limit = limit.unwrap_or(100).min(MAX_PENDING_RETRIES as u64) as usize;
+    Ok(PENDING_RETRIES.with_borrow(|retries| retries.iter().rev().take(limit).cloned().collect()))
+}
+
+#[query]
+// 获取死信列表 (最新的在前)
+pub fn get_alert_dead_letters(limit: Option<u64>) -> Result<Vec<DeadLetter>> {
+    crate::access_control::check_permission(ic_api::caller(), Permission::ViewMetrics)?;
+
+    let limit = limit.unwrap_or(100).min(MAX_DEAD_LETTERS as u64) as usize;
+    Ok(DEAD_LETTERS.with_borrow(|letters| letters.iter().rev().take(limit).cloned().collect()))
+}
+
+#[update]
+// 重新投递死信列表中仍有订阅的告警，订阅已取消的死信被丢弃并计入结果
+pub fn retry_dead_letters() -> Result<DeadLetterRetryResult> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, Permission::SystemAdmin)?;
+
+    let letters: Vec<DeadLetter> = DEAD_LETTERS.with_borrow_mut(|letters| letters.drain(..).collect());
+
+    let mut result = DeadLetterRetryResult::default();
+    for mut letter in letters {
+        let method = SUBSCRIPTIONS.with_borrow(|subscriptions| {
+            subscriptions.get(&letter.subscriber).map(|s| s.method)
+        });
+        let Some(method) = method else {
+            result.dropped += 1;
+            continue;
+        };
+
+        match deliver(&letter.alert, letter.subscriber, &method) {
+            Ok(()) => result.delivered += 1,
+            Err(e) => {
+                letter.method = method;
+                letter.attempts += 1;
+                letter.last_error = e.to_string();
+                letter.failed_at = ic_api::time();
+                push_dead_letter(letter);
+                result.failed += 1;
+            }
+        }
+    }
+
+    crate::error::audit_log(
+        "retry_dead_letters",
+        &caller.to_string(),
+        &format!("delivered={}, failed={}, dropped={}", result.delivered, result.failed, result.dropped)
+    );
+
+    Ok(result)
+}
@@ -0,0 +1,246 @@
+// audit.rs - 持久化审计日志
+// 这个模块实现存放在稳定内存中的只追加审计日志，每条记录通过哈希链接到上一条，可分页查询并校验完整性
+
+use crate::{Result, ic_api};
+use crate::access_control::Permission;
+use crate::secure_logging::LogCategory;
+use candid::{CandidType, Deserialize};
+use ic_cdk_macros::query;
+use ic_stable_structures::{
+    StableLog, Storable,
+    memory_manager::MemoryId,
+    storable::Bound,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+// 创世记录的上一条哈希
+const GENESIS_HASH: [u8; 32] = [0u8; 32];
+
+// 单次查询返回的最大条目数
+const MAX_PAGE_SIZE: u64 = 500;
+
+// 单次查询最多扫描的条目数
+const MAX_SCAN_ENTRIES: u64 = 10_000;
+
+// 审计日志条目
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub timestamp: u64,
+    pub category: LogCategory,
+    pub operation: String,
+    pub principal: String,
+    pub details: String,
+    pub prev_hash: String,        // 上一条记录的哈希 (hex)
+    pub hash: String,             // 本条记录的哈希 (hex)
+}
+
+impl AuditEntry {
+    // 计算记录哈希，覆盖上一条哈希和除 hash 外的所有字段
+    pub fn compute_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.prev_hash.as_bytes());
+        hasher.update(self.seq.to_be_bytes());
+        hasher.update(self.timestamp.to_be_bytes());
+        for field in [format!("{:?}", self.category).as_str(), &self.operation, &self.principal, &self.details] {
+            // 长度前缀避免字段边界歧义
+            hasher.update((field.len() as u64).to_be_bytes());
+            hasher.update(field.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+impl Storable for AuditEntry {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode AuditEntry")
+    }
+}
+
+// 审计日志查询条件
+#[derive(CandidType, Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AuditLogQuery {
+    pub from_seq: Option<u64>,           // 从该序号开始 (用于翻页)
+    pub from_time: Option<u64>,          // 起始时间 (含)
+    pub to_time: Option<u64>,            // 结束时间 (含)
+    pub category: Option<LogCategory>,
+    pub principal: Option<String>,
+    pub limit: Option<u64>,
+}
+
+// 审计日志分页结果
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct AuditLogPage {
+    pub entries: Vec<AuditEntry>,
+    pub next_seq: Option<u64>,           // 下一页的起始序号，没有更多记录时为 None
+    pub total_entries: u64,
+}
+
+// 哈希链校验结果
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct AuditChainVerification {
+    pub valid: bool,
+    pub verified_from: u64,
+    pub verified_entries: u64,
+    pub first_invalid_seq: Option<u64>,
+    pub head_hash: String,               // 已校验部分最后一条记录的哈希
+    pub total_entries: u64,
+}
+
+thread_local! {
+    // 审计日志 (索引和数据分别占用一块内存)
+    static AUDIT_LOG: RefCell<StableLog<AuditEntry, crate::Memory, crate::Memory>> = RefCell::new(
+        StableLog::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(15))),
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(16))),
+        ).expect("Failed to initialize audit log")
+    );
+}
+
+// 追加一条审计记录
+pub fn append_audit_entry(category: LogCategory, operation: &str, principal: &str, details: &str) -> u64 {
+    AUDIT_LOG.with_borrow_mut(|log| {
+        let seq = log.len();
+        let prev_hash = match seq.checked_sub(1).and_then(|last| log.get(last)) {
+            Some(last) => last.hash,
+            None => hex::encode(GENESIS_HASH),
+        };
+
+        let mut entry = AuditEntry {
+            seq,
+            timestamp: ic_api::time(),
+            category,
+            operation: operation.to_string(),
+            principal: principal.to_string(),
+            details: details.to_string(),
+            prev_hash,
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+
+        log.append(&entry).expect("Failed to append audit entry")
+    })
+}
+
+// 审计日志条目总数
+pub fn audit_log_len() -> u64 {
+    AUDIT_LOG.with_borrow(|log| log.len())
+}
+
+// 校验一段连续的记录，返回第一条无效记录的序号
+pub(crate) fn verify_chain_segment(prev_hash: &str, expected_seq: u64, entries: &[AuditEntry]) -> Option<u64> {
+    let mut prev_hash = prev_hash.to_string();
+    for (offset, entry) in entries.iter().enumerate() {
+        let seq = expected_seq + offset as u64;
+        if entry.seq != seq || entry.prev_hash != prev_hash || entry.compute_hash() != entry.hash {
+            return Some(seq);
+        }
+        prev_hash = entry.hash.clone();
+    }
+    None
+}
+
+// 第一条时间戳不早于 from_time 的记录序号 (记录按时间追加，可二分查找)
+fn first_seq_at_or_after(from_time: u64) -> u64 {
+    AUDIT_LOG.with_borrow(|log| {
+        let (mut low, mut high) = (0, log.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match log.get(mid) {
+                Some(entry) if entry.timestamp < from_time => low = mid + 1,
+                _ => high = mid,
+            }
+        }
+        low
+    })
+}
+
+#[query]
+// 分页查询审计日志 (需要系统管理员权限)
+pub fn get_audit_log(filter: AuditLogQuery) -> Result<AuditLogPage> {
+    crate::access_control::check_permission(ic_api::caller(), Permission::SystemAdmin)?;
+
+    let limit = filter.limit.unwrap_or(100).clamp(1, MAX_PAGE_SIZE) as usize;
+    let total_entries = audit_log_len();
+    let start = filter.from_seq.unwrap_or(0)
+        .max(filter.from_time.map(first_seq_at_or_after).unwrap_or(0));
+
+    let mut entries = Vec::new();
+    let mut next_seq = None;
+    let mut seq = start;
+
+    AUDIT_LOG.with_borrow(|log| {
+        while seq < total_entries {
+            if entries.len() >= limit || seq - start >= MAX_SCAN_ENTRIES {
+                next_seq = Some(seq);
+                break;
+            }
+
+            let Some(entry) = log.get(seq) else {
+                break;
+            };
+
+            if filter.to_time.is_some_and(|to_time| entry.timestamp > to_time) {
+                break;
+            }
+
+            let matches = filter.category.as_ref().map_or(true, |c| &entry.category == c)
+                && filter.principal.as_ref().map_or(true, |p| &entry.principal == p);
+            if matches {
+                entries.push(entry);
+            }
+            seq += 1;
+        }
+    });
+
+    Ok(AuditLogPage { entries, next_seq, total_entries })
+}
+
+#[query]
+// 校验审计日志哈希链，可分段校验 (from_seq 从 0 开始，limit 默认 MAX_SCAN_ENTRIES)
+pub fn verify_audit_chain(from_seq: Option<u64>, limit: Option<u64>) -> AuditChainVerification {
+    let total_entries = audit_log_len();
+    let from_seq = from_seq.unwrap_or(0).min(total_entries);
+    let limit = limit.unwrap_or(MAX_SCAN_ENTRIES).min(MAX_SCAN_ENTRIES);
+    let end = from_seq.saturating_add(limit).min(total_entries);
+
+    AUDIT_LOG.with_borrow(|log| {
+        let prev_hash = match from_seq.checked_sub(1).and_then(|prev| log.get(prev)) {
+            Some(prev) => prev.hash,
+            None => hex::encode(GENESIS_HASH),
+        };
+
+        let entries: Vec<AuditEntry> = (from_seq..end).filter_map(|seq| log.get(seq)).collect();
+        let first_invalid_seq = if entries.len() as u64 != end - from_seq {
+            Some(from_seq + entries.len() as u64)
+        } else {
+            verify_chain_segment(&prev_hash, from_seq, &entries)
+        };
+
+        let verified_entries = first_invalid_seq.unwrap_or(end) - from_seq;
+        let head_hash = match verified_entries {
+            0 => prev_hash,
+            n => entries[(n - 1) as usize].hash.clone(),
+        };
+
+        AuditChainVerification {
+            valid: first_invalid_seq.is_none(),
+            verified_from: from_seq,
+            verified_entries,
+            first_invalid_seq,
+            head_hash,
+            total_entries,
+        }
+    })
+}
@@ -6,21 +6,46 @@ use candid::{CandidType, Deserialize, Principal};
 use ic_cdk_macros::{query, update};
 use serde::Serialize;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use bitcoin::{Address, Network, PublicKey};
 use secp256k1::{Secp256k1, Message, Signature, ecdsa, PublicKey as Secp256k1PublicKey};
 use sha2::{Sha256, Digest};
-use rand::{RngCore, rngs::OsRng};
 use hmac::{Hmac, Mac};
-use aes_gcm::{Aes256Gcm, Key, Nonce, aead::{Aead, KeyInit}};
+use ic_stable_structures::{StableBTreeMap, StableCell, Storable, memory_manager::MemoryId, storable::Bound};
+use std::borrow::Cow;
 use std::str::FromStr;
+use base64::{Engine, engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64_URL}};
+
+type HmacSha256 = Hmac<Sha256>;
 
 // 会话有效期 (纳秒)
 const SESSION_VALIDITY_PERIOD_NS: u64 = 24 * 60 * 60 * 1_000_000_000; // 24小时
 
+// 会话空闲超时 (纳秒)
+const SESSION_IDLE_TIMEOUT_NS: u64 = 2 * 60 * 60 * 1_000_000_000; // 2小时
+
+// 每个 Principal 同时保留的最大会话数量
+const MAX_SESSIONS_PER_PRINCIPAL: usize = 10;
+
+// 每个 Principal 可关联的最大地址数量
+const MAX_LINKED_ADDRESSES: usize = 20;
+
+// 会话令牌作用域
+const SESSION_SCOPE: &str = "session";
+
+// 认证挑战有效期 (纳秒)
+const AUTH_CHALLENGE_VALIDITY_NS: u64 = 5 * 60 * 1_000_000_000; // 5分钟
+
+// 每个 Principal 同时保留的最大挑战数量
+const MAX_CHALLENGES_PER_PRINCIPAL: usize = 5;
+
+// 所有 Principal 未使用挑战的总数上限
+const MAX_PENDING_CHALLENGES: usize = 10_000;
+
 // 用户会话数据
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct UserSession {
+    pub session_id: String,         // 会话 ID (每个设备一个)
     pub user_address: String,        // 用户比特币地址
     pub principal: Principal,        // 用户 Principal
     pub created_at: u64,            // 创建时间戳 (纳秒)
@@ -28,6 +53,62 @@ pub struct UserSession {
     pub token: String,              // 会话令牌
 }
 
+// 会话概要 (不包含令牌)
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub user_address: String,
+    pub created_at: u64,
+    pub last_accessed: u64,
+    pub expires_at: u64,            // 绝对过期时间 (纳秒)
+    pub idle_expires_at: u64,       // 空闲过期时间 (纳秒)
+}
+
+// 认证挑战
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct AuthChallenge {
+    pub address: String,            // 待认证的比特币地址
+    pub principal: Principal,       // 请求挑战的 Principal
+    pub nonce: String,              // 一次性随机数
+    pub message: String,            // 需要钱包签名的完整消息
+    pub issued_at: u64,             // 签发时间戳 (纳秒)
+    pub expires_at: u64,            // 过期时间戳 (纳秒)
+}
+
+// 会话令牌声明
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct SessionClaims {
+    pub principal: Principal,       // 令牌持有者
+    pub session_id: String,         // 所属会话 ID
+    pub address: String,            // 认证的比特币地址
+    pub scope: String,              // 令牌作用域
+    pub issued_at: u64,             // 签发时间戳 (纳秒)
+    pub expires_at: u64,            // 过期时间戳 (纳秒)
+    pub generation: u64,            // 签发时的令牌代数
+}
+
+// 已验证的关联地址
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct LinkedAddress {
+    pub address: String,            // 比特币地址
+    pub principal: Principal,       // 所属 Principal
+    pub linked_at: u64,             // 关联时间戳 (纳秒)
+}
+
+impl Storable for LinkedAddress {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode LinkedAddress")
+    }
+}
+
 // 认证请求
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct AuthRequest {
@@ -37,11 +118,131 @@ pub struct AuthRequest {
 }
 
 thread_local! {
-    // 用户会话存储 (Principal -> UserSession)
-    static USER_SESSIONS: RefCell<HashMap<Principal, UserSession>> = RefCell::new(HashMap::new());
+    // 用户会话存储 ((Principal, 会话 ID) -> UserSession)
+    static USER_SESSIONS: RefCell<BTreeMap<(Principal, String), UserSession>> = RefCell::new(BTreeMap::new());
+    
+    // 会话计数器，用于生成唯一会话 ID
+    static SESSION_COUNTER: RefCell<u64> = RefCell::new(0);
     
     // 地址到 Principal 的映射
     static ADDRESS_TO_PRINCIPAL: RefCell<HashMap<String, Principal>> = RefCell::new(HashMap::new());
+    
+    // 未使用的认证挑战 (签名消息 -> AuthChallenge)
+    static AUTH_CHALLENGES: RefCell<HashMap<String, AuthChallenge>> = RefCell::new(HashMap::new());
+    
+    // 会话令牌 MAC 密钥 (由 raw_rand 生成，空表示未初始化)
+    static SESSION_SECRET: RefCell<StableCell<Vec<u8>, crate::Memory>> = RefCell::new(
+        StableCell::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(9))),
+            Vec::new(),
+        ).expect("Failed to initialize session secret")
+    );
+    
+    // 每个 principal 的令牌代数，递增即撤销旧令牌
+    static TOKEN_GENERATIONS: RefCell<StableBTreeMap<Principal, u64, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(10))),
+        )
+    );
+    
+    // 地址簿 (地址 -> LinkedAddress)
+    static ADDRESS_BOOK: RefCell<StableBTreeMap<String, LinkedAddress, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(11))),
+        )
+    );
+    
+    // 地址簿索引 ("principal|address" -> ())
+    static ADDRESSES_BY_PRINCIPAL: RefCell<StableBTreeMap<String, (), crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(12))),
+        )
+    );
+}
+
+#[update]
+// 请求认证挑战，返回需要钱包签名的消息
+pub async fn request_auth_challenge(address: String) -> Result<AuthChallenge> {
+    crate::input_validation::validate_bitcoin_address(&address, "request_auth_challenge")?;
+    
+    let caller = ic_api::caller();
+    crate::input_validation::validate_principal(caller, "request_auth_challenge")?;
+    
+    // nonce 使用 raw_rand 生成，不可预测
+    let nonce = hex::encode(ic_api::raw_rand().await?);
+    
+    let now = ic_api::time();
+    let expires_at = now + AUTH_CHALLENGE_VALIDITY_NS;
+    let message = format!(
+        "BollarMoney authentication\nAddress: {}\nPrincipal: {}\nCanister: {}\nNonce: {}\nIssued At: {}\nExpires At: {}",
+        address, caller, ic_api::canister_id(), nonce, now, expires_at
+    );
+    
+    let challenge = AuthChallenge {
+        address,
+        principal: caller,
+        nonce,
+        message: message.clone(),
+        issued_at: now,
+        expires_at,
+    };
+    
+    AUTH_CHALLENGES.with_borrow_mut(|challenges| {
+        // 清理过期挑战
+        challenges.retain(|_, c| c.expires_at > now);
+        
+        // 限制同一 Principal 的未使用挑战数量，移除最旧的
+        let mut own: Vec<(String, u64)> = challenges.iter()
+            .filter(|(_, c)| c.principal == caller)
+            .map(|(m, c)| (m.clone(), c.issued_at))
+            .collect();
+        if own.len() >= MAX_CHALLENGES_PER_PRINCIPAL {
+            own.sort_by_key(|(_, issued_at)| *issued_at);
+            for (m, _) in own.iter().take(own.len() + 1 - MAX_CHALLENGES_PER_PRINCIPAL) {
+                challenges.remove(m);
+            }
+        }
+        
+        // 总数达到上限时拒绝新挑战，防止大量 Principal 耗尽内存
+        if challenges.len() >= MAX_PENDING_CHALLENGES {
+            return Err(Error::SystemError("认证挑战过多，请稍后重试".to_string()));
+        }
+        
+        challenges.insert(message, challenge.clone());
+        Ok(())
+    })?;
+    
+    Ok(challenge)
+}
+
+// principal 未使用的认证挑战数量
+#[cfg(test)]
+pub(crate) fn pending_challenges_of(principal: &Principal) -> usize {
+    let now = ic_api::time();
+    AUTH_CHALLENGES.with_borrow(|challenges| {
+        challenges.values()
+            .filter(|c| c.principal == *principal && c.expires_at > now)
+            .count()
+    })
+}
+
+// 消费与签名消息匹配的挑战，每个挑战只能使用一次
+// 先校验挑战属于调用者和该地址再删除，其他调用者无法销毁别人的挑战
+fn consume_auth_challenge(address: &str, message: &str, caller: Principal) -> Result<AuthChallenge> {
+    let challenge = AUTH_CHALLENGES.with_borrow(|challenges| challenges.get(message).cloned())
+        .ok_or(Error::AuthenticationFailed)?;
+    
+    if challenge.principal != caller || challenge.address != address {
+        return Err(Error::AuthenticationFailed);
+    }
+    
+    AUTH_CHALLENGES.with_borrow_mut(|challenges| challenges.remove(message));
+    
+    if ic_api::time() > challenge.expires_at {
+        return Err(Error::AuthenticationFailed);
+    }
+    
+    Ok(challenge)
 }
 
 #[update]
@@ -58,41 +259,22 @@ pub fn authenticate(address: String, signature: String, message: String) -> Resu
             // 获取调用者 Principal
             let caller = ic_api::caller();
             
-            // 验证签名 (简化实现，实际应该验证比特币签名)
-            if !verify_bitcoin_signature(&address, &signature, &message) {
-                return Err(Error::AuthenticationFailed);
-            }
+            // 检查黑名单
+            crate::blocklist::ensure_not_blocked(caller, Some(&address), "authenticate")?;
             
-            // 生成会话令牌
-            let token = generate_session_token(&address, caller);
+            // 签名消息必须是签发给调用者和该地址的未使用挑战，且签名有效
+            verify_address_ownership(&address, &signature, &message, caller)?;
             
-            // 创建用户会话
-            let now = ic_api::time();
-            let session = UserSession {
-                user_address: address.clone(),
-                principal: caller,
-                created_at: now,
-                last_accessed: now,
-                token: token.clone(),
-            };
-            
-            // 存储会话
-            USER_SESSIONS.with_borrow_mut(|sessions| {
-                sessions.insert(caller, session);
-            });
-            
-            // 存储地址映射
-            ADDRESS_TO_PRINCIPAL.with_borrow_mut(|mapping| {
-                mapping.insert(address.clone(), caller);
-            });
+            // 为该设备创建新会话，不影响其他设备的会话
+            let session = create_session(&address, caller)?;
             
             // 记录认证成功
-            ic_cdk::println!("User authenticated: address={}, principal={}", address, caller);
+            ic_cdk::println!("User authenticated: address={}, principal={}, session={}", address, caller, session.session_id);
             
             Ok(AuthResult {
                 success: true,
                 message: "认证成功".to_string(),
-                token: Some(token),
+                token: Some(session.token),
             })
         },
         LogLevel::Error,
@@ -101,59 +283,33 @@ pub fn authenticate(address: String, signature: String, message: String) -> Resu
 }
 
 #[query]
-// 获取当前用户会话
-pub fn get_current_session() -> Result<UserSession> {
+// 获取调用者的指定会话
+pub fn get_current_session(session_id: String) -> Result<UserSession> {
     // 使用 catch_and_log 包装操作
     catch_and_log(
-        || {
-            let caller = ic_api::caller();
-            
-            // 获取用户会话
-            let session = USER_SESSIONS.with_borrow(|sessions| {
-                sessions.get(&caller).cloned()
-            }).ok_or(Error::AuthenticationFailed)?;
-            
-            // 检查会话是否过期
-            let now = ic_api::time();
-            if now - session.created_at > SESSION_VALIDITY_PERIOD_NS {
-                return Err(Error::AuthenticationFailed);
-            }
-            
-            Ok(session)
-        },
+        || active_session(&ic_api::caller(), &session_id),
         LogLevel::Debug,
         "get_current_session: 获取当前用户会话失败"
     )
 }
 
 #[update]
-// 刷新用户会话
-pub fn refresh_session() -> Result<AuthResult> {
+// 刷新调用者的指定会话并签发新令牌
+pub fn refresh_session(session_id: String) -> Result<AuthResult> {
     // 使用 catch_and_log 包装操作
     catch_and_log(
         || {
             let caller = ic_api::caller();
             
-            // 获取现有会话
-            let mut session = USER_SESSIONS.with_borrow(|sessions| {
-                sessions.get(&caller).cloned()
-            }).ok_or(Error::AuthenticationFailed)?;
-            
-            // 检查会话是否过期
-            let now = ic_api::time();
-            if now - session.created_at > SESSION_VALIDITY_PERIOD_NS {
-                return Err(Error::AuthenticationFailed);
-            }
-            
             // 更新最后访问时间
-            session.last_accessed = now;
+            let mut session = touch_session_record(&caller, &session_id)?;
             
             // 生成新的令牌
-            session.token = generate_session_token(&session.user_address, caller);
+            session.token = generate_session_token(&session.user_address, caller, &session.session_id)?;
             
             // 更新会话
             USER_SESSIONS.with_borrow_mut(|sessions| {
-                sessions.insert(caller, session.clone());
+                sessions.insert((caller, session.session_id.clone()), session.clone());
             });
             
             Ok(AuthResult {
@@ -168,33 +324,31 @@ pub fn refresh_session() -> Result<AuthResult> {
 }
 
 #[update]
-// 用户登出
+// 记录调用者指定会话的活动，延长空闲超时
+pub fn touch_session(session_id: String) -> Result<SessionInfo> {
+    let caller = ic_api::caller();
+    touch_session_record(&caller, &session_id).map(session_info)
+}
+
+#[update]
+// 用户登出 (结束调用者的所有会话)
 pub fn logout() -> Result<bool> {
     // 使用 catch_and_log 包装操作
     catch_and_log(
         || {
             let caller = ic_api::caller();
             
-            // 获取用户会话以获取地址
-            let session = USER_SESSIONS.with_borrow(|sessions| {
-                sessions.get(&caller).cloned()
-            });
+            // 删除会话和地址映射
+            let removed = remove_sessions_of(&caller);
             
-            // 删除会话
-            let removed = USER_SESSIONS.with_borrow_mut(|sessions| {
-                sessions.remove(&caller).is_some()
-            });
+            // 撤销已签发的令牌
+            bump_token_generation(&caller);
             
-            // 删除地址映射
-            if let Some(session) = session {
-                ADDRESS_TO_PRINCIPAL.with_borrow_mut(|mapping| {
-                    mapping.remove(&session.user_address);
-                });
-                
-                ic_cdk::println!("User logged out: address={}, principal={}", session.user_address, caller);
+            if !removed.is_empty() {
+                ic_cdk::println!("User logged out: principal={}, sessions={}", caller, removed.len());
             }
             
-            Ok(removed)
+            Ok(!removed.is_empty())
         },
         LogLevel::Debug,
         "logout: 用户登出失败"
@@ -207,24 +361,178 @@ pub fn is_authenticated() -> bool {
     let caller = ic_api::caller();
     
     // 检查是否有有效会话
-    USER_SESSIONS.with_borrow(|sessions| {
-        if let Some(session) = sessions.get(&caller) {
-            let now = ic_api::time();
-            now - session.created_at <= SESSION_VALIDITY_PERIOD_NS
-        } else {
-            false
-        }
-    })
+    !active_sessions_of(&caller).is_empty()
 }
 
 #[query]
-// 根据地址获取 Principal
+// 列出调用者的所有有效会话
+pub fn list_my_sessions() -> Vec<SessionInfo> {
+    let caller = ic_api::caller();
+    
+    active_sessions_of(&caller).into_iter()
+        .map(session_info)
+        .collect()
+}
+
+// 会话概要
+fn session_info(session: UserSession) -> SessionInfo {
+    SessionInfo {
+        expires_at: session.created_at + SESSION_VALIDITY_PERIOD_NS,
+        idle_expires_at: session.last_accessed + SESSION_IDLE_TIMEOUT_NS,
+        session_id: session.session_id,
+        user_address: session.user_address,
+        created_at: session.created_at,
+        last_accessed: session.last_accessed,
+    }
+}
+
+#[update]
+// 撤销调用者的指定会话 (例如丢失的设备)
+pub fn revoke_session(session_id: String) -> Result<()> {
+    let caller = ic_api::caller();
+    
+    let session = remove_session(&caller, &session_id)
+        .ok_or(Error::InvalidArgument(format!("会话 {} 不存在", session_id)))?;
+    
+    crate::error::audit_log(
+        "revoke_session",
+        &caller.to_string(),
+        &format!("session_id={}, address={}", session_id, session.user_address)
+    );
+    
+    Ok(())
+}
+
+#[update]
+// 撤销调用者的所有会话并使已签发的令牌失效
+pub fn revoke_all_sessions() -> Result<u64> {
+    let caller = ic_api::caller();
+    
+    let removed = remove_sessions_of(&caller).len() as u64;
+    let generation = bump_token_generation(&caller);
+    
+    crate::error::audit_log(
+        "revoke_all_sessions",
+        &caller.to_string(),
+        &format!("sessions={}, generation={}", removed, generation)
+    );
+    
+    Ok(removed)
+}
+
+#[query]
+// 根据地址获取 Principal (优先查询地址簿，其次查询会话地址)
 pub fn get_principal_by_address(address: String) -> Option<Principal> {
+    if let Some(linked) = ADDRESS_BOOK.with_borrow(|book| book.get(&address)) {
+        return Some(linked.principal);
+    }
+    
     ADDRESS_TO_PRINCIPAL.with_borrow(|mapping| {
         mapping.get(&address).cloned()
     })
 }
 
+#[update]
+// 将比特币地址关联到调用者，需要对该地址的认证挑战签名
+pub fn link_address(address: String, signature: String, message: String) -> Result<LinkedAddress> {
+    let caller = ic_api::caller();
+    crate::input_validation::validate_principal(caller, "link_address")?;
+    crate::input_validation::validate_bitcoin_address(&address, "link_address")?;
+    crate::blocklist::ensure_not_blocked(caller, Some(&address), "link_address")?;
+    
+    verify_address_ownership(&address, &signature, &message, caller)?;
+    
+    let linked = link_verified_address(caller, &address)?;
+    
+    crate::error::audit_log("link_address", &caller.to_string(), &format!("address={}", address));
+    
+    Ok(linked)
+}
+
+#[update]
+// 解除调用者与比特币地址的关联，需要对该地址的认证挑战签名
+pub fn unlink_address(address: String, signature: String, message: String) -> Result<()> {
+    let caller = ic_api::caller();
+    
+    let linked = ADDRESS_BOOK.with_borrow(|book| book.get(&address))
+        .filter(|linked| linked.principal == caller)
+        .ok_or(Error::InvalidArgument(format!("地址 {} 未关联到调用者", address)))?;
+    
+    verify_address_ownership(&address, &signature, &message, caller)?;
+    
+    ADDRESS_BOOK.with_borrow_mut(|book| book.remove(&address));
+    ADDRESSES_BY_PRINCIPAL.with_borrow_mut(|index| index.remove(&address_index_key(&linked.principal, &address)));
+    
+    crate::error::audit_log("unlink_address", &caller.to_string(), &format!("address={}", address));
+    
+    Ok(())
+}
+
+#[query]
+// 获取 principal 关联的所有地址
+pub fn get_linked_addresses(principal: Principal) -> Vec<LinkedAddress> {
+    let prefix = address_index_key(&principal, "");
+    let addresses: Vec<String> = ADDRESSES_BY_PRINCIPAL.with_borrow(|index| {
+        index.range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, _)| key[prefix.len()..].to_string())
+            .collect()
+    });
+    
+    ADDRESS_BOOK.with_borrow(|book| {
+        addresses.iter().filter_map(|address| book.get(address)).collect()
+    })
+}
+
+// 将 principal 文本或关联地址解析为头寸所有者
+pub(crate) fn resolve_owner(user: &str) -> String {
+    ADDRESS_BOOK.with_borrow(|book| book.get(&user.to_string()))
+        .map(|linked| linked.principal.to_string())
+        .unwrap_or_else(|| user.to_string())
+}
+
+// 验证调用者对地址的控制权 (消费挑战并验证签名)
+fn verify_address_ownership(address: &str, signature: &str, message: &str, caller: Principal) -> Result<()> {
+    consume_auth_challenge(address, message, caller)?;
+    
+    if !verify_bitcoin_signature(address, signature, message) {
+        return Err(Error::AuthenticationFailed);
+    }
+    
+    Ok(())
+}
+
+// 将已验证的地址写入地址簿
+pub(crate) fn link_verified_address(principal: Principal, address: &str) -> Result<LinkedAddress> {
+    if let Some(existing) = ADDRESS_BOOK.with_borrow(|book| book.get(&address.to_string())) {
+        if existing.principal == principal {
+            return Ok(existing);
+        }
+        return Err(Error::InvalidState(format!("地址 {} 已关联到其他 principal", address)));
+    }
+    
+    validate_param!(
+        get_linked_addresses(principal).len() < MAX_LINKED_ADDRESSES,
+        format!("每个 principal 最多关联 {} 个地址", MAX_LINKED_ADDRESSES)
+    );
+    
+    let linked = LinkedAddress {
+        address: address.to_string(),
+        principal,
+        linked_at: ic_api::time(),
+    };
+    
+    ADDRESS_BOOK.with_borrow_mut(|book| book.insert(address.to_string(), linked.clone()));
+    ADDRESSES_BY_PRINCIPAL.with_borrow_mut(|index| index.insert(address_index_key(&principal, address), ()));
+    
+    Ok(linked)
+}
+
+// 地址簿索引键
+fn address_index_key(principal: &Principal, address: &str) -> String {
+    format!("{}|{}", principal, address)
+}
+
 #[query]
 // 获取所有活跃会话 (仅管理员)
 pub fn get_active_sessions() -> Result<Vec<UserSession>> {
@@ -242,7 +550,7 @@ pub fn get_active_sessions() -> Result<Vec<UserSession>> {
             // 获取所有活跃会话
             let active_sessions = USER_SESSIONS.with_borrow(|sessions| {
                 sessions.values()
-                    .filter(|session| now - session.created_at <= SESSION_VALIDITY_PERIOD_NS)
+                    .filter(|session| is_session_active(session, now))
                     .cloned()
                     .collect()
             });
@@ -254,7 +562,8 @@ pub fn get_active_sessions() -> Result<Vec<UserSession>> {
     )
 }
 
-// 验证比特币签名 (完整实现)
+// 验证比特币签名，根据地址类型选择验证方式
+// P2WPKH 和 P2TR 地址使用 BIP-322，P2PKH 地址使用传统的可恢复签名格式
 fn verify_bitcoin_signature(address: &str, signature: &str, message: &str) -> bool {
     // 验证参数
     if address.is_empty() || signature.is_empty() || message.is_empty() {
@@ -267,11 +576,19 @@ fn verify_bitcoin_signature(address: &str, signature: &str, message: &str) -> bo
         Err(_) => return false,
     };
     
-    // 获取地址对应的脚本公钥
-    let script_pubkey = btc_address.script_pubkey();
-    
+    match btc_address.address_type() {
+        Some(bitcoin::AddressType::P2wpkh) | Some(bitcoin::AddressType::P2tr) => {
+            crate::bip322::verify(&btc_address.script_pubkey(), message, signature)
+        }
+        Some(bitcoin::AddressType::P2pkh) => verify_legacy_signature(&btc_address, signature, message),
+        _ => false, // 暂不支持 P2SH / P2WSH
+    }
+}
+
+// 验证传统比特币消息签名 (65 字节可恢复签名)
+fn verify_legacy_signature(btc_address: &Address, signature: &str, message: &str) -> bool {
     // 解码 base64 签名
-    let signature_bytes = match base64::decode(signature) {
+    let signature_bytes = match BASE64.decode(signature) {
         Ok(bytes) => bytes,
         Err(_) => return false,
     };
@@ -330,7 +647,7 @@ fn verify_bitcoin_signature(address: &str, signature: &str, message: &str) -> bo
     };
     
     // 验证地址是否匹配
-    verify_address_matches_pubkey(&btc_address, &bitcoin_pubkey)
+    verify_address_matches_pubkey(btc_address, &bitcoin_pubkey)
 }
 
 // 创建比特币消息哈希
@@ -351,165 +668,327 @@ fn create_bitcoin_message_hash(message: &str) -> [u8; 32] {
     second_hasher.finalize().into()
 }
 
-// 验证地址是否与公钥匹配
+// 验证 P2PKH 地址是否与公钥匹配
 fn verify_address_matches_pubkey(address: &Address, pubkey: &PublicKey) -> bool {
-    // 根据地址类型验证
-    match address.address_type() {
-        Some(bitcoin::AddressType::P2pkh) => {
-            // P2PKH 地址验证
-            let pubkey_hash = bitcoin::hashes::hash160::Hash::hash(&pubkey.to_bytes());
-            address.script_pubkey() == bitcoin::Script::new_p2pkh(&pubkey_hash.into())
-        }
-        Some(bitcoin::AddressType::P2sh) => {
-            // P2SH 地址验证 (更复杂，这里简化处理)
-            false // 暂不支持 P2SH
-        }
-        Some(bitcoin::AddressType::P2wpkh) => {
-            // P2WPKH 地址验证
-            let pubkey_hash = bitcoin::hashes::hash160::Hash::hash(&pubkey.to_bytes());
-            address.script_pubkey() == bitcoin::Script::new_v0_p2wpkh(&pubkey_hash.into())
-        }
-        Some(bitcoin::AddressType::P2wsh) => {
-            // P2WSH 地址验证 (更复杂，这里简化处理)
-            false // 暂不支持 P2WSH
-        }
-        Some(bitcoin::AddressType::P2tr) => {
-            // Taproot 地址验证 (更复杂，这里简化处理)
-            false // 暂不支持 Taproot
-        }
-        _ => false,
+    address.script_pubkey() == bitcoin::ScriptBuf::new_p2pkh(&pubkey.pubkey_hash())
+}
+
+// 签发会话令牌
+// 令牌格式: base64url(CBOR 编码的 SessionClaims) "." base64url(HMAC-SHA256(canister 密钥, claims))
+pub(crate) fn generate_session_token(address: &str, principal: Principal, session_id: &str) -> Result<String> {
+    let secret = session_secret().ok_or(Error::InvalidState("会话密钥尚未初始化".to_string()))?;
+    
+    let now = ic_api::time();
+    let claims = SessionClaims {
+        principal,
+        session_id: session_id.to_string(),
+        address: address.to_string(),
+        scope: SESSION_SCOPE.to_string(),
+        issued_at: now,
+        expires_at: now + SESSION_VALIDITY_PERIOD_NS,
+        generation: token_generation(&principal),
+    };
+    
+    let mut payload = vec![];
+    ciborium::ser::into_writer(&claims, &mut payload)
+        .map_err(|e| Error::SystemError(format!("会话令牌编码失败: {}", e)))?;
+    
+    let mut mac = HmacSha256::new_from_slice(&secret)
+        .map_err(|e| Error::SystemError(e.to_string()))?;
+    mac.update(&payload);
+    let tag = mac.finalize().into_bytes();
+    
+    Ok(format!("{}.{}", BASE64_URL.encode(&payload), BASE64_URL.encode(tag)))
+}
+
+// 验证会话令牌 (O(1): 一次 HMAC 计算加一次代数查询)
+pub(crate) fn verify_session_token(token: &str, scope: &str) -> Result<SessionClaims> {
+    let secret = session_secret().ok_or(Error::AuthenticationFailed)?;
+    
+    let (payload_part, tag_part) = token.split_once('.').ok_or(Error::AuthenticationFailed)?;
+    let payload = BASE64_URL.decode(payload_part).map_err(|_| Error::AuthenticationFailed)?;
+    let tag = BASE64_URL.decode(tag_part).map_err(|_| Error::AuthenticationFailed)?;
+    
+    // 常数时间比较 MAC
+    let mut mac = HmacSha256::new_from_slice(&secret)
+        .map_err(|e| Error::SystemError(e.to_string()))?;
+    mac.update(&payload);
+    mac.verify_slice(&tag).map_err(|_| Error::AuthenticationFailed)?;
+    
+    let claims: SessionClaims = ciborium::de::from_reader(payload.as_slice())
+        .map_err(|_| Error::AuthenticationFailed)?;
+    
+    if claims.scope != scope || ic_api::time() >= claims.expires_at {
+        return Err(Error::AuthenticationFailed);
+    }
+    
+    // 代数计数器递增后，之前签发的令牌全部失效
+    if claims.generation != token_generation(&claims.principal) {
+        return Err(Error::AuthenticationFailed);
     }
+    
+    Ok(claims)
 }
 
-// 生成安全的会话令牌
-fn generate_session_token(address: &str, principal: Principal) -> String {
-    // 生成随机盐
-    let mut salt = [0u8; 32];
-    OsRng.fill_bytes(&mut salt);
+#[query]
+// 验证会话令牌并返回其声明，令牌所属会话必须仍然有效
+pub fn validate_session_token(token: String) -> Result<SessionClaims> {
+    let claims = verify_session_token(&token, SESSION_SCOPE)?;
+    
+    let now = ic_api::time();
+    let active = USER_SESSIONS.with_borrow(|sessions| {
+        sessions.get(&(claims.principal, claims.session_id.clone()))
+            .map(|session| session.token == token && is_session_active(session, now))
+            .unwrap_or(false)
+    });
+    
+    if !active {
+        return Err(Error::AuthenticationFailed);
+    }
     
-    // 创建 HMAC 密钥
-    let timestamp = ic_api::time();
-    let key_material = format!("{}:{}:{}", address, principal.to_text(), timestamp);
+    Ok(claims)
+}
+
+#[update]
+// 撤销 principal 已签发的所有会话令牌 (本人或系统管理员)
+pub fn revoke_session_tokens(principal: Principal) -> Result<u64> {
+    let caller = ic_api::caller();
+    if caller != principal {
+        crate::access_control::check_permission(caller, crate::access_control::Permission::SystemAdmin)?;
+    }
     
-    // 使用 PBKDF2 派生密钥
-    let mut derived_key = [0u8; 32];
-    pbkdf2::pbkdf2::<Hmac<Sha256>>(key_material.as_bytes(), &salt, 10000, &mut derived_key);
+    let generation = bump_token_generation(&principal);
+    remove_sessions_of(&principal);
     
-    // 生成随机 nonce
-    let mut nonce_bytes = [0u8; 12];
-    OsRng.fill_bytes(&mut nonce_bytes);
+    crate::error::audit_log(
+        "revoke_session_tokens",
+        &caller.to_string(),
+        &format!("principal={}, generation={}", principal, generation)
+    );
     
-    // 创建要加密的数据
-    let payload = format!("{}:{}:{}", address, principal.to_text(), timestamp);
+    Ok(generation)
+}
+
+// 获取会话密钥，未初始化时返回 None
+fn session_secret() -> Option<Vec<u8>> {
+    SESSION_SECRET.with_borrow(|secret| {
+        let secret = secret.get();
+        if secret.is_empty() { None } else { Some(secret.clone()) }
+    })
+}
+
+// 设置会话密钥
+pub(crate) fn set_session_secret(secret: Vec<u8>) -> Result<()> {
+    validate_param!(secret.len() >= 32, "会话密钥长度至少为 32 字节");
+    SESSION_SECRET.with_borrow_mut(|cell| {
+        cell.set(secret).map(|_| ()).map_err(|e| Error::SystemError(format!("{:?}", e)))
+    })
+}
+
+// 确保会话密钥已使用 raw_rand 初始化
+pub(crate) async fn ensure_session_secret() -> Result<()> {
+    if session_secret().is_some() {
+        return Ok(());
+    }
     
-    // 使用 AES-GCM 加密
-    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&derived_key));
-    let nonce = Nonce::from_slice(&nonce_bytes);
+    let random_bytes = ic_api::raw_rand().await?;
     
-    match cipher.encrypt(nonce, payload.as_bytes()) {
-        Ok(ciphertext) => {
-            // 组合 salt + nonce + ciphertext
-            let mut token_bytes = Vec::new();
-            token_bytes.extend_from_slice(&salt);
-            token_bytes.extend_from_slice(&nonce_bytes);
-            token_bytes.extend_from_slice(&ciphertext);
-            
-            // 返回 base64 编码的令牌
-            base64::encode(&token_bytes)
-        }
-        Err(_) => {
-            // 如果加密失败，回退到简单方法（不推荐）
-            ic_cdk::println!("Warning: Token encryption failed, using fallback method");
-            let mut random_bytes = [0u8; 32];
-            OsRng.fill_bytes(&mut random_bytes);
-            format!("{}:{}", hex::encode(&random_bytes), timestamp)
-        }
+    // 等待期间可能已被其他调用初始化
+    if session_secret().is_none() {
+        set_session_secret(random_bytes)?;
+        ic_cdk::println!("Session secret initialized");
     }
+    
+    Ok(())
 }
 
-// 验证会话令牌
-fn verify_session_token(token: &str, address: &str, principal: Principal) -> bool {
-    // 解码 base64 令牌
-    let token_bytes = match base64::decode(token) {
-        Ok(bytes) => bytes,
-        Err(_) => return false,
+#[update]
+// 初始化会话密钥 (仅控制者)
+pub async fn initialize_session_secret() -> Result<()> {
+    let caller = ic_api::caller();
+    if !ic_api::is_controller(&caller) {
+        return Err(Error::PermissionDenied("Only controllers can initialize the session secret".to_string()));
+    }
+    
+    ensure_session_secret().await
+}
+
+// 获取 principal 当前的令牌代数
+fn token_generation(principal: &Principal) -> u64 {
+    TOKEN_GENERATIONS.with_borrow(|generations| generations.get(principal).unwrap_or(0))
+}
+
+// 递增 principal 的令牌代数，返回新代数
+fn bump_token_generation(principal: &Principal) -> u64 {
+    TOKEN_GENERATIONS.with_borrow_mut(|generations| {
+        let generation = generations.get(principal).unwrap_or(0) + 1;
+        generations.insert(*principal, generation);
+        generation
+    })
+}
+
+// 会话是否仍然有效 (未超过绝对有效期且未空闲超时)
+fn is_session_active(session: &UserSession, now: u64) -> bool {
+    now.saturating_sub(session.created_at) <= SESSION_VALIDITY_PERIOD_NS
+        && now.saturating_sub(session.last_accessed) <= SESSION_IDLE_TIMEOUT_NS
+}
+
+// 获取 principal 的有效会话，按最后访问时间从新到旧排序
+fn active_sessions_of(principal: &Principal) -> Vec<UserSession> {
+    let now = ic_api::time();
+    
+    let mut active: Vec<UserSession> = USER_SESSIONS.with_borrow(|sessions| {
+        sessions.range((*principal, String::new())..)
+            .take_while(|((owner, _), _)| owner == principal)
+            .map(|(_, session)| session)
+            .filter(|session| is_session_active(session, now))
+            .filter(|session| verify_session_token(&session.token, SESSION_SCOPE).is_ok())
+            .cloned()
+            .collect()
+    });
+    
+    active.sort_by(|a, b| b.last_accessed.cmp(&a.last_accessed));
+    active
+}
+
+// 获取 principal 的指定有效会话
+fn active_session(principal: &Principal, session_id: &str) -> Result<UserSession> {
+    let now = ic_api::time();
+    USER_SESSIONS.with_borrow(|sessions| sessions.get(&(*principal, session_id.to_string())).cloned())
+        .filter(|session| is_session_active(session, now))
+        .filter(|session| verify_session_token(&session.token, SESSION_SCOPE).is_ok())
+        .ok_or(Error::AuthenticationFailed)
+}
+
+// 更新指定会话的最后访问时间，返回更新后的会话
+fn touch_session_record(principal: &Principal, session_id: &str) -> Result<UserSession> {
+    let mut session = active_session(principal, session_id)?;
+    session.last_accessed = ic_api::time();
+    USER_SESSIONS.with_borrow_mut(|sessions| {
+        sessions.insert((*principal, session.session_id.clone()), session.clone());
+    });
+    Ok(session)
+}
+
+// 生成唯一的会话 ID
+fn next_session_id(principal: &Principal, now: u64) -> String {
+    let counter = SESSION_COUNTER.with_borrow_mut(|counter| {
+        *counter += 1;
+        *counter
+    });
+    
+    let mut hasher = Sha256::new();
+    hasher.update(counter.to_be_bytes());
+    hasher.update(now.to_be_bytes());
+    hasher.update(principal.as_slice());
+    hex::encode(&hasher.finalize()[..16])
+}
+
+// 为 principal 创建新会话并签发令牌
+pub(crate) fn create_session(address: &str, principal: Principal) -> Result<UserSession> {
+    let now = ic_api::time();
+    let session_id = next_session_id(&principal, now);
+    let token = generate_session_token(address, principal, &session_id)?;
+    
+    let session = UserSession {
+        session_id: session_id.clone(),
+        user_address: address.to_string(),
+        principal,
+        created_at: now,
+        last_accessed: now,
+        token,
     };
     
-    // 检查令牌长度 (至少 salt(32) + nonce(12) + min_ciphertext(16))
-    if token_bytes.len() < 60 {
-        return false;
+    // 清理失效会话，超过上限时移除最久未访问的会话
+    let mut own: Vec<(String, u64, bool)> = USER_SESSIONS.with_borrow(|sessions| {
+        sessions.range((principal, String::new())..)
+            .take_while(|((owner, _), _)| *owner == principal)
+            .map(|((_, id), s)| (id.clone(), s.last_accessed, is_session_active(s, now)))
+            .collect()
+    });
+    own.sort_by_key(|(_, last_accessed, _)| *last_accessed);
+    
+    let mut remaining = own.len();
+    for (id, _, active) in own {
+        if !active || remaining >= MAX_SESSIONS_PER_PRINCIPAL {
+            remove_session(&principal, &id);
+            remaining -= 1;
+        }
     }
     
-    // 提取组件
-    let salt = &token_bytes[0..32];
-    let nonce_bytes = &token_bytes[32..44];
-    let ciphertext = &token_bytes[44..];
+    USER_SESSIONS.with_borrow_mut(|sessions| {
+        sessions.insert((principal, session_id), session.clone());
+    });
     
-    // 重建密钥 (需要时间戳，这里简化处理)
-    // 在实际实现中，应该在令牌中包含时间戳或使用其他方法
-    let current_time = ic_api::time();
-    let time_window = 24 * 60 * 60 * 1_000_000_000; // 24小时窗口
+    // 存储地址映射
+    ADDRESS_TO_PRINCIPAL.with_borrow_mut(|mapping| {
+        mapping.insert(address.to_string(), principal);
+    });
     
-    // 尝试多个时间戳 (简化的时间窗口验证)
-    for offset in 0..24 {
-        let test_timestamp = current_time - (offset * 60 * 60 * 1_000_000_000); // 每小时一个测试点
-        let key_material = format!("{}:{}:{}", address, principal.to_text(), test_timestamp);
-        
-        let mut derived_key = [0u8; 32];
-        pbkdf2::pbkdf2::<Hmac<Sha256>>(key_material.as_bytes(), salt, 10000, &mut derived_key);
-        
-        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&derived_key));
-        let nonce = Nonce::from_slice(nonce_bytes);
-        
-        if let Ok(decrypted) = cipher.decrypt(nonce, ciphertext) {
-            if let Ok(payload) = String::from_utf8(decrypted) {
-                let expected = format!("{}:{}:{}", address, principal.to_text(), test_timestamp);
-                if payload == expected && (current_time - test_timestamp) <= time_window {
-                    return true;
-                }
+    Ok(session)
+}
+
+// 删除 principal 的单个会话，地址不再被任何会话使用时删除地址映射
+fn remove_session(principal: &Principal, session_id: &str) -> Option<UserSession> {
+    let session = USER_SESSIONS.with_borrow_mut(|sessions| {
+        sessions.remove(&(*principal, session_id.to_string()))
+    })?;
+    
+    let address_in_use = USER_SESSIONS.with_borrow(|sessions| {
+        sessions.range((*principal, String::new())..)
+            .take_while(|((owner, _), _)| owner == principal)
+            .any(|(_, s)| s.user_address == session.user_address)
+    });
+    
+    if !address_in_use {
+        ADDRESS_TO_PRINCIPAL.with_borrow_mut(|mapping| {
+            if mapping.get(&session.user_address) == Some(principal) {
+                mapping.remove(&session.user_address);
             }
-        }
+        });
     }
     
-    false
+    Some(session)
 }
 
-// 清理过期会话的定时任务
-#[update]
-pub fn cleanup_expired_sessions() -> u64 {
+// 删除 principal 的所有会话
+fn remove_sessions_of(principal: &Principal) -> Vec<UserSession> {
+    let session_ids: Vec<String> = USER_SESSIONS.with_borrow(|sessions| {
+        sessions.range((*principal, String::new())..)
+            .take_while(|((owner, _), _)| owner == principal)
+            .map(|((_, id), _)| id.clone())
+            .collect()
+    });
+    
+    session_ids.iter()
+        .filter_map(|id| remove_session(principal, id))
+        .collect()
+}
+
+// 清理过期会话 (仅由调度器的 SessionCleanup 任务调用)
+pub(crate) fn cleanup_expired_sessions() -> u64 {
     let now = ic_api::time();
     let mut removed_count = 0;
     
-    // 收集过期的会话
-    let expired_principals: Vec<Principal> = USER_SESSIONS.with_borrow(|sessions| {
+    // 收集过期或空闲超时的会话
+    let expired_sessions: Vec<(Principal, String)> = USER_SESSIONS.with_borrow(|sessions| {
         sessions.iter()
-            .filter(|(_, session)| now - session.created_at > SESSION_VALIDITY_PERIOD_NS)
-            .map(|(principal, _)| *principal)
+            .filter(|(_, session)| !is_session_active(session, now))
+            .map(|(key, _)| key.clone())
             .collect()
     });
     
     // 删除过期会话
-    for principal in expired_principals {
-        // 获取会话以获取地址
-        let session = USER_SESSIONS.with_borrow(|sessions| {
-            sessions.get(&principal).cloned()
-        });
-        
-        // 删除会话
-        USER_SESSIONS.with_borrow_mut(|sessions| {
-            sessions.remove(&principal);
-        });
-        
-        // 删除地址映射
-        if let Some(session) = session {
-            ADDRESS_TO_PRINCIPAL.with_borrow_mut(|mapping| {
-                mapping.remove(&session.user_address);
-            });
-        }
-        
+    for (principal, session_id) in expired_sessions {
+        remove_session(&principal, &session_id);
         removed_count += 1;
     }
     
+    // 清理过期的认证挑战
+    AUTH_CHALLENGES.with_borrow_mut(|challenges| {
+        challenges.retain(|_, challenge| challenge.expires_at > now);
+    });
+    
     if removed_count > 0 {
         ic_cdk::println!("Cleaned up {} expired sessions", removed_count);
     }
@@ -2,11 +2,30 @@
 // 这个模块实现数据备份、恢复和灾难恢复功能
 
 use crate::{Error, Result, types::*, secure_logging::*, ic_api};
-use candid::{CandidType, Deserialize};
+use candid::{CandidType, Deserialize, Principal};
 use ic_cdk_macros::{query, update};
 use serde::Serialize;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// 导出分块大小 (低于 IC 的 2MB 响应上限)
+pub const EXPORT_CHUNK_SIZE: usize = 1_000_000;
+
+// 导入备份的最大大小
+const MAX_IMPORT_BYTES: u64 = 256 * 1024 * 1024;
+
+// 同时进行中的导入数量上限
+const MAX_PENDING_IMPORTS: usize = 4;
+
+// 已准备的导出数量上限
+const MAX_PREPARED_EXPORTS: usize = 2;
+
+// AES-256-GCM 密钥和 nonce 长度
+const ENCRYPTION_KEY_SIZE: usize = 32;
+const ENCRYPTION_NONCE_SIZE: usize = 12;
+
+// 加密归档的附加认证数据
+const ARCHIVE_AAD: &[u8] = b"bollar-backup-archive-v1";
 
 // 备份类型
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
@@ -47,19 +66,25 @@ pub struct FullBackupData {
     pub positions: HashMap<String, Position>,
     pub system_config: SystemConfig,
     pub version: String,
+    #[serde(default)]
+    pub last_change_seq: u64,        // 备份时已记录的最后一个变更序号
 }
 
 // 增量备份数据
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct IncrementalBackupData {
     pub metadata: BackupMetadata,
-    pub base_backup_id: String,
+    pub base_backup_id: String,      // 完整备份或上一个增量备份
     pub changes: Vec<DataChange>,
+    #[serde(default)]
+    pub last_change_seq: u64,
 }
 
 // 数据变更记录
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct DataChange {
+    #[serde(default)]
+    pub seq: u64,                 // 变更序号 (单调递增)
     pub timestamp: u64,
     pub change_type: ChangeType,
     pub entity_type: EntityType,
@@ -90,6 +115,97 @@ pub struct SystemConfig {
     pub log_config: crate::secure_logging::LogConfig,
 }
 
+// 导出的备份归档 (包含来源 canister，便于在新 canister 中恢复时识别)
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BackupArchive {
+    pub source_canister: Principal,
+    pub exported_at: u64,
+    pub backup: FullBackupData,
+    #[serde(default)]
+    pub incrementals: Vec<IncrementalBackupData>,  // 导出增量备份时，从完整备份到该增量的备份链 (按顺序)
+}
+
+// 归档文件格式
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ArchiveEnvelope {
+    Plain(BackupArchive),
+    Encrypted {                    // AES-256-GCM 加密的 BackupArchive
+        nonce: Vec<u8>,
+        ciphertext: Vec<u8>,
+    },
+}
+
+// 备份内容的规范化表示 (按键排序，相同数据总是得到相同字节)
+#[derive(Serialize)]
+struct CanonicalBackupContent<'a> {
+    pools: BTreeMap<&'a String, &'a Pool>,
+    positions: BTreeMap<&'a String, &'a Position>,
+    system_config: &'a SystemConfig,
+}
+
+// 备份导出信息
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct BackupExportInfo {
+    pub backup_id: String,
+    pub source_canister: Principal,
+    pub total_bytes: u64,
+    pub chunk_size: u64,
+    pub total_chunks: u32,
+    pub checksum: String,          // 归档字节的 SHA-256 (hex)
+    pub encrypted: bool,
+    pub metadata: BackupMetadata,
+}
+
+// 备份导入清单
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct BackupImportManifest {
+    pub total_bytes: u64,
+    pub total_chunks: u32,
+    pub checksum: String,          // 导出时返回的 SHA-256 (hex)
+}
+
+// 备份导入进度
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct BackupImportStatus {
+    pub import_id: String,
+    pub received_chunks: u32,
+    pub total_chunks: u32,
+    pub received_bytes: u64,
+    pub total_bytes: u64,
+}
+
+// 备份导入结果
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct BackupImportResult {
+    pub metadata: BackupMetadata,
+    pub source_canister: Principal,
+    pub warnings: Vec<String>,
+}
+
+// 进行中的导入
+#[derive(Clone, Debug)]
+struct PendingImport {
+    manifest: BackupImportManifest,
+    chunks: BTreeMap<u32, Vec<u8>>,
+    started_at: u64,
+}
+
+impl PendingImport {
+    fn received_bytes(&self) -> u64 {
+        self.chunks.values().map(|chunk| chunk.len() as u64).sum()
+    }
+
+    fn status(&self, import_id: &str) -> BackupImportStatus {
+        BackupImportStatus {
+            import_id: import_id.to_string(),
+            received_chunks: self.chunks.len() as u32,
+            total_chunks: self.manifest.total_chunks,
+            received_bytes: self.received_bytes(),
+            total_bytes: self.manifest.total_bytes,
+        }
+    }
+}
+
 // 恢复选项
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct RecoveryOptions {
@@ -99,6 +215,7 @@ pub struct RecoveryOptions {
     pub restore_config: bool,
     pub verify_integrity: bool,
     pub create_recovery_point: bool,
+    pub target_time: Option<u64>,     // 只回放此时间点 (含) 之前的增量变更
 }
 
 // 恢复结果
@@ -110,8 +227,93 @@ pub struct RecoveryResult {
     pub errors: Vec<String>,
     pub warnings: Vec<String>,
     pub recovery_time_ms: u64,
+    pub replayed_changes: u64,
+}
+
+// 头寸差异
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct PositionDiff {
+    pub position_id: String,
+    pub current: Position,
+    pub restored: Position,
+}
+
+// 资金池差异 (比较最新状态)
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct PoolDiff {
+    pub pool_address: String,
+    pub exists: bool,                      // 当前是否存在该池
+    pub current_nonce: Option<u64>,
+    pub restored_nonce: Option<u64>,
+    pub current_utxo: Option<String>,      // UTXO outpoint
+    pub restored_utxo: Option<String>,
+    pub current_utxo_sats: Option<u64>,
+    pub restored_utxo_sats: Option<u64>,
+    pub parameters_changed: bool,          // 抵押率或清算阈值是否变化
 }
 
+impl PoolDiff {
+    fn new(pool_address: &str, current: Option<&Pool>, restored: &Pool) -> Self {
+        let last_state = |pool: &Pool| pool.states.last().cloned();
+        let current_state = current.and_then(last_state);
+        let restored_state = last_state(restored);
+        let utxo = |state: &Option<PoolState>| state.as_ref().and_then(|s| s.utxo.clone());
+        
+        Self {
+            pool_address: pool_address.to_string(),
+            exists: current.is_some(),
+            current_nonce: current_state.as_ref().map(|s| s.nonce),
+            restored_nonce: restored_state.as_ref().map(|s| s.nonce),
+            current_utxo: utxo(&current_state).map(|u| u.outpoint.to_string()),
+            restored_utxo: utxo(&restored_state).map(|u| u.outpoint.to_string()),
+            current_utxo_sats: utxo(&current_state).map(|u| u.sats),
+            restored_utxo_sats: utxo(&restored_state).map(|u| u.sats),
+            parameters_changed: current.map_or(true, |pool| {
+                pool.collateral_ratio != restored.collateral_ratio
+                    || pool.liquidation_threshold != restored.liquidation_threshold
+            }),
+        }
+    }
+    
+    fn has_changes(&self) -> bool {
+        !self.exists
+            || self.current_nonce != self.restored_nonce
+            || self.current_utxo != self.restored_utxo
+            || self.current_utxo_sats != self.restored_utxo_sats
+            || self.parameters_changed
+    }
+}
+
+// 恢复预览
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct RestorePreview {
+    pub backup_id: String,
+    pub replayed_changes: u64,
+    pub positions_added: Vec<Position>,
+    pub positions_removed: Vec<Position>,
+    pub positions_changed: Vec<PositionDiff>,
+    pub pools_changed: Vec<PoolDiff>,
+    pub pools_removed: Vec<String>,     // 目标时间点不存在、恢复时会删除的资金池
+    pub current_bollar_supply: u64,
+    pub restored_bollar_supply: u64,
+    pub bollar_supply_delta: i64,
+    pub current_btc_locked: u64,
+    pub restored_btc_locked: u64,
+    pub btc_locked_delta: i64,
+    pub warnings: Vec<String>,          // 恢复时会被跳过的记录
+}
+
+// 恢复目标状态
+struct RestoreTarget {
+    pools: HashMap<String, Pool>,
+    positions: HashMap<String, Position>,
+    removed_pools: Vec<String>,         // 恢复时需要删除的资金池
+    removed_positions: Vec<String>,     // 恢复时需要删除的头寸
+    replayed_changes: u64,
+}
+
+// 备份、增量备份和变更日志只保存在堆内存中，升级前需要通过 prepare_backup_export 导出
+// (导出增量备份时会连同其备份链一起导出)
 thread_local! {
     // 备份存储
     static BACKUPS: RefCell<HashMap<String, FullBackupData>> = RefCell::new(HashMap::new());
@@ -122,8 +324,63 @@ thread_local! {
     // 数据变更日志
     static CHANGE_LOG: RefCell<Vec<DataChange>> = RefCell::new(Vec::new());
     
+    // 下一个变更序号
+    static NEXT_CHANGE_SEQ: RefCell<u64> = RefCell::new(1);
+    
     // 备份配置
     static BACKUP_CONFIG: RefCell<BackupConfig> = RefCell::new(BackupConfig::default());
+    
+    // 已准备好分块下载的导出 (备份 ID -> (准备时间, 归档字节))
+    static PREPARED_EXPORTS: RefCell<HashMap<String, (u64, Vec<u8>)>> = RefCell::new(HashMap::new());
+    
+    // 进行中的导入
+    static PENDING_IMPORTS: RefCell<HashMap<String, PendingImport>> = RefCell::new(HashMap::new());
+    
+    // 导入 ID 计数器
+    static NEXT_IMPORT_ID: RefCell<u64> = RefCell::new(0);
+}
+
+// 计算字节的 SHA-256 (hex)
+fn sha256_hex(bytes: &[u8]) -> String {
+    use sha2::{Sha256, Digest};
+    hex::encode(Sha256::digest(bytes))
+}
+
+// CBOR 编码
+fn encode_cbor<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    ciborium::ser::into_writer(value, &mut bytes)
+        .map_err(|e| Error::SystemError(format!("Failed to encode backup data: {}", e)))?;
+    Ok(bytes)
+}
+
+// 创建 AES-256-GCM 加密器
+fn archive_cipher(key: &[u8]) -> Result<aes_gcm::Aes256Gcm> {
+    use aes_gcm::KeyInit;
+    
+    validate_param!(key.len() == ENCRYPTION_KEY_SIZE, format!("Encryption key must be {} bytes", ENCRYPTION_KEY_SIZE));
+    aes_gcm::Aes256Gcm::new_from_slice(key)
+        .map_err(|e| Error::InvalidArgument(format!("Invalid encryption key: {}", e)))
+}
+
+// 加密归档
+fn encrypt_archive(key: &[u8], nonce: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    use aes_gcm::aead::{Aead, Payload};
+    
+    validate_param!(nonce.len() == ENCRYPTION_NONCE_SIZE, "Invalid encryption nonce");
+    archive_cipher(key)?
+        .encrypt(aes_gcm::Nonce::from_slice(nonce), Payload { msg: plaintext, aad: ARCHIVE_AAD })
+        .map_err(|_| Error::SystemError("Failed to encrypt backup archive".to_string()))
+}
+
+// 解密归档，密钥错误或数据被篡改时失败
+fn decrypt_archive(key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    use aes_gcm::aead::{Aead, Payload};
+    
+    validate_param!(nonce.len() == ENCRYPTION_NONCE_SIZE, "Invalid encryption nonce");
+    archive_cipher(key)?
+        .decrypt(aes_gcm::Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: ARCHIVE_AAD })
+        .map_err(|_| Error::InvalidState("Failed to decrypt backup archive: wrong key or tampered data".to_string()))
 }
 
 // 备份配置
@@ -178,7 +435,7 @@ impl BackupManager {
         
         // 计算数据大小和校验和
         let data_size = Self::calculate_backup_size(&pools, &positions);
-        let checksum = Self::calculate_checksum(&pools, &positions, &system_config);
+        let checksum = Self::calculate_checksum(&pools, &positions, &system_config)?;
         
         // 创建备份元数据
         let metadata = BackupMetadata {
@@ -200,6 +457,7 @@ impl BackupManager {
             positions,
             system_config,
             version: env!("CARGO_PKG_VERSION").to_string(),
+            last_change_seq: Self::last_change_seq(),
         };
         
         // 标记为完成
@@ -238,24 +496,30 @@ impl BackupManager {
         let caller = ic_api::caller().to_string();
         let backup_id = format!("inc_{}_{}", ic_api::time(), caller);
         
-        // 验证基础备份存在
-        let base_exists = BACKUPS.with_borrow(|backups| backups.contains_key(&base_backup_id));
-        if !base_exists {
-            return Err(Error::InvalidArgument("Base backup not found".to_string()));
-        }
+        // 验证基础备份存在 (可以是完整备份或另一个增量备份)
+        let base_seq = Self::backup_change_seq(&base_backup_id)
+            .ok_or(Error::InvalidArgument("Base backup not found".to_string()))?;
         
         // 获取自基础备份以来的变更
-        let base_backup = BACKUPS.with_borrow(|backups| {
-            backups.get(&base_backup_id).cloned()
-        }).unwrap();
-        
-        let changes = CHANGE_LOG.with_borrow(|log| {
-            log.iter()
-                .filter(|change| change.timestamp > base_backup.metadata.created_at)
+        let (changes, covered) = CHANGE_LOG.with_borrow(|log| {
+            let changes: Vec<DataChange> = log.iter()
+                .filter(|change| change.seq > base_seq)
                 .cloned()
-                .collect()
+                .collect();
+            
+            // 变更日志被截断后无法保证增量完整
+            let covered = log.first().map_or(true, |first| first.seq <= base_seq + 1);
+            (changes, covered)
         });
         
+        if !covered {
+            return Err(Error::InvalidState(format!(
+                "Change log no longer covers base backup {}, create a new full backup", base_backup_id
+            )));
+        }
+        
+        let last_change_seq = changes.last().map_or(base_seq, |change| change.seq);
+        
         // 创建增量备份元数据
         let metadata = BackupMetadata {
             id: backup_id.clone(),
@@ -264,7 +528,7 @@ impl BackupManager {
             created_at: ic_api::time(),
             completed_at: Some(ic_api::time()),
             size_bytes: changes.len() as u64 * 1000, // 粗略估算
-            checksum: Self::calculate_changes_checksum(&changes),
+            checksum: Self::calculate_changes_checksum(&changes)?,
             description,
             created_by: caller,
         };
@@ -274,6 +538,7 @@ impl BackupManager {
             metadata,
             base_backup_id,
             changes,
+            last_change_seq,
         };
         
         // 存储增量备份
@@ -312,6 +577,7 @@ impl BackupManager {
             errors: Vec::new(),
             warnings: Vec::new(),
             recovery_time_ms: 0,
+            replayed_changes: 0,
         };
         
         // 创建恢复点
@@ -326,31 +592,30 @@ impl BackupManager {
             }
         }
         
-        // 获取备份数据
-        let backup_data = BACKUPS.with_borrow(|backups| {
-            backups.get(&options.backup_id).cloned()
-        });
-        
-        let Some(backup) = backup_data else {
-            result.errors.push("Backup not found".to_string());
-            return Ok(result);
-        };
-        
-        // 验证备份完整性
-        if options.verify_integrity {
-            if let Err(e) = Self::verify_backup_integrity(&backup) {
-                result.errors.push(format!("Backup integrity check failed: {:?}", e));
+        // 构建恢复目标状态
+        let target = match Self::build_restore_target(&options) {
+            Ok(target) => target,
+            Err(e) => {
+                result.errors.push(format!("{:?}", e));
                 return Ok(result);
             }
-        }
+        };
+        result.replayed_changes = target.replayed_changes;
+        
+        // 记录被修改的实体，供恢复事件使用
+        let mut restored_pools = Vec::new();
+        let mut restored_positions = Vec::new();
+        let mut removed_pools = Vec::new();
+        let mut removed_positions = Vec::new();
         
         // 恢复池数据
         if options.restore_pools {
-            for (addr, pool) in backup.pools {
+            for (addr, pool) in target.pools {
                 match crate::input_validation::validate_pool(&pool) {
                     Ok(_) => {
                         crate::save_pool(pool);
                         result.restored_pools += 1;
+                        restored_pools.push(addr);
                     }
                     Err(e) => {
                         result.errors.push(format!("Failed to restore pool {}: {:?}", addr, e));
@@ -361,11 +626,17 @@ impl BackupManager {
         
         // 恢复头寸数据
         if options.restore_positions {
-            for (id, position) in backup.positions {
+            for id in &target.removed_positions {
+                crate::delete_position(id);
+            }
+            removed_positions = target.removed_positions.clone();
+            
+            for (id, position) in target.positions {
                 match crate::input_validation::validate_position(&position) {
                     Ok(_) => {
                         crate::save_position(position);
                         result.restored_positions += 1;
+                        restored_positions.push(id);
                     }
                     Err(e) => {
                         result.errors.push(format!("Failed to restore position {}: {:?}", id, e));
@@ -374,6 +645,21 @@ impl BackupManager {
             }
         }
         
+        // 删除目标时间点不存在的资金池 (仍有头寸引用的池保留)
+        if options.restore_pools {
+            let referenced: HashSet<String> = crate::get_positions().into_iter()
+                .map(|position| position.pool_address)
+                .collect();
+            for addr in &target.removed_pools {
+                if referenced.contains(addr) {
+                    result.warnings.push(format!("Pool {} kept: positions still reference it", addr));
+                    continue;
+                }
+                crate::delete_pool(addr);
+                removed_pools.push(addr.clone());
+            }
+        }
+        
         // 恢复系统配置
         if options.restore_config {
             // 这里可以恢复各种系统配置
@@ -384,6 +670,21 @@ impl BackupManager {
         result.recovery_time_ms = (end_time - start_time) / 1_000_000;
         result.success = result.errors.is_empty();
         
+        // 恢复直接覆盖和删除状态，通知事件流的订阅方重新同步 (部分失败时同样已修改状态)
+        if !(restored_pools.is_empty() && restored_positions.is_empty() && removed_pools.is_empty() && removed_positions.is_empty()) {
+            restored_pools.sort();
+            restored_positions.sort();
+            crate::events::record_event(crate::events::ProtocolEvent::StateRestored {
+                backup_id: options.backup_id.clone(),
+                target_time: options.target_time,
+                restored_pools,
+                restored_positions,
+                removed_pools,
+                removed_positions,
+                operator: ic_api::caller().to_string(),
+            });
+        }
+        
         secure_log_info!(
             LogCategory::System,
             format!("Data recovery completed: success={}", result.success),
@@ -394,6 +695,149 @@ impl BackupManager {
         Ok(result)
     }
     
+    // 解析备份链、校验完整性并回放增量变更，得到恢复后的目标状态 (不修改任何数据)
+    fn build_restore_target(options: &RecoveryOptions) -> Result<RestoreTarget> {
+        let (backup, incrementals) = Self::resolve_backup_chain(&options.backup_id)?;
+        
+        // 始终校验校验和，verify_integrity 时额外验证每条记录
+        let integrity = if options.verify_integrity {
+            Self::verify_backup_integrity(&backup)
+        } else {
+            Self::verify_backup_checksum(&backup)
+        };
+        integrity.map_err(|e| Error::InvalidState(format!("Backup integrity check failed: {:?}", e)))?;
+        
+        for incremental in &incrementals {
+            if Self::calculate_changes_checksum(&incremental.changes)? != incremental.metadata.checksum {
+                return Err(Error::InvalidState(format!("Incremental backup {} checksum mismatch", incremental.metadata.id)));
+            }
+        }
+        
+        if let Some(target_time) = options.target_time {
+            if target_time < backup.metadata.created_at {
+                return Err(Error::InvalidArgument(format!(
+                    "Target time {} is earlier than base backup {}", target_time, backup.metadata.id
+                )));
+            }
+        }
+        
+        // 回放增量变更到目标时间点
+        let mut pools = backup.pools;
+        let mut positions = backup.positions;
+        let mut replayed_changes = 0;
+        let changes = incrementals.iter()
+            .flat_map(|incremental| incremental.changes.iter())
+            .filter(|change| options.target_time.map_or(true, |target_time| change.timestamp <= target_time));
+        
+        for change in changes {
+            Self::apply_change(change, &mut pools, &mut positions)
+                .map_err(|e| Error::InvalidState(format!("Failed to replay change {}: {:?}", change.seq, e)))?;
+            replayed_changes += 1;
+        }
+        
+        // 当前存在但在目标时间点不存在的头寸和资金池 (已删除或在目标时间之后创建，
+        // 包括备份链末端之后创建的) 需要删除
+        let mut removed_positions: Vec<String> = crate::get_positions().into_iter()
+            .map(|position| position.id)
+            .filter(|id| !positions.contains_key(id))
+            .collect();
+        removed_positions.sort();
+        let mut removed_pools: Vec<String> = crate::get_pools().into_iter()
+            .map(|pool| pool.addr)
+            .filter(|addr| !pools.contains_key(addr))
+            .collect();
+        removed_pools.sort();
+        
+        Ok(RestoreTarget { pools, positions, removed_pools, removed_positions, replayed_changes })
+    }
+    
+    // 预览恢复结果，计算当前状态与恢复后状态的差异
+    pub fn preview_restore(options: &RecoveryOptions) -> Result<RestorePreview> {
+        let target = Self::build_restore_target(options)?;
+        let mut preview = RestorePreview {
+            backup_id: options.backup_id.clone(),
+            replayed_changes: target.replayed_changes,
+            positions_added: Vec::new(),
+            positions_removed: Vec::new(),
+            positions_changed: Vec::new(),
+            pools_changed: Vec::new(),
+            pools_removed: Vec::new(),
+            current_bollar_supply: 0,
+            restored_bollar_supply: 0,
+            bollar_supply_delta: 0,
+            current_btc_locked: 0,
+            restored_btc_locked: 0,
+            btc_locked_delta: 0,
+            warnings: Vec::new(),
+        };
+        
+        if options.restore_pools {
+            for (addr, pool) in &target.pools {
+                if let Err(e) = crate::input_validation::validate_pool(pool) {
+                    preview.warnings.push(format!("Pool {} would fail validation: {:?}", addr, e));
+                    continue;
+                }
+                
+                let current = crate::get_pool(addr);
+                let diff = PoolDiff::new(addr, current.as_ref(), pool);
+                if diff.has_changes() {
+                    preview.pools_changed.push(diff);
+                }
+            }
+            
+            preview.pools_removed = target.removed_pools.clone();
+        }
+        
+        // 恢复后的 Bollar 供应量和锁定 BTC 以当前值为基础累加差异
+        let current_positions = crate::get_positions();
+        preview.current_bollar_supply = current_positions.iter().map(|p| p.bollar_debt).sum();
+        preview.current_btc_locked = current_positions.iter().map(|p| p.btc_collateral).sum();
+        let mut supply_delta: i128 = 0;
+        let mut btc_delta: i128 = 0;
+        
+        if options.restore_positions {
+            for id in &target.removed_positions {
+                if let Some(current) = crate::get_position(id) {
+                    supply_delta -= current.bollar_debt as i128;
+                    btc_delta -= current.btc_collateral as i128;
+                    preview.positions_removed.push(current);
+                }
+            }
+            
+            for (id, restored) in &target.positions {
+                if let Err(e) = crate::input_validation::validate_position(restored) {
+                    preview.warnings.push(format!("Position {} would fail validation: {:?}", id, e));
+                    continue;
+                }
+                
+                match crate::get_position(id) {
+                    None => {
+                        supply_delta += restored.bollar_debt as i128;
+                        btc_delta += restored.btc_collateral as i128;
+                        preview.positions_added.push(restored.clone());
+                    }
+                    Some(current) if &current != restored => {
+                        supply_delta += restored.bollar_debt as i128 - current.bollar_debt as i128;
+                        btc_delta += restored.btc_collateral as i128 - current.btc_collateral as i128;
+                        preview.positions_changed.push(PositionDiff {
+                            position_id: id.clone(),
+                            current,
+                            restored: restored.clone(),
+                        });
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        
+        preview.restored_bollar_supply = (preview.current_bollar_supply as i128 + supply_delta) as u64;
+        preview.restored_btc_locked = (preview.current_btc_locked as i128 + btc_delta) as u64;
+        preview.bollar_supply_delta = supply_delta.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+        preview.btc_locked_delta = btc_delta.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+        
+        Ok(preview)
+    }
+    
     // 记录数据变更
     pub fn log_data_change(
         change_type: ChangeType,
@@ -402,7 +846,14 @@ impl BackupManager {
         old_data: Option<String>,
         new_data: Option<String>,
     ) {
+        let seq = NEXT_CHANGE_SEQ.with_borrow_mut(|next| {
+            let seq = *next;
+            *next += 1;
+            seq
+        });
+        
         let change = DataChange {
+            seq,
             timestamp: ic_api::time(),
             change_type,
             entity_type,
@@ -421,6 +872,91 @@ impl BackupManager {
         });
     }
     
+    // 记录实体变更，变更类型由新旧数据推断
+    pub fn record_change<T: Serialize>(entity_type: EntityType, entity_id: &str, old: Option<&T>, new: Option<&T>) {
+        let change_type = match (old, new) {
+            (None, Some(_)) => ChangeType::Create,
+            (Some(_), Some(_)) => ChangeType::Update,
+            (_, None) => ChangeType::Delete,
+        };
+        
+        let encode = |value: &T| serde_json::to_string(value).ok();
+        Self::log_data_change(change_type, entity_type, entity_id.to_string(), old.and_then(encode), new.and_then(encode));
+    }
+    
+    // 最后一个已记录的变更序号
+    fn last_change_seq() -> u64 {
+        NEXT_CHANGE_SEQ.with_borrow(|next| *next - 1)
+    }
+    
+    // 备份覆盖到的变更序号
+    fn backup_change_seq(backup_id: &str) -> Option<u64> {
+        BACKUPS.with_borrow(|backups| backups.get(backup_id).map(|b| b.last_change_seq))
+            .or_else(|| INCREMENTAL_BACKUPS.with_borrow(|backups| backups.get(backup_id).map(|b| b.last_change_seq)))
+    }
+    
+    // 完整备份或增量备份的元数据
+    fn backup_metadata(backup_id: &str) -> Option<BackupMetadata> {
+        BACKUPS.with_borrow(|backups| backups.get(backup_id).map(|b| b.metadata.clone()))
+            .or_else(|| INCREMENTAL_BACKUPS.with_borrow(|backups| backups.get(backup_id).map(|b| b.metadata.clone())))
+    }
+    
+    // 从指定备份回溯到完整备份，返回完整备份和按时间顺序排列的增量备份
+    fn resolve_backup_chain(backup_id: &str) -> Result<(FullBackupData, Vec<IncrementalBackupData>)> {
+        let mut incrementals = Vec::new();
+        let mut current = backup_id.to_string();
+        
+        loop {
+            if let Some(full) = BACKUPS.with_borrow(|backups| backups.get(&current).cloned()) {
+                incrementals.reverse();
+                return Ok((full, incrementals));
+            }
+            
+            let incremental = INCREMENTAL_BACKUPS.with_borrow(|backups| backups.get(&current).cloned())
+                .ok_or(Error::InvalidArgument(format!("Backup {} not found", current)))?;
+            
+            current = incremental.base_backup_id.clone();
+            incrementals.push(incremental);
+            
+            if incrementals.len() > INCREMENTAL_BACKUPS.with_borrow(|backups| backups.len()) {
+                return Err(Error::InvalidState(format!("Backup chain of {} contains a cycle", backup_id)));
+            }
+        }
+    }
+    
+    // 将一条变更应用到内存中的状态
+    fn apply_change(
+        change: &DataChange,
+        pools: &mut HashMap<String, Pool>,
+        positions: &mut HashMap<String, Position>,
+    ) -> Result<()> {
+        match (&change.entity_type, &change.change_type) {
+            (EntityType::Pool, ChangeType::Delete) => {
+                pools.remove(&change.entity_id);
+            }
+            (EntityType::Pool, _) => {
+                pools.insert(change.entity_id.clone(), Self::decode_change_data(change)?);
+            }
+            (EntityType::Position, ChangeType::Delete) => {
+                positions.remove(&change.entity_id);
+            }
+            (EntityType::Position, _) => {
+                positions.insert(change.entity_id.clone(), Self::decode_change_data(change)?);
+            }
+            // 配置变更不参与数据回放
+            (EntityType::Config, _) => {}
+        }
+        Ok(())
+    }
+    
+    // 解析变更后的数据
+    fn decode_change_data<T: serde::de::DeserializeOwned>(change: &DataChange) -> Result<T> {
+        let data = change.new_data.as_ref()
+            .ok_or(Error::InvalidState(format!("Change {} has no data", change.seq)))?;
+        serde_json::from_str(data)
+            .map_err(|e| Error::InvalidState(format!("Invalid data in change {}: {}", change.seq, e)))
+    }
+    
     // 计算备份大小
     fn calculate_backup_size(pools: &HashMap<String, Pool>, positions: &HashMap<String, Position>) -> u64 {
         let pools_size = pools.len() * std::mem::size_of::<Pool>();
@@ -428,59 +964,52 @@ impl BackupManager {
         (pools_size + positions_size) as u64
     }
     
-    // 计算校验和
+    // 备份内容的规范化字节
+    pub fn canonical_bytes(
+        pools: &HashMap<String, Pool>,
+        positions: &HashMap<String, Position>,
+        config: &SystemConfig,
+    ) -> Result<Vec<u8>> {
+        encode_cbor(&CanonicalBackupContent {
+            pools: pools.iter().collect(),
+            positions: positions.iter().collect(),
+            system_config: config,
+        })
+    }
+    
+    // 计算校验和 (规范化字节的 SHA-256)
     fn calculate_checksum(
         pools: &HashMap<String, Pool>,
         positions: &HashMap<String, Position>,
-        _config: &SystemConfig,
-    ) -> String {
-        use sha2::{Sha256, Digest};
-        
-        let mut hasher = Sha256::new();
-        
-        // 对池数据进行哈希
-        for (addr, pool) in pools {
-            hasher.update(addr.as_bytes());
-            hasher.update(&pool.collateral_ratio.to_be_bytes());
-            hasher.update(&pool.liquidation_threshold.to_be_bytes());
-        }
-        
-        // 对头寸数据进行哈希
-        for (id, position) in positions {
-            hasher.update(id.as_bytes());
-            hasher.update(&position.btc_collateral.to_be_bytes());
-            hasher.update(&position.bollar_debt.to_be_bytes());
-        }
-        
-        hex::encode(hasher.finalize())
+        config: &SystemConfig,
+    ) -> Result<String> {
+        Ok(sha256_hex(&Self::canonical_bytes(pools, positions, config)?))
     }
     
     // 计算变更校验和
-    fn calculate_changes_checksum(changes: &[DataChange]) -> String {
-        use sha2::{Sha256, Digest};
-        
-        let mut hasher = Sha256::new();
-        for change in changes {
-            hasher.update(&change.timestamp.to_be_bytes());
-            hasher.update(change.entity_id.as_bytes());
-        }
-        
-        hex::encode(hasher.finalize())
+    fn calculate_changes_checksum(changes: &[DataChange]) -> Result<String> {
+        Ok(sha256_hex(&encode_cbor(&changes)?))
     }
     
-    // 验证备份完整性
-    fn verify_backup_integrity(backup: &FullBackupData) -> Result<()> {
-        // 重新计算校验和
+    // 验证备份校验和
+    fn verify_backup_checksum(backup: &FullBackupData) -> Result<()> {
         let calculated_checksum = Self::calculate_checksum(
             &backup.pools,
             &backup.positions,
             &backup.system_config,
-        );
+        )?;
         
         if calculated_checksum != backup.metadata.checksum {
             return Err(Error::InvalidState("Backup checksum mismatch".to_string()));
         }
         
+        Ok(())
+    }
+    
+    // 验证备份完整性
+    fn verify_backup_integrity(backup: &FullBackupData) -> Result<()> {
+        Self::verify_backup_checksum(backup)?;
+        
         // 验证数据完整性
         for pool in backup.pools.values() {
             crate::input_validation::validate_pool(pool)?;
@@ -493,6 +1022,148 @@ impl BackupManager {
         Ok(())
     }
     
+    // 将备份编码为可导出的归档字节，提供 (密钥, nonce) 时使用 AES-256-GCM 加密
+    // 导出增量备份时归档包含其完整备份和整条增量备份链
+    pub fn export_archive(backup_id: &str, encryption: Option<(&[u8], &[u8])>) -> Result<Vec<u8>> {
+        let (backup, incrementals) = Self::resolve_backup_chain(backup_id)?;
+        
+        let archive = BackupArchive {
+            source_canister: ic_api::canister_id(),
+            exported_at: ic_api::time(),
+            backup,
+            incrementals,
+        };
+        
+        let envelope = match encryption {
+            Some((key, nonce)) => ArchiveEnvelope::Encrypted {
+                nonce: nonce.to_vec(),
+                ciphertext: encrypt_archive(key, nonce, &encode_cbor(&archive)?)?,
+            },
+            None => ArchiveEnvelope::Plain(archive),
+        };
+        
+        encode_cbor(&envelope)
+    }
+    
+    // 从归档字节导入备份，加密的归档需要提供解密密钥
+    pub fn import_archive(bytes: &[u8], decryption_key: Option<&[u8]>) -> Result<BackupImportResult> {
+        let envelope: ArchiveEnvelope = ciborium::de::from_reader(bytes)
+            .map_err(|e| Error::InvalidArgument(format!("Invalid backup archive: {}", e)))?;
+        
+        let archive: BackupArchive = match envelope {
+            ArchiveEnvelope::Plain(archive) => archive,
+            ArchiveEnvelope::Encrypted { nonce, ciphertext } => {
+                let key = decryption_key.ok_or(Error::InvalidArgument(
+                    "Backup archive is encrypted, a decryption key is required".to_string()
+                ))?;
+                let plaintext = decrypt_archive(key, &nonce, &ciphertext)?;
+                ciborium::de::from_reader(plaintext.as_slice())
+                    .map_err(|e| Error::InvalidArgument(format!("Invalid backup archive: {}", e)))?
+            }
+        };
+        
+        let mut backup = archive.backup;
+        let mut incrementals = archive.incrementals;
+        let mut warnings = Vec::new();
+        
+        // 被篡改的备份不能导入
+        Self::verify_backup_integrity(&backup)
+            .map_err(|e| Error::InvalidState(format!("Imported backup failed integrity check: {:?}", e)))?;
+        
+        // 增量备份链必须从完整备份开始逐个衔接，且每个增量的校验和正确
+        let mut base_id = backup.metadata.id.clone();
+        for incremental in &incrementals {
+            if incremental.base_backup_id != base_id {
+                return Err(Error::InvalidState(format!(
+                    "Incremental backup {} does not follow {}", incremental.metadata.id, base_id
+                )));
+            }
+            if Self::calculate_changes_checksum(&incremental.changes)? != incremental.metadata.checksum {
+                return Err(Error::InvalidState(format!(
+                    "Imported incremental backup {} failed integrity check", incremental.metadata.id
+                )));
+            }
+            base_id = incremental.metadata.id.clone();
+        }
+        
+        if archive.source_canister != ic_api::canister_id() {
+            warnings.push(format!(
+                "Backup was exported from canister {}; pool addresses are derived from that canister's key",
+                archive.source_canister
+            ));
+        }
+        
+        // 先检查冲突，任何一个备份 ID 已被不同内容占用时整体拒绝
+        let backup_id = backup.metadata.id.clone();
+        let existing = BACKUPS.with_borrow(|backups| backups.get(&backup_id).map(|b| b.metadata.checksum.clone()));
+        let backup_exists = match existing {
+            Some(checksum) if checksum == backup.metadata.checksum => true,
+            Some(_) => {
+                return Err(Error::InvalidState(format!("A different backup with id {} already exists", backup_id)));
+            }
+            None => false,
+        };
+        let mut incremental_exists = Vec::with_capacity(incrementals.len());
+        for incremental in &incrementals {
+            let id = &incremental.metadata.id;
+            let existing = INCREMENTAL_BACKUPS.with_borrow(|backups| {
+                backups.get(id).map(|b| (b.base_backup_id.clone(), b.metadata.checksum.clone()))
+            });
+            match existing {
+                Some((base, checksum)) if base == incremental.base_backup_id && checksum == incremental.metadata.checksum => {
+                    incremental_exists.push(true);
+                }
+                Some(_) => {
+                    return Err(Error::InvalidState(format!("A different incremental backup with id {} already exists", id)));
+                }
+                None if BACKUPS.with_borrow(|backups| backups.contains_key(id)) => {
+                    return Err(Error::InvalidState(format!("A full backup with id {} already exists", id)));
+                }
+                None => incremental_exists.push(false),
+            }
+        }
+        
+        // 来源 canister 的变更序号在本地无意义，之后的增量备份从本地当前变更开始
+        let local_seq = Self::last_change_seq();
+        if backup_exists {
+            warnings.push(format!("Backup {} already exists", backup_id));
+        } else {
+            backup.last_change_seq = local_seq;
+            BACKUPS.with_borrow_mut(|backups| {
+                backups.insert(backup_id.clone(), backup.clone());
+            });
+        }
+        
+        for (incremental, exists) in incrementals.iter_mut().zip(incremental_exists) {
+            if exists {
+                warnings.push(format!("Backup {} already exists", incremental.metadata.id));
+                continue;
+            }
+            incremental.last_change_seq = local_seq;
+            INCREMENTAL_BACKUPS.with_borrow_mut(|backups| {
+                backups.insert(incremental.metadata.id.clone(), incremental.clone());
+            });
+        }
+        
+        // 返回备份链末端的元数据，可直接用于 restore_from_backup
+        let metadata = incrementals.last()
+            .map(|incremental| incremental.metadata.clone())
+            .unwrap_or(backup.metadata);
+        
+        secure_log_info!(
+            LogCategory::System,
+            format!("Backup imported: {}", metadata.id),
+            format!("Source canister: {}, Incrementals: {}, Size: {} bytes",
+                    archive.source_canister, incrementals.len(), bytes.len())
+        );
+        
+        Ok(BackupImportResult {
+            metadata,
+            source_canister: archive.source_canister,
+            warnings,
+        })
+    }
+    
     // 自动备份任务
     pub fn auto_backup_task() {
         let config = BACKUP_CONFIG.with_borrow(|config| config.clone());
@@ -573,8 +1244,32 @@ pub fn create_full_backup(description: String) -> Result<String> {
     BackupManager::create_full_backup(description)
 }
 
+#[query]
+// 预览恢复结果 (不修改任何数据)
+pub fn preview_restore(options: RecoveryOptions) -> Result<RestorePreview> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, crate::access_control::Permission::SystemAdmin)?;
+    
+    crate::input_validation::validate_string(&options.backup_id, "backup_id", true)?;
+    
+    BackupManager::preview_restore(&options)
+}
+
 #[update]
-// 恢复数据
+// 创建增量备份 (基础备份可以是完整备份或增量备份)
+pub fn create_incremental_backup(base_backup_id: String, description: String) -> Result<String> {
+    // 检查权限
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, crate::access_control::Permission::SystemAdmin)?;
+    
+    crate::input_validation::validate_string(&base_backup_id, "base_backup_id", true)?;
+    crate::input_validation::validate_string(&description, "description", true)?;
+    
+    BackupManager::create_incremental_backup(base_backup_id, description)
+}
+
+#[update]
+// 恢复数据 (可指定增量备份和目标时间点)
 pub fn restore_from_backup(options: RecoveryOptions) -> Result<RecoveryResult> {
     // 检查权限
     let caller = ic_api::caller();
@@ -585,8 +1280,211 @@ pub fn restore_from_backup(options: RecoveryOptions) -> Result<RecoveryResult> {
     BackupManager::restore_from_backup(options)
 }
 
-// 心跳函数中的备份任务
-#[ic_cdk_macros::heartbeat]
-async fn backup_recovery_heartbeat() {
-    BackupManager::auto_backup_task();
+#[update]
+// 设置导出备份是否必须加密
+pub fn set_backup_encryption_required(required: bool) -> Result<()> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, crate::access_control::Permission::SuperAdmin)?;
+    
+    BACKUP_CONFIG.with_borrow_mut(|config| config.encryption_enabled = required);
+    
+    crate::error::audit_log("set_backup_encryption_required", &caller.to_string(), &format!("required={}", required));
+    
+    Ok(())
+}
+
+#[update]
+// 准备分块导出备份，返回校验和与分块信息 (提供 32 字节密钥时使用 AES-256-GCM 加密)
+// 增量备份连同其完整备份和备份链一起导出
+pub async fn prepare_backup_export(backup_id: String, encryption_key: Option<Vec<u8>>) -> Result<BackupExportInfo> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, crate::access_control::Permission::SystemAdmin)?;
+    
+    let encryption_required = BACKUP_CONFIG.with_borrow(|config| config.encryption_enabled);
+    validate_param!(!encryption_required || encryption_key.is_some(), "Backup encryption is required, an encryption key must be provided");
+    
+    let bytes = match &encryption_key {
+        Some(key) => {
+            let random_bytes = ic_api::raw_rand().await?;
+            let nonce = random_bytes.get(..ENCRYPTION_NONCE_SIZE)
+                .ok_or(Error::SystemError("Not enough randomness for encryption nonce".to_string()))?;
+            BackupManager::export_archive(&backup_id, Some((key.as_slice(), nonce)))?
+        }
+        None => BackupManager::export_archive(&backup_id, None)?,
+    };
+    let metadata = BackupManager::backup_metadata(&backup_id)
+        .ok_or(Error::InvalidArgument(format!("Backup {} not found", backup_id)))?;
+    
+    let info = BackupExportInfo {
+        backup_id: backup_id.clone(),
+        source_canister: ic_api::canister_id(),
+        total_bytes: bytes.len() as u64,
+        chunk_size: EXPORT_CHUNK_SIZE as u64,
+        total_chunks: bytes.len().div_ceil(EXPORT_CHUNK_SIZE) as u32,
+        checksum: sha256_hex(&bytes),
+        encrypted: encryption_key.is_some(),
+        metadata,
+    };
+    
+    PREPARED_EXPORTS.with_borrow_mut(|exports| {
+        exports.insert(backup_id.clone(), (ic_api::time(), bytes));
+        
+        // 只保留最近准备的导出
+        while exports.len() > MAX_PREPARED_EXPORTS {
+            let oldest = exports.iter()
+                .min_by_key(|(_, (prepared_at, _))| *prepared_at)
+                .map(|(id, _)| id.clone());
+            match oldest {
+                Some(id) => { exports.remove(&id); }
+                None => break,
+            }
+        }
+    });
+    
+    crate::error::audit_log(
+        "prepare_backup_export",
+        &caller.to_string(),
+        &format!("backup_id={}, bytes={}, checksum={}, encrypted={}", backup_id, info.total_bytes, info.checksum, info.encrypted)
+    );
+    
+    Ok(info)
+}
+
+#[query]
+// 获取已准备导出的备份分块
+pub fn get_backup_export_chunk(backup_id: String, index: u32) -> Result<Vec<u8>> {
+    crate::access_control::check_permission(ic_api::caller(), crate::access_control::Permission::SystemAdmin)?;
+    
+    PREPARED_EXPORTS.with_borrow(|exports| {
+        let (_, bytes) = exports.get(&backup_id)
+            .ok_or(Error::InvalidArgument(format!("Backup {} has not been prepared for export", backup_id)))?;
+        
+        let start = index as usize * EXPORT_CHUNK_SIZE;
+        if start >= bytes.len() {
+            return Err(Error::InvalidArgument(format!("Chunk {} out of range", index)));
+        }
+        let end = (start + EXPORT_CHUNK_SIZE).min(bytes.len());
+        Ok(bytes[start..end].to_vec())
+    })
+}
+
+#[update]
+// 开始导入备份
+pub fn begin_backup_import(manifest: BackupImportManifest) -> Result<BackupImportStatus> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, crate::access_control::Permission::SuperAdmin)?;
+    
+    validate_param!(manifest.total_bytes > 0 && manifest.total_bytes <= MAX_IMPORT_BYTES,
+                    format!("Backup size must be between 1 and {} bytes", MAX_IMPORT_BYTES));
+    validate_param!(manifest.total_chunks > 0 && manifest.total_chunks as u64 <= manifest.total_bytes,
+                    "Invalid chunk count");
+    validate_param!(manifest.checksum.len() == 64 && hex::decode(&manifest.checksum).is_ok(),
+                    "Checksum must be a hex-encoded SHA-256 digest");
+    
+    let pending = PENDING_IMPORTS.with_borrow(|imports| imports.len());
+    validate_param!(pending < MAX_PENDING_IMPORTS, format!("At most {} imports can be in progress", MAX_PENDING_IMPORTS));
+    
+    let import_id = NEXT_IMPORT_ID.with_borrow_mut(|next| {
+        *next += 1;
+        format!("import_{}", next)
+    });
+    
+    let import = PendingImport {
+        manifest,
+        chunks: BTreeMap::new(),
+        started_at: ic_api::time(),
+    };
+    let status = import.status(&import_id);
+    
+    PENDING_IMPORTS.with_borrow_mut(|imports| {
+        imports.insert(import_id.clone(), import);
+    });
+    
+    crate::error::audit_log(
+        "begin_backup_import",
+        &caller.to_string(),
+        &format!("import_id={}, bytes={}, chunks={}", import_id, status.total_bytes, status.total_chunks)
+    );
+    
+    Ok(status)
+}
+
+#[update]
+// 上传备份分块，分块可以按任意顺序上传
+pub fn upload_backup_chunk(import_id: String, index: u32, chunk: Vec<u8>) -> Result<BackupImportStatus> {
+    crate::access_control::check_permission(ic_api::caller(), crate::access_control::Permission::SuperAdmin)?;
+    
+    PENDING_IMPORTS.with_borrow_mut(|imports| {
+        let import = imports.get_mut(&import_id)
+            .ok_or(Error::InvalidArgument(format!("Import {} not found", import_id)))?;
+        
+        validate_param!(index < import.manifest.total_chunks, format!("Chunk {} out of range", index));
+        validate_param!(!chunk.is_empty(), "Chunk cannot be empty");
+        
+        let previous = import.chunks.get(&index).map(|c| c.len() as u64).unwrap_or(0);
+        let received = import.received_bytes() - previous + chunk.len() as u64;
+        validate_param!(received <= import.manifest.total_bytes, "Uploaded data exceeds declared size");
+        
+        import.chunks.insert(index, chunk);
+        Ok(import.status(&import_id))
+    })
+}
+
+#[update]
+// 校验并完成导入，导入的备份可通过 restore_from_backup 恢复 (加密的归档需要提供解密密钥)
+pub fn finalize_backup_import(import_id: String, decryption_key: Option<Vec<u8>>) -> Result<BackupImportResult> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, crate::access_control::Permission::SuperAdmin)?;
+    
+    let import = PENDING_IMPORTS.with_borrow(|imports| imports.get(&import_id).cloned())
+        .ok_or(Error::InvalidArgument(format!("Import {} not found", import_id)))?;
+    
+    if import.chunks.len() as u32 != import.manifest.total_chunks {
+        return Err(Error::InvalidState(format!(
+            "Missing chunks: received {} of {}", import.chunks.len(), import.manifest.total_chunks
+        )));
+    }
+    
+    let bytes: Vec<u8> = import.chunks.into_values().flatten().collect();
+    if bytes.len() as u64 != import.manifest.total_bytes {
+        return Err(Error::InvalidState(format!(
+            "Size mismatch: expected {} bytes, received {}", import.manifest.total_bytes, bytes.len()
+        )));
+    }
+    
+    let checksum = sha256_hex(&bytes);
+    if checksum != import.manifest.checksum.to_lowercase() {
+        return Err(Error::InvalidState("Backup checksum mismatch".to_string()));
+    }
+    
+    let result = BackupManager::import_archive(&bytes, decryption_key.as_deref())?;
+    
+    PENDING_IMPORTS.with_borrow_mut(|imports| {
+        imports.remove(&import_id);
+    });
+    
+    crate::error::audit_log(
+        "finalize_backup_import",
+        &caller.to_string(),
+        &format!("import_id={}, backup_id={}, source_canister={}, started_at={}",
+                 import_id, result.metadata.id, result.source_canister, import.started_at)
+    );
+    
+    Ok(result)
+}
+
+#[update]
+// 取消进行中的导入
+pub fn cancel_backup_import(import_id: String) -> Result<()> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, crate::access_control::Permission::SuperAdmin)?;
+    
+    let removed = PENDING_IMPORTS.with_borrow_mut(|imports| imports.remove(&import_id));
+    if removed.is_none() {
+        return Err(Error::InvalidArgument(format!("Import {} not found", import_id)));
+    }
+    
+    crate::error::audit_log("cancel_backup_import", &caller.to_string(), &format!("import_id={}", import_id));
+    
+    Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,270 @@
+// bip322.rs - BIP-322 消息签名验证
+// 这个模块实现 BIP-322 simple/full 格式的签名验证，支持 P2WPKH (ECDSA) 和 P2TR (Schnorr) 地址
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use bitcoin::{
+    Amount, OutPoint, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+    absolute::LockTime,
+    blockdata::{opcodes::all::{OP_PUSHBYTES_0, OP_RETURN}, script::Builder},
+    consensus::deserialize,
+    hashes::{Hash, HashEngine, sha256},
+    key::XOnlyPublicKey,
+    secp256k1::{Message, Secp256k1},
+    sighash::{EcdsaSighashType, Prevouts, SighashCache},
+    transaction::Version,
+    PublicKey,
+};
+
+// BIP-322 消息哈希标签
+const BIP322_TAG: &[u8] = b"BIP0322-signed-message";
+
+// 计算 BIP-322 消息哈希 (tagged hash)
+pub fn message_hash(message: &[u8]) -> [u8; 32] {
+    let tag_hash = sha256::Hash::hash(BIP322_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag_hash.as_ref());
+    engine.input(tag_hash.as_ref());
+    engine.input(message);
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+// 构建 to_spend 虚拟交易
+pub fn to_spend_tx(script_pubkey: &Script, message: &[u8]) -> Transaction {
+    let script_sig = Builder::new()
+        .push_opcode(OP_PUSHBYTES_0)
+        .push_slice(message_hash(message))
+        .into_script();
+
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint { txid: Txid::all_zeros(), vout: 0xFFFF_FFFF },
+            script_sig,
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: script_pubkey.to_owned(),
+        }],
+    }
+}
+
+// 构建 to_sign 虚拟交易 (simple 格式只携带见证数据)
+pub fn to_sign_tx(to_spend_txid: Txid, witness: Witness) -> Transaction {
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint { txid: to_spend_txid, vout: 0 },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness,
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
+// 验证 BIP-322 签名，自动识别 simple (见证数据) 和 full (完整交易) 格式
+pub fn verify(script_pubkey: &Script, message: &str, signature: &str) -> bool {
+    let signature_bytes = match BASE64.decode(signature) {
+        Ok(bytes) => bytes,
+        Err(_) => return false,
+    };
+
+    let to_spend = to_spend_tx(script_pubkey, message.as_bytes());
+
+    let to_sign = if let Ok(witness) = deserialize::<Witness>(&signature_bytes) {
+        to_sign_tx(to_spend.txid(), witness)
+    } else if let Ok(tx) = deserialize::<Transaction>(&signature_bytes) {
+        if !is_valid_full_to_sign(&tx, to_spend.txid()) {
+            return false;
+        }
+        tx
+    } else {
+        return false;
+    };
+
+    verify_to_sign(&to_sign, script_pubkey)
+}
+
+// 检查 full 格式的 to_sign 交易结构 (不支持资金证明的额外输入)
+fn is_valid_full_to_sign(tx: &Transaction, to_spend_txid: Txid) -> bool {
+    tx.input.len() == 1
+        && tx.input[0].previous_output == (OutPoint { txid: to_spend_txid, vout: 0 })
+        && tx.output.len() == 1
+        && tx.output[0].value == Amount::ZERO
+        && tx.output[0].script_pubkey.as_bytes() == [OP_RETURN.to_u8()]
+}
+
+// 根据脚本类型验证 to_sign 交易的第一个输入
+fn verify_to_sign(to_sign: &Transaction, script_pubkey: &Script) -> bool {
+    if script_pubkey.is_p2wpkh() {
+        verify_p2wpkh(to_sign, script_pubkey)
+    } else if script_pubkey.is_p2tr() {
+        verify_p2tr(to_sign, script_pubkey)
+    } else {
+        false
+    }
+}
+
+// 验证 P2WPKH 签名 (BIP-143 sighash + ECDSA)
+fn verify_p2wpkh(to_sign: &Transaction, script_pubkey: &Script) -> bool {
+    let witness = &to_sign.input[0].witness;
+    if witness.len() != 2 || !to_sign.input[0].script_sig.is_empty() {
+        return false;
+    }
+
+    let (signature_bytes, pubkey_bytes) = match (witness.nth(0), witness.nth(1)) {
+        (Some(sig), Some(pk)) => (sig, pk),
+        _ => return false,
+    };
+
+    let pubkey = match PublicKey::from_slice(pubkey_bytes) {
+        Ok(pk) if pk.compressed => pk,
+        _ => return false,
+    };
+
+    // 公钥必须与地址的见证程序匹配
+    match pubkey.wpubkey_hash() {
+        Some(hash) if ScriptBuf::new_p2wpkh(&hash).as_script() == script_pubkey => {}
+        _ => return false,
+    }
+
+    let signature = match bitcoin::ecdsa::Signature::from_slice(signature_bytes) {
+        Ok(sig) if sig.hash_ty == EcdsaSighashType::All => sig,
+        _ => return false,
+    };
+
+    let sighash = match SighashCache::new(to_sign)
+        .p2wpkh_signature_hash(0, script_pubkey, Amount::ZERO, signature.hash_ty) {
+        Ok(hash) => hash,
+        Err(_) => return false,
+    };
+
+    let mut sig = signature.sig;
+    sig.normalize_s();
+
+    Secp256k1::verification_only()
+        .verify_ecdsa(&Message::from_digest(sighash.to_byte_array()), &sig, &pubkey.inner)
+        .is_ok()
+}
+
+// 验证 P2TR 密钥路径签名 (BIP-341 sighash + Schnorr)
+fn verify_p2tr(to_sign: &Transaction, script_pubkey: &Script) -> bool {
+    let witness = &to_sign.input[0].witness;
+    if witness.len() != 1 || !to_sign.input[0].script_sig.is_empty() {
+        return false;
+    }
+
+    let signature = match witness.nth(0).map(bitcoin::taproot::Signature::from_slice) {
+        Some(Ok(sig)) => sig,
+        _ => return false,
+    };
+
+    // 输出公钥即为地址见证程序
+    let output_key = match XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..]) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+
+    let prevouts = [TxOut { value: Amount::ZERO, script_pubkey: script_pubkey.to_owned() }];
+    let sighash = match SighashCache::new(to_sign)
+        .taproot_key_spend_signature_hash(0, &Prevouts::All(&prevouts), signature.hash_ty) {
+        Ok(hash) => hash,
+        Err(_) => return false,
+    };
+
+    Secp256k1::verification_only()
+        .verify_schnorr(&signature.sig, &Message::from_digest(sighash.to_byte_array()), &output_key)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Address;
+    use std::str::FromStr;
+
+    // BIP-322 测试向量使用的地址 (私钥 L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k)
+    const P2WPKH_ADDRESS: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+    const P2TR_ADDRESS: &str = "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3";
+
+    fn script_pubkey(address: &str) -> ScriptBuf {
+        Address::from_str(address).unwrap().assume_checked().script_pubkey()
+    }
+
+    #[test]
+    fn test_message_hash_vectors() {
+        assert_eq!(
+            hex::encode(message_hash(b"")),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            hex::encode(message_hash(b"Hello World")),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+    }
+
+    #[test]
+    fn test_transaction_vectors() {
+        let script = script_pubkey(P2WPKH_ADDRESS);
+
+        let to_spend = to_spend_tx(&script, b"");
+        assert_eq!(to_spend.txid().to_string(), "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7");
+        assert_eq!(to_sign_tx(to_spend.txid(), Witness::new()).txid().to_string(),
+                   "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6");
+
+        let to_spend = to_spend_tx(&script, b"Hello World");
+        assert_eq!(to_spend.txid().to_string(), "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b");
+        assert_eq!(to_sign_tx(to_spend.txid(), Witness::new()).txid().to_string(),
+                   "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf");
+    }
+
+    #[test]
+    fn test_p2wpkh_simple_vectors() {
+        let script = script_pubkey(P2WPKH_ADDRESS);
+
+        assert!(verify(&script, "",
+            "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="));
+        assert!(verify(&script, "Hello World",
+            "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="));
+
+        // 签名与消息不匹配
+        assert!(!verify(&script, "Hello World",
+            "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="));
+    }
+
+    #[test]
+    fn test_p2tr_simple_vector() {
+        let script = script_pubkey(P2TR_ADDRESS);
+        let signature = "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==";
+
+        assert!(verify(&script, "Hello World", signature));
+        assert!(!verify(&script, "Hello World!", signature));
+
+        // 签名不能用于其他地址
+        assert!(!verify(&script_pubkey(P2WPKH_ADDRESS), "Hello World", signature));
+    }
+
+    #[test]
+    fn test_full_format() {
+        let script = script_pubkey(P2WPKH_ADDRESS);
+        let simple = BASE64.decode(
+            "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="
+        ).unwrap();
+        let witness: Witness = deserialize(&simple).unwrap();
+
+        // 将 simple 签名组装成完整的 to_sign 交易
+        let to_spend = to_spend_tx(&script, b"Hello World");
+        let to_sign = to_sign_tx(to_spend.txid(), witness);
+        let full = BASE64.encode(bitcoin::consensus::serialize(&to_sign));
+
+        assert!(verify(&script, "Hello World", &full));
+        assert!(!verify(&script, "", &full));
+    }
+}
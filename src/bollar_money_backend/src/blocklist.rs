@@ -0,0 +1,244 @@
+// blocklist.rs - 制裁名单
+// 这个模块实现持久化的 principal/地址黑名单、管理接口和执行检查
+
+use crate::{Error, Result, ic_api};
+use crate::access_control::Permission;
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::{
+    StableBTreeMap, Storable,
+    memory_manager::MemoryId,
+    storable::Bound,
+};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::str::FromStr;
+
+// 单次批量导入的最大条目数
+const MAX_IMPORT_ENTRIES: usize = 10_000;
+
+// 被封禁的对象
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum BlockedEntity {
+    Principal(Principal),
+    Address(String),
+}
+
+impl BlockedEntity {
+    // 解析 principal 文本或比特币地址
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Ok(principal) = Principal::from_text(value) {
+            return Some(BlockedEntity::Principal(principal));
+        }
+        if bitcoin::Address::from_str(value).is_ok() {
+            return Some(BlockedEntity::Address(value.to_string()));
+        }
+        None
+    }
+
+    // 存储键
+    fn key(&self) -> String {
+        match self {
+            BlockedEntity::Principal(principal) => format!("principal:{}", principal),
+            BlockedEntity::Address(address) => format!("address:{}", address),
+        }
+    }
+}
+
+// 黑名单条目
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct BlocklistEntry {
+    pub entity: BlockedEntity,
+    pub reason: String,
+    pub added_by: String,
+    pub added_at: u64,
+}
+
+impl Storable for BlocklistEntry {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode BlocklistEntry")
+    }
+}
+
+// 批量导入结果
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, Default)]
+pub struct BlocklistImportResult {
+    pub added: u64,                  // 新增条目数
+    pub skipped: u64,                // 已存在的条目数
+    pub invalid: Vec<String>,        // 无法解析的条目
+}
+
+thread_local! {
+    // 黑名单存储 ("principal:<text>" / "address:<addr>" -> BlocklistEntry)
+    static BLOCKLIST: RefCell<StableBTreeMap<String, BlocklistEntry, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(13))),
+        )
+    );
+}
+
+// 检查对象是否在黑名单中
+pub fn is_entity_blocked(entity: &BlockedEntity) -> bool {
+    BLOCKLIST.with_borrow(|list| list.contains_key(&entity.key()))
+}
+
+// 检查 principal 是否在黑名单中
+pub fn is_principal_blocked(principal: &Principal) -> bool {
+    is_entity_blocked(&BlockedEntity::Principal(*principal))
+}
+
+// 检查地址是否在黑名单中
+pub fn is_address_blocked(address: &str) -> bool {
+    is_entity_blocked(&BlockedEntity::Address(address.to_string()))
+}
+
+// 执行检查: 调用者、其关联地址以及操作涉及的地址都不能在黑名单中
+// 被拦截的尝试会写入审计日志
+pub fn ensure_not_blocked(principal: Principal, address: Option<&str>, operation: &str) -> Result<()> {
+    let blocked = if is_principal_blocked(&principal) {
+        Some(format!("principal {}", principal))
+    } else if let Some(address) = address.filter(|a| is_address_blocked(a)) {
+        Some(format!("address {}", address))
+    } else {
+        crate::auth::get_linked_addresses(principal).into_iter()
+            .find(|linked| is_address_blocked(&linked.address))
+            .map(|linked| format!("linked address {}", linked.address))
+    };
+
+    match blocked {
+        Some(detail) => {
+            crate::error::audit_log(
+                "blocked_attempt",
+                &principal.to_string(),
+                &format!("operation={}, matched={}", operation, detail)
+            );
+            Err(Error::PermissionDenied(format!("{}: {} is blocked", operation, detail)))
+        }
+        None => Ok(()),
+    }
+}
+
+#[query]
+// 查询 principal 或地址是否被封禁
+pub fn is_blocked(value: String) -> bool {
+    BlockedEntity::parse(&value)
+        .map(|entity| is_entity_blocked(&entity))
+        .unwrap_or(false)
+}
+
+#[query]
+// 获取完整黑名单 (需要黑名单管理权限)
+pub fn get_blocklist() -> Result<Vec<BlocklistEntry>> {
+    crate::access_control::check_permission(ic_api::caller(), Permission::ManageBlocklist)?;
+
+    Ok(BLOCKLIST.with_borrow(|list| list.iter().map(|(_, entry)| entry).collect()))
+}
+
+#[update]
+// 将 principal 或地址加入黑名单
+pub fn add_to_blocklist(entity: BlockedEntity, reason: String) -> Result<()> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, Permission::ManageBlocklist)?;
+    validate_param!(!reason.trim().is_empty(), "封禁原因不能为空");
+
+    if let BlockedEntity::Address(address) = &entity {
+        validate_param!(bitcoin::Address::from_str(address).is_ok(), format!("无效的比特币地址: {}", address));
+    }
+
+    insert_entry(entity.clone(), &reason, caller);
+
+    crate::error::audit_log(
+        "add_to_blocklist",
+        &caller.to_string(),
+        &format!("entity={}, reason={}", entity.key(), reason)
+    );
+
+    Ok(())
+}
+
+#[update]
+// 将 principal 或地址移出黑名单
+pub fn remove_from_blocklist(entity: BlockedEntity, reason: String) -> Result<()> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, Permission::ManageBlocklist)?;
+    validate_param!(!reason.trim().is_empty(), "解封原因不能为空");
+
+    let removed = BLOCKLIST.with_borrow_mut(|list| list.remove(&entity.key()));
+    if removed.is_none() {
+        return Err(Error::InvalidArgument(format!("{} 不在黑名单中", entity.key())));
+    }
+
+    crate::error::audit_log(
+        "remove_from_blocklist",
+        &caller.to_string(),
+        &format!("entity={}, reason={}", entity.key(), reason)
+    );
+
+    Ok(())
+}
+
+#[update]
+// 批量导入黑名单，每行一个或以逗号分隔，'#' 开头的行为注释
+pub fn import_blocklist(data: String, reason: String) -> Result<BlocklistImportResult> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, Permission::ManageBlocklist)?;
+    validate_param!(!reason.trim().is_empty(), "封禁原因不能为空");
+
+    let values: Vec<&str> = data.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .flat_map(|line| line.split(','))
+        .map(|value| value.trim().trim_matches('"'))
+        .filter(|value| !value.is_empty())
+        .collect();
+
+    validate_param!(
+        values.len() <= MAX_IMPORT_ENTRIES,
+        format!("单次最多导入 {} 个条目", MAX_IMPORT_ENTRIES)
+    );
+
+    let mut result = BlocklistImportResult::default();
+    for value in values {
+        match BlockedEntity::parse(value) {
+            Some(entity) if is_entity_blocked(&entity) => result.skipped += 1,
+            Some(entity) => {
+                insert_entry(entity, &reason, caller);
+                result.added += 1;
+            }
+            None => result.invalid.push(value.to_string()),
+        }
+    }
+
+    crate::error::audit_log(
+        "import_blocklist",
+        &caller.to_string(),
+        &format!("added={}, skipped={}, invalid={}, reason={}",
+                 result.added, result.skipped, result.invalid.len(), reason)
+    );
+
+    Ok(result)
+}
+
+// 写入黑名单条目
+fn insert_entry(entity: BlockedEntity, reason: &str, added_by: Principal) {
+    let entry = BlocklistEntry {
+        entity: entity.clone(),
+        reason: reason.to_string(),
+        added_by: added_by.to_string(),
+        added_at: ic_api::time(),
+    };
+
+    BLOCKLIST.with_borrow_mut(|list| {
+        list.insert(entity.key(), entry);
+    });
+}
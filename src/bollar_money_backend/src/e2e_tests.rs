@@ -26,9 +26,11 @@ mod tests {
 
     // 模拟用户认证
     async fn authenticate_user() -> (String, String) {
-        let address = "bc1qtest123456789".to_string();
-        let signature = "test_signature".to_string();
-        let message = "test_message".to_string();
+        crate::test_utils::set_caller(crate::test_utils::test_principal(1));
+        crate::auth::ensure_session_secret().await.unwrap();
+        let address = crate::test_utils::TEST_P2WPKH_ADDRESS.to_string();
+        let message = crate::auth::request_auth_challenge(address.clone()).await.unwrap().message;
+        let signature = crate::test_utils::sign_bip322_p2wpkh(crate::test_utils::TEST_P2WPKH_WIF, &message);
         
         let auth_result = crate::auth::authenticate(address.clone(), signature, message).unwrap();
         let token = auth_result.token.unwrap();
@@ -256,12 +258,12 @@ mod tests {
         
         // 2. 更新抵押率
         let new_collateral_ratio = 70u8;
-        let result = crate::stability::update_collateral_ratio(new_collateral_ratio);
+        let result = crate::stability::update_system_parameters(Some(new_collateral_ratio), None);
         assert!(result.is_ok(), "Should be able to update collateral ratio");
         
         // 3. 更新清算阈值
         let new_liquidation_threshold = 85u8;
-        let result = crate::stability::update_liquidation_threshold(new_liquidation_threshold);
+        let result = crate::stability::update_system_parameters(None, Some(new_liquidation_threshold));
         assert!(result.is_ok(), "Should be able to update liquidation threshold");
         
         // 4. 验证参数已更新
@@ -4,21 +4,61 @@
 use crate::{Error, LogLevel, Result, error::log_error, ic_api};
 use candid::{CandidType, Deserialize};
 use ic_cdk_macros::{query, update};
+use ic_stable_structures::{StableBTreeMap, StableCell, Storable, memory_manager::MemoryId, storable::Bound};
 use serde::Serialize;
+use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::HashMap;
+
+// 恢复和维护操作所需的默认审批人数 (与 production.toml 中 emergency_multisig_threshold 一致)
+const DEFAULT_EMERGENCY_MULTISIG_THRESHOLD: u8 = 2;
+
+// 待审批紧急操作的有效期 (纳秒)
+const EMERGENCY_APPROVAL_EXPIRY_NS: u64 = 24 * 60 * 60 * 1_000_000_000; // 24小时
 
 // 紧急状态类型
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub enum EmergencyState {
-    Normal,                    // 正常运行
+    Normal,                    // 正常运行 (单项操作可能被暂停标志禁用)
     Paused,                   // 全面暂停
-    DepositPaused,            // 仅暂停存款
-    WithdrawPaused,           // 仅暂停提款
-    LiquidationPaused,        // 仅暂停清算
     MaintenanceMode,          // 维护模式
 }
 
+// 可独立暂停的操作
+#[derive(CandidType, Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PauseOperation {
+    Deposit,                  // 存款和借款
+    Repay,                    // 还款
+    Liquidate,                // 清算
+    Redeem,                   // 赎回/提取 BTC
+    OracleUpdate,             // 价格更新
+    Restructure,              // 合并/拆分头寸
+}
+
+impl PauseOperation {
+    // 从操作名称解析，未列出的操作只受全局状态控制
+    pub fn from_operation(operation: &str) -> Option<Self> {
+        match operation {
+            "deposit" | "borrow" => Some(PauseOperation::Deposit),
+            "repay" => Some(PauseOperation::Repay),
+            "liquidate" => Some(PauseOperation::Liquidate),
+            "redeem" | "withdraw" => Some(PauseOperation::Redeem),
+            "oracle_update" => Some(PauseOperation::OracleUpdate),
+            "merge" | "split" => Some(PauseOperation::Restructure),
+            _ => None,
+        }
+    }
+}
+
+// 暂停标志
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct PauseFlag {
+    pub operation: PauseOperation,
+    pub pool_address: Option<String>,  // None 表示全局暂停
+    pub reason: String,
+    pub operator: String,
+    pub timestamp: u64,
+}
+
 // 紧急控制结构
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct EmergencyControls {
@@ -29,6 +69,79 @@ pub struct EmergencyControls {
     pub auto_resume_time: Option<u64>,
 }
 
+// 需要多人审批的紧急操作
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum EmergencyAction {
+    Resume,                                   // 恢复正常运行
+    Maintenance { duration_hours: u64 },      // 进入维护模式
+    ResumeOperation {                         // 解除单项操作的暂停
+        operation: PauseOperation,
+        pool_address: Option<String>,
+    },
+}
+
+// 待审批的紧急操作
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct PendingEmergencyAction {
+    pub id: u64,
+    pub action: EmergencyAction,
+    pub reason: String,
+    pub proposer: String,
+    pub approvals: Vec<String>,               // 已审批的操作员 (包含发起人)
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub executed: bool,
+}
+
+impl Storable for EmergencyControls {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode EmergencyControls")
+    }
+}
+
+impl Storable for PauseFlag {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode PauseFlag")
+    }
+}
+
+impl Storable for PendingEmergencyAction {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode PendingEmergencyAction")
+    }
+}
+
+impl EmergencyAction {
+    // 是否改变全局紧急状态 (恢复或维护)
+    fn changes_global_state(&self) -> bool {
+        matches!(self, EmergencyAction::Resume | EmergencyAction::Maintenance { .. })
+    }
+}
+
 impl Default for EmergencyControls {
     fn default() -> Self {
         Self {
@@ -42,42 +155,127 @@ impl Default for EmergencyControls {
 }
 
 thread_local! {
-    // 紧急控制状态
-    static EMERGENCY_CONTROLS: RefCell<EmergencyControls> = RefCell::new(EmergencyControls::default());
+    // 紧急控制状态 (稳定内存，升级后保持暂停状态)
+    static EMERGENCY_CONTROLS: RefCell<StableCell<EmergencyControls, crate::Memory>> = RefCell::new(
+        StableCell::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(19))),
+            EmergencyControls::default(),
+        ).expect("Failed to initialize emergency controls")
+    );
+    
+    // 紧急操作员 (principal 文本 -> ())
+    static EMERGENCY_OPERATORS: RefCell<StableBTreeMap<String, (), crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(21))),
+        )
+    );
     
-    // 操作权限映射
-    static EMERGENCY_OPERATORS: RefCell<HashMap<String, bool>> = RefCell::new(HashMap::new());
+    // 单项操作暂停标志 (pause_flag_key(操作, 池地址) -> 暂停标志)
+    static PAUSE_FLAGS: RefCell<StableBTreeMap<String, PauseFlag, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(20))),
+        )
+    );
+    
+    // 待审批的紧急操作
+    static PENDING_EMERGENCY_ACTIONS: RefCell<StableBTreeMap<u64, PendingEmergencyAction, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(22))),
+        )
+    );
+    
+    // 下一个紧急操作 ID
+    static NEXT_EMERGENCY_ACTION_ID: RefCell<StableCell<u64, crate::Memory>> = RefCell::new(
+        StableCell::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(23))),
+            0,
+        ).expect("Failed to initialize emergency action id")
+    );
+    
+    // 恢复和维护操作所需的审批人数
+    static EMERGENCY_MULTISIG_THRESHOLD: RefCell<StableCell<u8, crate::Memory>> = RefCell::new(
+        StableCell::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(24))),
+            DEFAULT_EMERGENCY_MULTISIG_THRESHOLD,
+        ).expect("Failed to initialize emergency threshold")
+    );
 }
 
 #[query]
 // 获取当前紧急状态
 pub fn get_emergency_state() -> EmergencyControls {
-    EMERGENCY_CONTROLS.with_borrow(|controls| controls.clone())
+    EMERGENCY_CONTROLS.with_borrow(|cell| cell.get().clone())
+}
+
+// 修改并持久化紧急控制状态
+fn update_emergency_controls<R>(f: impl FnOnce(&mut EmergencyControls) -> R) -> R {
+    EMERGENCY_CONTROLS.with_borrow_mut(|cell| {
+        let mut controls = cell.get().clone();
+        let result = f(&mut controls);
+        cell.set(controls).expect("Failed to persist emergency controls");
+        result
+    })
 }
 
 #[query]
 // 检查系统是否正常运行
 pub fn is_system_operational() -> bool {
-    EMERGENCY_CONTROLS.with_borrow(|controls| {
-        controls.state == EmergencyState::Normal
-    })
+    get_emergency_state().state == EmergencyState::Normal
 }
 
 #[query]
-// 检查特定操作是否被允许
+// 检查特定操作是否被允许 (全局)
 pub fn is_operation_allowed(operation: String) -> bool {
-    EMERGENCY_CONTROLS.with_borrow(|controls| {
-        match controls.state {
-            EmergencyState::Normal => true,
-            EmergencyState::Paused => false,
-            EmergencyState::DepositPaused => operation != "deposit",
-            EmergencyState::WithdrawPaused => operation != "withdraw" && operation != "repay",
-            EmergencyState::LiquidationPaused => operation != "liquidate",
-            EmergencyState::MaintenanceMode => false,
+    operation_block_reason(&operation, None).is_none()
+}
+
+#[query]
+// 检查特定操作在指定资金池上是否被允许
+pub fn is_operation_allowed_for_pool(operation: String, pool_address: String) -> bool {
+    operation_block_reason(&operation, Some(pool_address)).is_none()
+}
+
+#[query]
+// 获取所有暂停标志
+pub fn get_pause_flags() -> Vec<PauseFlag> {
+    PAUSE_FLAGS.with_borrow(|flags| flags.iter().map(|(_, flag)| flag).collect())
+}
+
+// 暂停标志的存储键，全局标志的池地址部分为空
+fn pause_flag_key(operation: PauseOperation, pool_address: Option<&str>) -> String {
+    format!("{:?}/{}", operation, pool_address.unwrap_or(""))
+}
+
+// 检查单项操作是否被全局或指定池的暂停标志禁用
+pub fn is_operation_paused(operation: PauseOperation, pool_address: Option<&str>) -> bool {
+    find_pause_flag(operation, pool_address).is_some()
+}
+
+// 返回操作被禁止的原因，允许时返回 None
+pub fn operation_block_reason(operation: &str, pool_address: Option<String>) -> Option<String> {
+    let controls = get_emergency_state();
+    if controls.state != EmergencyState::Normal {
+        return Some(format!("{:?} - {}", controls.state, controls.reason));
+    }
+    
+    let pause_operation = PauseOperation::from_operation(operation)?;
+    find_pause_flag(pause_operation, pool_address.as_deref()).map(|flag| {
+        match flag.pool_address {
+            Some(pool) => format!("{:?} paused for pool {} - {}", flag.operation, pool, flag.reason),
+            None => format!("{:?} paused globally - {}", flag.operation, flag.reason),
         }
     })
 }
 
+// 查找生效的暂停标志 (全局标志优先)
+fn find_pause_flag(operation: PauseOperation, pool_address: Option<&str>) -> Option<PauseFlag> {
+    PAUSE_FLAGS.with_borrow(|flags| {
+        flags.get(&pause_flag_key(operation, None)).or_else(|| {
+            pool_address.and_then(|pool| flags.get(&pause_flag_key(operation, Some(pool))))
+        })
+    })
+}
+
 #[update]
 // 紧急暂停系统
 pub fn emergency_pause(reason: String) -> Result<()> {
@@ -93,7 +291,7 @@ pub fn emergency_pause(reason: String) -> Result<()> {
     }
     
     // 设置紧急状态
-    EMERGENCY_CONTROLS.with_borrow_mut(|controls| {
+    update_emergency_controls(|controls| {
         controls.state = EmergencyState::Paused;
         controls.reason = reason.clone();
         controls.timestamp = ic_api::time();
@@ -101,6 +299,15 @@ pub fn emergency_pause(reason: String) -> Result<()> {
         controls.auto_resume_time = None;
     });
     
+    // 暂停之前发起的恢复/维护提案已过时，不能撤销这次暂停
+    invalidate_pending_actions(|action| action.changes_global_state());
+    
+    crate::events::record_event(crate::events::ProtocolEvent::EmergencyStateChanged {
+        state: EmergencyState::Paused,
+        reason: reason.clone(),
+        operator: caller.clone(),
+    });
+    
     // 记录紧急暂停事件
     log_error(
         LogLevel::Error,
@@ -127,61 +334,120 @@ pub fn emergency_partial_pause(operation: String, reason: String) -> Result<()>
         return Err(Error::InvalidArgument("Emergency reason cannot be empty".to_string()));
     }
     
-    // 确定暂停状态
-    let new_state = match operation.as_str() {
-        "deposit" => EmergencyState::DepositPaused,
-        "withdraw" => EmergencyState::WithdrawPaused,
-        "liquidate" => EmergencyState::LiquidationPaused,
-        _ => return Err(Error::InvalidArgument("Invalid operation type".to_string())),
-    };
+    // 确定暂停的操作
+    let pause_operation = PauseOperation::from_operation(&operation)
+        .ok_or(Error::InvalidArgument("Invalid operation type".to_string()))?;
     
-    // 设置紧急状态
-    EMERGENCY_CONTROLS.with_borrow_mut(|controls| {
-        controls.state = new_state;
-        controls.reason = reason.clone();
-        controls.timestamp = ic_api::time();
-        controls.operator = caller.clone();
-        controls.auto_resume_time = None;
-    });
+    set_pause_flag(pause_operation, None, reason, caller);
     
-    // 记录部分暂停事件
-    ic_cdk::println!("EMERGENCY: {} paused by {} - {}", operation, caller, reason);
+    Ok(())
+}
+
+#[update]
+// 暂停单项操作，可限定到某个资金池 (单个操作员即可执行)
+pub fn emergency_pause_operation(
+    operation: PauseOperation,
+    pool_address: Option<String>,
+    reason: String,
+) -> Result<()> {
+    // 验证调用者权限
+    let caller = ic_api::caller().to_string();
+    if !is_emergency_operator(&caller) {
+        return Err(Error::PermissionDenied("Not authorized for emergency operations".to_string()));
+    }
+    
+    // 验证参数
+    if reason.trim().is_empty() {
+        return Err(Error::InvalidArgument("Emergency reason cannot be empty".to_string()));
+    }
+    
+    if let Some(pool) = &pool_address {
+        if crate::get_pool(pool).is_none() {
+            return Err(Error::InvalidPool);
+        }
+    }
+    
+    set_pause_flag(operation, pool_address, reason, caller);
     
     Ok(())
 }
 
 #[update]
-// 恢复系统正常运行
-pub fn emergency_resume(reason: String) -> Result<()> {
+// 发起解除单项操作的暂停 (需要多名操作员审批)
+pub fn emergency_resume_operation(
+    operation: PauseOperation,
+    pool_address: Option<String>,
+    reason: String,
+) -> Result<PendingEmergencyAction> {
     // 验证调用者权限
     let caller = ic_api::caller().to_string();
     if !is_emergency_operator(&caller) {
         return Err(Error::PermissionDenied("Not authorized for emergency operations".to_string()));
     }
     
-    // 验证原因不为空
+    // 验证参数
     if reason.trim().is_empty() {
         return Err(Error::InvalidArgument("Resume reason cannot be empty".to_string()));
     }
     
-    // 恢复正常状态
-    EMERGENCY_CONTROLS.with_borrow_mut(|controls| {
-        controls.state = EmergencyState::Normal;
-        controls.reason = reason.clone();
-        controls.timestamp = ic_api::time();
-        controls.operator = caller.clone();
-        controls.auto_resume_time = None;
+    let exists = PAUSE_FLAGS.with_borrow(|flags| flags.contains_key(&pause_flag_key(operation, pool_address.as_deref())));
+    if !exists {
+        return Err(Error::InvalidArgument("Operation is not paused in this scope".to_string()));
+    }
+    
+    propose_emergency_action(EmergencyAction::ResumeOperation { operation, pool_address }, reason, caller)
+}
+
+// 设置暂停标志
+fn set_pause_flag(operation: PauseOperation, pool_address: Option<String>, reason: String, operator: String) {
+    let scope = pool_address.clone().unwrap_or_else(|| "global".to_string());
+    
+    PAUSE_FLAGS.with_borrow_mut(|flags| {
+        flags.insert(pause_flag_key(operation, pool_address.as_deref()), PauseFlag {
+            operation,
+            pool_address: pool_address.clone(),
+            reason: reason.clone(),
+            operator: operator.clone(),
+            timestamp: ic_api::time(),
+        });
     });
     
-    // 记录恢复事件
-    ic_cdk::println!("EMERGENCY: System resumed by {} - {}", caller, reason);
+    // 之前发起的同一范围的解除提案已过时
+    invalidate_pending_actions(|action| {
+        *action == EmergencyAction::ResumeOperation { operation, pool_address: pool_address.clone() }
+    });
     
-    Ok(())
+    crate::events::record_event(crate::events::ProtocolEvent::OperationPaused {
+        operation,
+        pool_address,
+        reason: reason.clone(),
+        operator: operator.clone(),
+    });
+    
+    // 记录部分暂停事件
+    ic_cdk::println!("EMERGENCY: {:?} paused ({}) by {} - {}", operation, scope, operator, reason);
 }
 
 #[update]
-// 设置维护模式
-pub fn set_maintenance_mode(duration_hours: u64, reason: String) -> Result<()> {
+// 发起恢复系统正常运行 (需要多名操作员审批)
+pub fn emergency_resume(reason: String) -> Result<PendingEmergencyAction> {
+    // 验证调用者权限
+    let caller = ic_api::caller().to_string();
+    if !is_emergency_operator(&caller) {
+        return Err(Error::PermissionDenied("Not authorized for emergency operations".to_string()));
+    }
+    
+    // 验证原因不为空
+    if reason.trim().is_empty() {
+        return Err(Error::InvalidArgument("Resume reason cannot be empty".to_string()));
+    }
+    
+    propose_emergency_action(EmergencyAction::Resume, reason, caller)
+}
+
+#[update]
+// 发起设置维护模式 (需要多名操作员审批)
+pub fn set_maintenance_mode(duration_hours: u64, reason: String) -> Result<PendingEmergencyAction> {
     // 验证调用者权限
     let caller = ic_api::caller().to_string();
     if !is_emergency_operator(&caller) {
@@ -197,25 +463,231 @@ pub fn set_maintenance_mode(duration_hours: u64, reason: String) -> Result<()> {
         return Err(Error::InvalidArgument("Maintenance duration must be 1-72 hours".to_string()));
     }
     
-    // 计算自动恢复时间
-    let auto_resume_time = ic_api::time() + (duration_hours * 60 * 60 * 1_000_000_000);
+    propose_emergency_action(EmergencyAction::Maintenance { duration_hours }, reason, caller)
+}
+
+#[update]
+// 审批待执行的紧急操作，达到审批人数后立即执行
+pub fn approve_emergency_action(action_id: u64) -> Result<PendingEmergencyAction> {
+    // 验证调用者权限
+    let caller = ic_api::caller().to_string();
+    if !is_emergency_operator(&caller) {
+        return Err(Error::PermissionDenied("Not authorized for emergency operations".to_string()));
+    }
+    
+    remove_expired_emergency_actions();
     
-    // 设置维护模式
-    EMERGENCY_CONTROLS.with_borrow_mut(|controls| {
-        controls.state = EmergencyState::MaintenanceMode;
-        controls.reason = reason.clone();
-        controls.timestamp = ic_api::time();
-        controls.operator = caller.clone();
-        controls.auto_resume_time = Some(auto_resume_time);
+    let mut pending = PENDING_EMERGENCY_ACTIONS.with_borrow(|actions| actions.get(&action_id))
+        .ok_or(Error::InvalidArgument("Emergency action not found or expired".to_string()))?;
+    
+    if pending.approvals.contains(&caller) {
+        return Err(Error::InvalidArgument("Emergency action already approved by caller".to_string()));
+    }
+    
+    pending.approvals.push(caller.clone());
+    ic_cdk::println!("EMERGENCY: Action {} approved by {} ({}/{})",
+                     action_id, caller, valid_approval_count(&pending), get_emergency_multisig_threshold());
+    
+    Ok(execute_if_approved(pending))
+}
+
+#[query]
+// 获取所有未过期的待审批紧急操作
+pub fn get_pending_emergency_actions() -> Vec<PendingEmergencyAction> {
+    let now = ic_api::time();
+    PENDING_EMERGENCY_ACTIONS.with_borrow(|actions| {
+        actions.iter()
+            .map(|(_, action)| action)
+            .filter(|action| action.expires_at > now)
+            .collect()
+    })
+}
+
+#[query]
+// 获取恢复和维护操作所需的审批人数
+pub fn get_emergency_multisig_threshold() -> u8 {
+    EMERGENCY_MULTISIG_THRESHOLD.with_borrow(|threshold| *threshold.get())
+}
+
+#[update]
+// 设置恢复和维护操作所需的审批人数
+pub fn set_emergency_multisig_threshold(threshold: u8) -> Result<()> {
+    // 验证调用者是否为控制者
+    let caller = ic_api::caller();
+    if !ic_api::is_controller(&caller) {
+        return Err(Error::PermissionDenied("Only controllers can change the approval threshold".to_string()));
+    }
+    
+    // 至少两人审批，且不能超过现有操作员人数
+    let operator_count = EMERGENCY_OPERATORS.with_borrow(|operators| operators.len());
+    if threshold < 2 {
+        return Err(Error::InvalidArgument("Approval threshold must be at least 2".to_string()));
+    }
+    if threshold as u64 > operator_count {
+        return Err(Error::InvalidArgument(format!(
+            "Approval threshold {} exceeds operator count {}", threshold, operator_count
+        )));
+    }
+    
+    EMERGENCY_MULTISIG_THRESHOLD.with_borrow_mut(|current| {
+        current.set(threshold).expect("Failed to persist emergency threshold");
     });
     
-    // 记录维护模式事件
-    ic_cdk::println!("MAINTENANCE: Mode activated by {} for {} hours - {}", 
-                     caller, duration_hours, reason);
+    ic_cdk::println!("EMERGENCY: Approval threshold set to {} by {}", threshold, caller);
     
     Ok(())
 }
 
+// 创建待审批的紧急操作，发起人自动计入审批
+fn propose_emergency_action(action: EmergencyAction, reason: String, proposer: String) -> Result<PendingEmergencyAction> {
+    remove_expired_emergency_actions();
+    
+    let id = NEXT_EMERGENCY_ACTION_ID.with_borrow_mut(|next| {
+        let id = *next.get();
+        next.set(id + 1).expect("Failed to persist emergency action id");
+        id
+    });
+    
+    let now = ic_api::time();
+    let pending = PendingEmergencyAction {
+        id,
+        action,
+        reason,
+        proposer: proposer.clone(),
+        approvals: vec![proposer],
+        created_at: now,
+        expires_at: now + EMERGENCY_APPROVAL_EXPIRY_NS,
+        executed: false,
+    };
+    
+    ic_cdk::println!("EMERGENCY: Action {} ({:?}) proposed by {} - {}",
+                     id, pending.action, pending.proposer, pending.reason);
+    
+    Ok(execute_if_approved(pending))
+}
+
+// 审批人数达到阈值时执行操作，否则保存为待审批
+// 只统计仍是紧急操作员的审批人，已被移除的操作员的审批不再有效
+fn execute_if_approved(mut pending: PendingEmergencyAction) -> PendingEmergencyAction {
+    if valid_approval_count(&pending) < get_emergency_multisig_threshold() as usize {
+        PENDING_EMERGENCY_ACTIONS.with_borrow_mut(|actions| {
+            actions.insert(pending.id, pending.clone());
+        });
+        return pending;
+    }
+    
+    let operators = pending.approvals.iter()
+        .filter(|approver| is_emergency_operator(approver))
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(",");
+    match &pending.action {
+        EmergencyAction::Resume => {
+            // 恢复正常状态
+            update_emergency_controls(|controls| {
+                controls.state = EmergencyState::Normal;
+                controls.reason = pending.reason.clone();
+                controls.timestamp = ic_api::time();
+                controls.operator = operators.clone();
+                controls.auto_resume_time = None;
+            });
+            
+            crate::events::record_event(crate::events::ProtocolEvent::EmergencyStateChanged {
+                state: EmergencyState::Normal,
+                reason: pending.reason.clone(),
+                operator: operators.clone(),
+            });
+            
+            // 记录恢复事件
+            ic_cdk::println!("EMERGENCY: System resumed by {} - {}", operators, pending.reason);
+        }
+        EmergencyAction::Maintenance { duration_hours } => {
+            // 计算自动恢复时间
+            let auto_resume_time = ic_api::time() + (*duration_hours * 60 * 60 * 1_000_000_000);
+            
+            // 设置维护模式
+            update_emergency_controls(|controls| {
+                controls.state = EmergencyState::MaintenanceMode;
+                controls.reason = pending.reason.clone();
+                controls.timestamp = ic_api::time();
+                controls.operator = operators.clone();
+                controls.auto_resume_time = Some(auto_resume_time);
+            });
+            
+            crate::events::record_event(crate::events::ProtocolEvent::EmergencyStateChanged {
+                state: EmergencyState::MaintenanceMode,
+                reason: pending.reason.clone(),
+                operator: operators.clone(),
+            });
+            
+            // 记录维护模式事件
+            ic_cdk::println!("MAINTENANCE: Mode activated by {} for {} hours - {}", 
+                             operators, duration_hours, pending.reason);
+        }
+        EmergencyAction::ResumeOperation { operation, pool_address } => {
+            PAUSE_FLAGS.with_borrow_mut(|flags| {
+                flags.remove(&pause_flag_key(*operation, pool_address.as_deref()));
+            });
+            
+            crate::events::record_event(crate::events::ProtocolEvent::OperationResumed {
+                operation: *operation,
+                pool_address: pool_address.clone(),
+                operator: operators.clone(),
+            });
+            
+            ic_cdk::println!("EMERGENCY: {:?} resumed ({}) by {} - {}",
+                             operation, pool_address.as_deref().unwrap_or("global"), operators, pending.reason);
+        }
+    }
+    
+    PENDING_EMERGENCY_ACTIONS.with_borrow_mut(|actions| {
+        actions.remove(&pending.id);
+    });
+    
+    // 新的全局状态生效后，其他尚未执行的恢复/维护提案已过时
+    if pending.action.changes_global_state() {
+        invalidate_pending_actions(|action| action.changes_global_state());
+    }
+    
+    pending.executed = true;
+    pending
+}
+
+// 当前仍有效的审批人数
+fn valid_approval_count(pending: &PendingEmergencyAction) -> usize {
+    pending.approvals.iter()
+        .filter(|approver| is_emergency_operator(approver))
+        .count()
+}
+
+// 作废满足条件的待审批操作
+fn invalidate_pending_actions(predicate: impl Fn(&EmergencyAction) -> bool) {
+    PENDING_EMERGENCY_ACTIONS.with_borrow_mut(|actions| {
+        let stale: Vec<u64> = actions.iter()
+            .filter(|(_, pending)| predicate(&pending.action))
+            .map(|(id, _)| id)
+            .collect();
+        for id in stale {
+            actions.remove(&id);
+            ic_cdk::println!("EMERGENCY: Pending action {} invalidated by a newer emergency action", id);
+        }
+    });
+}
+
+// 清理过期的待审批操作
+pub(crate) fn remove_expired_emergency_actions() {
+    let now = ic_api::time();
+    PENDING_EMERGENCY_ACTIONS.with_borrow_mut(|actions| {
+        let expired: Vec<u64> = actions.iter()
+            .filter(|(_, action)| action.expires_at <= now)
+            .map(|(id, _)| id)
+            .collect();
+        for id in expired {
+            actions.remove(&id);
+        }
+    });
+}
+
 #[update]
 // 添加紧急操作员
 pub fn add_emergency_operator(operator: String) -> Result<()> {
@@ -232,7 +704,7 @@ pub fn add_emergency_operator(operator: String) -> Result<()> {
     
     // 添加操作员
     EMERGENCY_OPERATORS.with_borrow_mut(|operators| {
-        operators.insert(operator.clone(), true);
+        operators.insert(operator.clone(), ());
     });
     
     ic_cdk::println!("Emergency operator added: {}", operator);
@@ -250,6 +722,16 @@ pub fn remove_emergency_operator(operator: String) -> Result<()> {
     }
     
     // 移除操作员
+    // 移除后剩余操作员不能少于审批人数，否则恢复操作将无法达到阈值
+    let threshold = get_emergency_multisig_threshold() as u64;
+    let operator_count = EMERGENCY_OPERATORS.with_borrow(|operators| operators.len());
+    let is_operator = EMERGENCY_OPERATORS.with_borrow(|operators| operators.contains_key(&operator));
+    if is_operator && operator_count >= threshold && operator_count - 1 < threshold {
+        return Err(Error::InvalidArgument(format!(
+            "Removing {} would leave fewer operators than the approval threshold", operator
+        )));
+    }
+    
     let removed = EMERGENCY_OPERATORS.with_borrow_mut(|operators| {
         operators.remove(&operator).is_some()
     });
@@ -271,7 +753,7 @@ pub fn get_emergency_operators() -> Vec<String> {
     }
     
     EMERGENCY_OPERATORS.with_borrow(|operators| {
-        operators.keys().cloned().collect()
+        operators.iter().map(|(operator, _)| operator).collect()
     })
 }
 
@@ -289,46 +771,54 @@ fn is_emergency_operator(operator: &str) -> bool {
     
     // 检查是否在操作员列表中
     EMERGENCY_OPERATORS.with_borrow(|operators| {
-        operators.get(operator).copied().unwrap_or(false)
+        operators.contains_key(&operator.to_string())
     })
 }
 
 // 自动检查和恢复维护模式
 pub fn check_auto_resume() {
-    EMERGENCY_CONTROLS.with_borrow_mut(|controls| {
-        if controls.state == EmergencyState::MaintenanceMode {
-            if let Some(resume_time) = controls.auto_resume_time {
-                let current_time = ic_api::time();
-                if current_time >= resume_time {
-                    controls.state = EmergencyState::Normal;
-                    controls.reason = "Auto-resumed from maintenance mode".to_string();
-                    controls.timestamp = current_time;
-                    controls.operator = "system".to_string();
-                    controls.auto_resume_time = None;
-                    
-                    ic_cdk::println!("MAINTENANCE: Auto-resumed from maintenance mode");
-                }
-            }
-        }
+    let controls = get_emergency_state();
+    let due = controls.state == EmergencyState::MaintenanceMode
+        && controls.auto_resume_time.is_some_and(|resume_time| ic_api::time() >= resume_time);
+    if !due {
+        return;
+    }
+    
+    let current_time = ic_api::time();
+    update_emergency_controls(|controls| {
+        controls.state = EmergencyState::Normal;
+        controls.reason = "Auto-resumed from maintenance mode".to_string();
+        controls.timestamp = current_time;
+        controls.operator = "system".to_string();
+        controls.auto_resume_time = None;
     });
+    
+    crate::events::record_event(crate::events::ProtocolEvent::EmergencyStateChanged {
+        state: EmergencyState::Normal,
+        reason: "Auto-resumed from maintenance mode".to_string(),
+        operator: "system".to_string(),
+    });
+    
+    ic_cdk::println!("MAINTENANCE: Auto-resumed from maintenance mode");
 }
 
 // 操作前检查宏
 #[macro_export]
 macro_rules! check_emergency_state {
     ($operation:expr) => {
-        if !$crate::emergency::is_operation_allowed($operation.to_string()) {
-            let state = $crate::emergency::get_emergency_state();
+        if let Some(reason) = $crate::emergency::operation_block_reason(&$operation.to_string(), None) {
             return Err($crate::Error::SystemError(format!(
-                "Operation '{}' is not allowed in current state: {:?} - {}",
-                $operation, state.state, state.reason
+                "Operation '{}' is not allowed: {}",
+                $operation, reason
+            )));
+        }
+    };
+    ($operation:expr, $pool_address:expr) => {
+        if let Some(reason) = $crate::emergency::operation_block_reason(&$operation.to_string(), Some($pool_address.to_string())) {
+            return Err($crate::Error::SystemError(format!(
+                "Operation '{}' is not allowed: {}",
+                $operation, reason
             )));
         }
     };
-}
-
-// 心跳函数中调用的检查
-#[ic_cdk_macros::heartbeat]
-async fn emergency_heartbeat() {
-    check_auto_resume();
 }
\ No newline at end of file
@@ -291,8 +291,16 @@ pub fn sanitize_log_message(message: &str) -> String {
 /// 操作审计日志
 pub fn audit_log(operation: &str, user: &str, details: &str) {
     let timestamp = crate::ic_api::time();
-    let sanitized_details = sanitize_log_message(details);
     
+    // 只对公开的调试输出脱敏
     ic_cdk::println!("[AUDIT] {} | User: {} | Operation: {} | Details: {}", 
-                     timestamp, user, operation, sanitized_details);
+                     timestamp, user, operation, sanitize_log_message(details));
+    
+    // 写入稳定内存中的哈希链审计日志，保留完整的地址、交易 ID 和校验和 (仅管理员可查询)
+    crate::audit::append_audit_entry(
+        crate::secure_logging::LogCategory::Audit,
+        operation,
+        user,
+        details
+    );
 }
\ No newline at end of file
@@ -0,0 +1,315 @@
+// events.rs - 协议事件流
+// 这个模块为每个改变状态的操作记录带递增序号的结构化事件，供索引器增量同步
+
+use crate::emergency::{EmergencyState, PauseOperation};
+use crate::ic_api;
+use candid::{CandidType, Deserialize};
+use ic_cdk_macros::query;
+use ic_stable_structures::{
+    StableLog, Storable,
+    memory_manager::MemoryId,
+    storable::Bound,
+};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+// 单次查询返回的最大事件数
+const MAX_EVENTS_PER_PAGE: u64 = 1_000;
+
+// 单次查询最多扫描的事件数
+const MAX_SCAN_EVENTS: u64 = 10_000;
+
+// 协议事件
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum ProtocolEvent {
+    PositionOpened {
+        position_id: String,
+        owner: String,
+        pool_address: String,
+        btc_collateral: u64,
+        bollar_debt: u64,
+        btc_price: u64,
+    },
+    Borrowed {
+        position_id: String,
+        owner: String,
+        pool_address: String,
+        bollar_amount: u64,
+        new_bollar_debt: u64,
+        btc_price: u64,
+    },
+    Repaid {
+        position_id: String,
+        owner: String,
+        pool_address: String,
+        bollar_repaid: u64,
+        btc_returned: u64,
+        remaining_debt: u64,
+        closed: bool,
+    },
+    Liquidated {
+        position_id: String,
+        owner: String,
+        pool_address: String,
+        liquidator: String,
+        bollar_repaid: u64,
+        btc_seized: u64,
+        remaining_debt: u64,
+        closed: bool,
+    },
+    PositionTransferred {
+        position_id: String,
+        pool_address: String,
+        from: String,
+        to: String,
+    },
+    PositionsMerged {
+        position_id: String,             // 合并后保留的头寸
+        merged_position_ids: Vec<String>, // 被并入并删除的头寸
+        owner: String,
+        pool_address: String,
+        btc_collateral: u64,
+        bollar_debt: u64,
+        btc_price: u64,
+    },
+    PositionSplit {
+        position_id: String,             // 被拆分的原头寸
+        new_position_id: String,         // 新建的子头寸
+        owner: String,
+        pool_address: String,
+        btc_amount: u64,
+        bollar_amount: u64,
+        remaining_btc_collateral: u64,
+        remaining_bollar_debt: u64,
+        btc_price: u64,
+    },
+    StateRestored {
+        backup_id: String,
+        target_time: Option<u64>,        // None 表示恢复到备份链末端
+        restored_pools: Vec<String>,     // 被覆盖为备份状态的资金池
+        restored_positions: Vec<String>, // 被覆盖为备份状态的头寸
+        removed_pools: Vec<String>,      // 目标时间点不存在而被删除的资金池
+        removed_positions: Vec<String>,  // 目标时间点不存在而被删除的头寸
+        operator: String,
+    },
+    ParametersChanged {
+        old_collateral_ratio: u8,
+        new_collateral_ratio: u8,
+        old_liquidation_threshold: u8,
+        new_liquidation_threshold: u8,
+    },
+    PoolStateCommitted {
+        pool_address: String,
+        txid: String,
+        action: String,
+    },
+    PoolStateRolledBack {
+        pool_address: String,
+        txid: String,
+    },
+    PoolStateFinalized {
+        pool_address: String,
+        txid: String,
+    },
+    EmergencyStateChanged {
+        state: EmergencyState,
+        reason: String,
+        operator: String,
+    },
+    OperationPaused {
+        operation: PauseOperation,
+        pool_address: Option<String>,
+        reason: String,
+        operator: String,
+    },
+    OperationResumed {
+        operation: PauseOperation,
+        pool_address: Option<String>,
+        operator: String,
+    },
+}
+
+// 事件类型 (用于过滤)
+#[derive(CandidType, Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum EventKind {
+    PositionOpened,
+    Borrowed,
+    Repaid,
+    Liquidated,
+    PositionTransferred,
+    PositionsMerged,
+    PositionSplit,
+    StateRestored,
+    ParametersChanged,
+    PoolStateCommitted,
+    PoolStateRolledBack,
+    PoolStateFinalized,
+    EmergencyStateChanged,
+    OperationPaused,
+    OperationResumed,
+}
+
+impl ProtocolEvent {
+    // 事件类型
+    pub fn kind(&self) -> EventKind {
+        match self {
+            ProtocolEvent::PositionOpened { .. } => EventKind::PositionOpened,
+            ProtocolEvent::Borrowed { .. } => EventKind::Borrowed,
+            ProtocolEvent::Repaid { .. } => EventKind::Repaid,
+            ProtocolEvent::Liquidated { .. } => EventKind::Liquidated,
+            ProtocolEvent::PositionTransferred { .. } => EventKind::PositionTransferred,
+            ProtocolEvent::PositionsMerged { .. } => EventKind::PositionsMerged,
+            ProtocolEvent::PositionSplit { .. } => EventKind::PositionSplit,
+            ProtocolEvent::StateRestored { .. } => EventKind::StateRestored,
+            ProtocolEvent::ParametersChanged { .. } => EventKind::ParametersChanged,
+            ProtocolEvent::PoolStateCommitted { .. } => EventKind::PoolStateCommitted,
+            ProtocolEvent::PoolStateRolledBack { .. } => EventKind::PoolStateRolledBack,
+            ProtocolEvent::PoolStateFinalized { .. } => EventKind::PoolStateFinalized,
+            ProtocolEvent::EmergencyStateChanged { .. } => EventKind::EmergencyStateChanged,
+            ProtocolEvent::OperationPaused { .. } => EventKind::OperationPaused,
+            ProtocolEvent::OperationResumed { .. } => EventKind::OperationResumed,
+        }
+    }
+
+    // 事件涉及的头寸 (合并和拆分事件涉及多个头寸)
+    pub fn position_ids(&self) -> Vec<&str> {
+        match self {
+            ProtocolEvent::PositionOpened { position_id, .. }
+            | ProtocolEvent::Borrowed { position_id, .. }
+            | ProtocolEvent::Repaid { position_id, .. }
+            | ProtocolEvent::Liquidated { position_id, .. }
+            | ProtocolEvent::PositionTransferred { position_id, .. } => vec![position_id],
+            ProtocolEvent::PositionsMerged { position_id, merged_position_ids, .. } => {
+                std::iter::once(position_id).chain(merged_position_ids).map(String::as_str).collect()
+            }
+            ProtocolEvent::PositionSplit { position_id, new_position_id, .. } => vec![position_id, new_position_id],
+            ProtocolEvent::StateRestored { restored_positions, removed_positions, .. } => {
+                restored_positions.iter().chain(removed_positions).map(String::as_str).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    // 事件涉及的资金池
+    pub fn pool_address(&self) -> Option<&str> {
+        match self {
+            ProtocolEvent::PositionOpened { pool_address, .. }
+            | ProtocolEvent::Borrowed { pool_address, .. }
+            | ProtocolEvent::Repaid { pool_address, .. }
+            | ProtocolEvent::Liquidated { pool_address, .. }
+            | ProtocolEvent::PositionTransferred { pool_address, .. }
+            | ProtocolEvent::PositionsMerged { pool_address, .. }
+            | ProtocolEvent::PositionSplit { pool_address, .. }
+            | ProtocolEvent::PoolStateCommitted { pool_address, .. }
+            | ProtocolEvent::PoolStateRolledBack { pool_address, .. }
+            | ProtocolEvent::PoolStateFinalized { pool_address, .. } => Some(pool_address),
+            ProtocolEvent::OperationPaused { pool_address, .. }
+            | ProtocolEvent::OperationResumed { pool_address, .. } => pool_address.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+// 带序号的事件记录
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct EventRecord {
+    pub seq: u64,
+    pub timestamp: u64,
+    pub event: ProtocolEvent,
+}
+
+impl Storable for EventRecord {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode EventRecord")
+    }
+}
+
+// 事件过滤条件
+#[derive(CandidType, Clone, Debug, Default, Deserialize, Serialize)]
+pub struct EventFilter {
+    pub kinds: Option<Vec<EventKind>>,
+    pub position_id: Option<String>,
+    pub pool_address: Option<String>,
+}
+
+impl EventFilter {
+    // 检查事件是否匹配
+    fn matches(&self, event: &ProtocolEvent) -> bool {
+        self.kinds.as_ref().map_or(true, |kinds| kinds.contains(&event.kind()))
+            && self.position_id.as_deref().map_or(true, |id| event.position_ids().contains(&id))
+            && self.pool_address.as_deref().map_or(true, |pool| event.pool_address() == Some(pool))
+    }
+}
+
+// 事件分页结果
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct EventPage {
+    pub events: Vec<EventRecord>,
+    pub next_seq: u64,         // 下次查询的起始序号
+    pub latest_seq: Option<u64>,
+}
+
+thread_local! {
+    // 事件日志 (索引和数据分别占用一块内存)
+    static EVENT_LOG: RefCell<StableLog<EventRecord, crate::Memory, crate::Memory>> = RefCell::new(
+        StableLog::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(17))),
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(18))),
+        ).expect("Failed to initialize event log")
+    );
+}
+
+// 记录协议事件，返回事件序号
+pub fn record_event(event: ProtocolEvent) -> u64 {
+    EVENT_LOG.with_borrow_mut(|log| {
+        let record = EventRecord {
+            seq: log.len(),
+            timestamp: ic_api::time(),
+            event,
+        };
+        log.append(&record).expect("Failed to append protocol event")
+    })
+}
+
+// 事件总数
+pub fn event_count() -> u64 {
+    EVENT_LOG.with_borrow(|log| log.len())
+}
+
+#[query]
+// 从 from_seq 开始增量获取事件，索引器用返回的 next_seq 继续查询
+pub fn get_events(from_seq: u64, limit: Option<u64>, filter: Option<EventFilter>) -> EventPage {
+    let limit = limit.unwrap_or(100).clamp(1, MAX_EVENTS_PER_PAGE) as usize;
+    let filter = filter.unwrap_or_default();
+
+    EVENT_LOG.with_borrow(|log| {
+        let total = log.len();
+        let scan_end = from_seq.saturating_add(MAX_SCAN_EVENTS).min(total);
+
+        let mut events = Vec::new();
+        let mut seq = from_seq;
+        while seq < scan_end && events.len() < limit {
+            if let Some(record) = log.get(seq) {
+                if filter.matches(&record.event) {
+                    events.push(record);
+                }
+            }
+            seq += 1;
+        }
+
+        EventPage {
+            events,
+            next_seq: seq,
+            latest_seq: total.checked_sub(1),
+        }
+    })
+}
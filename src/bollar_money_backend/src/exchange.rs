@@ -1,7 +1,7 @@
 // exchange.rs - REE 交互接口实现
 // 这个模块将实现与 REE 的交互，包括交易执行、回滚和区块处理
 
-use crate::{ExecuteTxGuard, Error, LogLevel, Result, error::log_error};
+use crate::{CombinedGuard, ExecuteTxGuard, Error, LogLevel, Result, error::log_error};
 use ic_cdk_macros::{query, update};
 use ree_types::{
     CoinBalance, CoinId, Intention, bitcoin::psbt::Psbt, bitcoin::Network,
@@ -103,17 +103,19 @@ pub fn rollback_tx(args: RollbackTxArgs) -> std::result::Result<(), String> {
 
         // 回滚每个受影响的池
         record.pools.iter().for_each(|pool_address| {
-            crate::POOLS.with_borrow_mut(|pools| {
-                if let Some(mut pool) = pools.get(pool_address) {
-                    if let Err(e) = pool.rollback(args.txid) {
-                        ic_cdk::println!("Rollback failed: {:?}", e);
-                    } else {
-                        pools.insert(pool_address.clone(), pool);
-                    }
+            if let Some(mut pool) = crate::get_pool(pool_address) {
+                if let Err(e) = pool.rollback(args.txid) {
+                    ic_cdk::println!("Rollback failed: {:?}", e);
                 } else {
-                    ic_cdk::println!("Pool not found: {}", pool_address);
+                    crate::save_pool(pool);
+                    crate::events::record_event(crate::events::ProtocolEvent::PoolStateRolledBack {
+                        pool_address: pool_address.clone(),
+                        txid: args.txid.to_string(),
+                    });
                 }
-            });
+            } else {
+                ic_cdk::println!("Pool not found: {}", pool_address);
+            }
         });
 
         // 删除交易记录
@@ -197,17 +199,19 @@ pub fn new_block(args: NewBlockArgs) -> std::result::Result<(), String> {
                             
                             // 在每个受影响的池中确认交易
                             record.pools.iter().for_each(|pool_address| {
-                                crate::POOLS.with_borrow_mut(|p| {
-                                    if let Some(mut pool) = p.get(pool_address) {
-                                        if let Err(e) = pool.finalize(txid.clone()) {
-                                            ic_cdk::println!("Finalize failed: {:?}", e);
-                                        } else {
-                                            p.insert(pool_address.clone(), pool);
-                                        }
+                                if let Some(mut pool) = crate::get_pool(pool_address) {
+                                    if let Err(e) = pool.finalize(txid.clone()) {
+                                        ic_cdk::println!("Finalize failed: {:?}", e);
                                     } else {
-                                        ic_cdk::println!("Pool not found: {}", pool_address);
+                                        crate::save_pool(pool);
+                                        crate::events::record_event(crate::events::ProtocolEvent::PoolStateFinalized {
+                                            pool_address: pool_address.clone(),
+                                            txid: txid.to_string(),
+                                        });
                                     }
-                                });
+                                } else {
+                                    ic_cdk::println!("Pool not found: {}", pool_address);
+                                }
                             });
                             
                             // 删除已确认的交易记录
@@ -246,6 +250,13 @@ pub async fn execute_tx(args: ExecuteTxArgs) -> std::result::Result<String, Stri
         zero_confirmed_tx_queue_length: _,
     } = args;
     
+    // 检查发起地址是否在黑名单中
+    crate::blocklist::ensure_not_blocked(
+        crate::ic_api::caller(),
+        Some(&intention_set.initiator_address),
+        "execute_tx",
+    ).map_err(|e| e.to_string())?;
+    
     // 解码 PSBT
     let raw = hex::decode(&psbt_hex).map_err(|_| "invalid psbt".to_string())?;
     let mut psbt = Psbt::deserialize(raw.as_slice()).map_err(|_| "invalid psbt".to_string())?;
@@ -255,7 +266,7 @@ pub async fn execute_tx(args: ExecuteTxArgs) -> std::result::Result<String, Stri
     let Intention {
         exchange_id: _,
         action,
-        action_params: _,
+        action_params,
         pool_address,
         nonce,
         pool_utxo_spent,
@@ -264,9 +275,23 @@ pub async fn execute_tx(args: ExecuteTxArgs) -> std::result::Result<String, Stri
         output_coins,
     } = intention;
 
+    // 检查紧急状态 (全局暂停和按池暂停)
+    ensure_action_allowed(&action, &pool_address).map_err(|e| e.to_string())?;
+
     // 创建交易执行锁，防止同一个池的并发交易
-    let _guard = ExecuteTxGuard::new(pool_address.clone())
-        .ok_or(format!("Pool {} Executing", pool_address))?;
+    // 追加借款会修改头寸，需要与拆分、合并、还款和转让使用同一把组合锁 (头寸所有者 + 池)
+    let (_pool_guard, _combined_guard) = if action == "borrow" {
+        let owner = crate::get_position(&action_params)
+            .map(|position| position.owner)
+            .ok_or("Position not found".to_string())?;
+        let guard = CombinedGuard::new(owner, pool_address.clone())
+            .ok_or(format!("Pool {} Executing", pool_address))?;
+        (None, Some(guard))
+    } else {
+        let guard = ExecuteTxGuard::new(pool_address.clone())
+            .ok_or(format!("Pool {} Executing", pool_address))?;
+        (Some(guard), None)
+    };
 
     // 获取池
     let pool = crate::POOLS
@@ -301,15 +326,62 @@ pub async fn execute_tx(args: ExecuteTxArgs) -> std::result::Result<String, Stri
             }
 
             // 更新池状态
-            crate::POOLS.with_borrow_mut(|m| {
-                let mut pool = m
-                    .get(&pool_address)
-                    .expect("already checked pool exists");
-                pool.commit(new_state);
-                m.insert(pool_address.clone(), pool);
-            });
+            let mut pool = crate::get_pool(&pool_address)
+                .expect("already checked pool exists");
+            pool.commit(new_state);
+            crate::save_pool(pool);
+        }
+        "borrow" => {
+            // action_params 为追加借款的头寸 ID，发起地址必须关联到头寸所有者
+            let position = crate::get_position(&action_params)
+                .ok_or("Position not found".to_string())?;
+            if position.pool_address != pool_address {
+                return Err("position does not belong to pool".to_string());
+            }
+            if crate::auth::resolve_owner(&intention_set.initiator_address) != position.owner {
+                return Err("initiator is not the position owner".to_string());
+            }
+            ensure_owner_not_blocked(&intention_set.initiator_address, "execute_tx")
+                .map_err(|e| e.to_string())?;
+
+            // 验证追加借款交易: 花费池当前 UTXO，且只转出 Bollar
+            let (new_state, consumed, bollar_amount) = pool
+                .validate_borrow(
+                    txid,
+                    nonce,
+                    pool_utxo_spent,
+                    pool_utxo_received,
+                    input_coins,
+                    output_coins,
+                )
+                .map_err(|e| e.to_string())?;
+
+            // 签名 UTXO
+            ree_pool_sign(
+                &mut psbt,
+                vec![&consumed],
+                crate::SCHNORR_KEY_NAME,
+                pool.derivation_path(),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+            // 签名期间头寸可能已被修改，重新读取并再次校验所有者和池
+            let position = crate::get_position(&action_params)
+                .filter(|current| current.owner == position.owner && current.pool_address == pool_address)
+                .ok_or("position changed during signing".to_string())?;
+
+            // 同时更新头寸债务和池状态
+            let pool = crate::get_pool(&pool_address)
+                .expect("already checked pool exists");
+            crate::lending::commit_borrow(&position, pool, new_state, bollar_amount)
+                .map_err(|e| e.to_string())?;
         }
         "repay" => {
+            // 发起地址关联的所有者被封禁时同样拒绝
+            ensure_owner_not_blocked(&intention_set.initiator_address, "execute_tx")
+                .map_err(|e| e.to_string())?;
+
             // 验证还款交易
             let (new_state, consumed) = pool
                 .validate_repay(
@@ -334,15 +406,16 @@ pub async fn execute_tx(args: ExecuteTxArgs) -> std::result::Result<String, Stri
             .map_err(|e| e.to_string())?;
 
             // 更新池状态
-            crate::POOLS.with_borrow_mut(|m| {
-                let mut pool = m
-                    .get(&pool_address)
-                    .expect("already checked pool exists");
-                pool.commit(new_state);
-                m.insert(pool_address.clone(), pool);
-            });
+            let mut pool = crate::get_pool(&pool_address)
+                .expect("already checked pool exists");
+            pool.commit(new_state);
+            crate::save_pool(pool);
         }
         "liquidate" => {
+            // 发起地址关联的所有者被封禁时同样拒绝
+            ensure_owner_not_blocked(&intention_set.initiator_address, "execute_tx")
+                .map_err(|e| e.to_string())?;
+
             // 验证清算交易
             let (new_state, consumed) = pool
                 .validate_repay(
@@ -367,13 +440,10 @@ pub async fn execute_tx(args: ExecuteTxArgs) -> std::result::Result<String, Stri
             .map_err(|e| e.to_string())?;
 
             // 更新池状态
-            crate::POOLS.with_borrow_mut(|m| {
-                let mut pool = m
-                    .get(&pool_address)
-                    .expect("already checked pool exists");
-                pool.commit(new_state);
-                m.insert(pool_address.clone(), pool);
-            });
+            let mut pool = crate::get_pool(&pool_address)
+                .expect("already checked pool exists");
+            pool.commit(new_state);
+            crate::save_pool(pool);
         }
         _ => {
             return Err("invalid action".to_string());
@@ -392,16 +462,38 @@ pub async fn execute_tx(args: ExecuteTxArgs) -> std::result::Result<String, Stri
         
         // 设置交易记录的其他字段
         record.timestamp = crate::ic_api::time();
-        record.action = action;
+        record.action = action.clone();
         record.user = crate::ic_api::caller().to_string();
         
         // 保存交易记录
         m.insert((txid.clone(), false), record);
     });
+    
+    crate::events::record_event(crate::events::ProtocolEvent::PoolStateCommitted {
+        pool_address: pool_address.clone(),
+        txid: txid.to_string(),
+        action,
+    });
 
     // 返回序列化的 PSBT
     Ok(psbt.serialize_hex())
 }
+// 检查 execute_tx 的操作是否被紧急状态或暂停标志阻止
+pub(crate) fn ensure_action_allowed(action: &str, pool_address: &str) -> Result<()> {
+    check_emergency_state!(action, pool_address);
+    Ok(())
+}
+
+// 检查发起地址解析出的所有者 principal 是否被封禁
+// 被封禁的 principal 可能通过未封禁的关联地址发起交易
+pub(crate) fn ensure_owner_not_blocked(initiator_address: &str, operation: &str) -> Result<()> {
+    match candid::Principal::from_text(crate::auth::resolve_owner(initiator_address)) {
+        Ok(owner) => crate::blocklist::ensure_not_blocked(owner, None, operation),
+        // 未关联的地址已在入口处按地址检查
+        Err(_) => Ok(()),
+    }
+}
+
 #[update]
 // 初始化 Bollar 资金池
 pub async fn init_bollar_pool(
@@ -451,9 +543,7 @@ pub async fn init_bollar_pool(
     };
     
     // 存储池
-    crate::POOLS.with_borrow_mut(|p| {
-        p.insert(addr.to_string(), pool);
-    });
+    crate::save_pool(pool);
     
     Ok(addr.to_string())
 }
\ No newline at end of file
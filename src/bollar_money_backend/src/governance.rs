@@ -0,0 +1,228 @@
+// governance.rs - 参数治理
+// 这个模块实现风险参数变更的时间锁提案队列
+
+use crate::{Error, Result, ic_api};
+use crate::access_control::Permission;
+use candid::{CandidType, Deserialize};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::{
+    StableBTreeMap, Storable,
+    memory_manager::MemoryId,
+    storable::Bound,
+};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+// 提案最短等待时间 (纳秒)
+const GOVERNANCE_MIN_DELAY_NS: u64 = 48 * 60 * 60 * 1_000_000_000; // 48小时
+
+// 到期后可执行的宽限期 (纳秒)
+const GOVERNANCE_GRACE_PERIOD_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7天
+
+// 提案状态
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ProposalStatus {
+    Queued,                      // 等待执行
+    Executed,                    // 已执行
+    Cancelled,                   // 已被守护者取消
+    Expired,                     // 超过宽限期未执行
+}
+
+// 参数变更提案
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct ParameterProposal {
+    pub id: u64,
+    pub collateral_ratio: Option<u8>,      // 新的抵押率
+    pub liquidation_threshold: Option<u8>, // 新的清算阈值
+    pub reason: String,
+    pub proposer: String,
+    pub created_at: u64,
+    pub eta: u64,                          // 最早执行时间
+    pub status: ProposalStatus,
+    pub closed_by: Option<String>,
+    pub closed_at: Option<u64>,
+}
+
+impl Storable for ParameterProposal {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode ParameterProposal")
+    }
+}
+
+thread_local! {
+    // 参数变更提案存储
+    static PARAMETER_PROPOSALS: RefCell<StableBTreeMap<u64, ParameterProposal, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(8))),
+        )
+    );
+}
+
+#[update]
+// 提交参数变更提案，最短等待时间后才能执行
+pub fn propose_parameter_change(
+    collateral_ratio: Option<u8>,
+    liquidation_threshold: Option<u8>,
+    reason: String,
+) -> Result<ParameterProposal> {
+    let caller = ic_api::caller();
+    check_change_permissions(caller, collateral_ratio, liquidation_threshold)?;
+
+    // 验证参数
+    validate_param!(collateral_ratio.is_some() || liquidation_threshold.is_some(), "提案至少需要包含一个参数变更");
+    validate_param!(!reason.trim().is_empty(), "提案原因不能为空");
+    if let Some(ratio) = collateral_ratio {
+        validate_param!(ratio > 0 && ratio <= 100, format!("无效的抵押率: {}，应在 1-100 之间", ratio));
+    }
+    if let Some(threshold) = liquidation_threshold {
+        validate_param!(threshold > 0 && threshold <= 100, format!("无效的清算阈值: {}，应在 1-100 之间", threshold));
+    }
+    if let (Some(ratio), Some(threshold)) = (collateral_ratio, liquidation_threshold) {
+        validate_param!(ratio < threshold, format!("抵押率 {} 必须小于清算阈值 {}", ratio, threshold));
+    }
+
+    let now = ic_api::time();
+    let proposal = PARAMETER_PROPOSALS.with_borrow_mut(|proposals| {
+        let id = proposals.last_key_value().map(|(id, _)| id + 1).unwrap_or(0);
+        let proposal = ParameterProposal {
+            id,
+            collateral_ratio,
+            liquidation_threshold,
+            reason: reason.clone(),
+            proposer: caller.to_string(),
+            created_at: now,
+            eta: now + GOVERNANCE_MIN_DELAY_NS,
+            status: ProposalStatus::Queued,
+            closed_by: None,
+            closed_at: None,
+        };
+        proposals.insert(id, proposal.clone());
+        proposal
+    });
+
+    crate::error::audit_log(
+        "propose_parameter_change",
+        &caller.to_string(),
+        &format!("proposal_id={}, collateral_ratio={:?}, liquidation_threshold={:?}, eta={}, reason={}",
+                 proposal.id, collateral_ratio, liquidation_threshold, proposal.eta, reason)
+    );
+
+    Ok(proposal)
+}
+
+#[update]
+// 执行已到期的参数变更提案
+pub fn execute_parameter_change(proposal_id: u64) -> Result<ParameterProposal> {
+    let caller = ic_api::caller();
+    let mut proposal = get_parameter_proposal(proposal_id)
+        .ok_or(Error::InvalidArgument(format!("提案 {} 不存在", proposal_id)))?;
+
+    check_change_permissions(caller, proposal.collateral_ratio, proposal.liquidation_threshold)?;
+
+    if proposal.status != ProposalStatus::Queued {
+        return Err(Error::InvalidState(format!("提案状态为 {:?}，无法执行", proposal.status)));
+    }
+
+    let now = ic_api::time();
+    if now < proposal.eta {
+        return Err(Error::InvalidState(format!("提案在 {} 之前不能执行", proposal.eta)));
+    }
+
+    // 超过宽限期的提案标记为过期
+    if now > proposal.eta + GOVERNANCE_GRACE_PERIOD_NS {
+        close_proposal(&mut proposal, ProposalStatus::Expired, "system".to_string());
+        crate::error::audit_log("expire_parameter_change", "system", &format!("proposal_id={}", proposal_id));
+        return Err(Error::InvalidState("提案已过期".to_string()));
+    }
+
+    crate::stability::update_system_parameters(proposal.collateral_ratio, proposal.liquidation_threshold)?;
+
+    close_proposal(&mut proposal, ProposalStatus::Executed, caller.to_string());
+
+    crate::error::audit_log(
+        "execute_parameter_change",
+        &caller.to_string(),
+        &format!("proposal_id={}, collateral_ratio={:?}, liquidation_threshold={:?}",
+                 proposal_id, proposal.collateral_ratio, proposal.liquidation_threshold)
+    );
+
+    Ok(proposal)
+}
+
+#[update]
+// 守护者取消待执行的提案
+pub fn cancel_parameter_change(proposal_id: u64, reason: String) -> Result<ParameterProposal> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, Permission::CancelProposal)?;
+
+    validate_param!(!reason.trim().is_empty(), "取消原因不能为空");
+
+    let mut proposal = get_parameter_proposal(proposal_id)
+        .ok_or(Error::InvalidArgument(format!("提案 {} 不存在", proposal_id)))?;
+
+    if proposal.status != ProposalStatus::Queued {
+        return Err(Error::InvalidState(format!("提案状态为 {:?}，无法取消", proposal.status)));
+    }
+
+    close_proposal(&mut proposal, ProposalStatus::Cancelled, caller.to_string());
+
+    crate::error::audit_log(
+        "cancel_parameter_change",
+        &caller.to_string(),
+        &format!("proposal_id={}, reason={}", proposal_id, reason)
+    );
+
+    Ok(proposal)
+}
+
+#[query]
+// 获取单个提案
+pub fn get_parameter_proposal(proposal_id: u64) -> Option<ParameterProposal> {
+    PARAMETER_PROPOSALS.with_borrow(|proposals| proposals.get(&proposal_id))
+}
+
+#[query]
+// 获取提案列表，默认只返回等待执行的提案
+pub fn get_parameter_proposals(include_closed: bool) -> Vec<ParameterProposal> {
+    PARAMETER_PROPOSALS.with_borrow(|proposals| {
+        proposals.iter()
+            .map(|(_, proposal)| proposal)
+            .filter(|proposal| include_closed || proposal.status == ProposalStatus::Queued)
+            .collect()
+    })
+}
+
+// 检查调用者是否有权变更提案中的参数
+fn check_change_permissions(
+    caller: candid::Principal,
+    collateral_ratio: Option<u8>,
+    liquidation_threshold: Option<u8>,
+) -> Result<()> {
+    if collateral_ratio.is_some() {
+        crate::access_control::check_permission(caller, Permission::UpdateCollateralRatio)?;
+    }
+    if liquidation_threshold.is_some() {
+        crate::access_control::check_permission(caller, Permission::UpdateLiquidationThreshold)?;
+    }
+    Ok(())
+}
+
+// 关闭提案并保存
+fn close_proposal(proposal: &mut ParameterProposal, status: ProposalStatus, closed_by: String) {
+    proposal.status = status;
+    proposal.closed_by = Some(closed_by);
+    proposal.closed_at = Some(ic_api::time());
+
+    PARAMETER_PROPOSALS.with_borrow_mut(|proposals| {
+        proposals.insert(proposal.id, proposal.clone());
+    });
+}
@@ -0,0 +1,55 @@
+// http.rs - HTTP 网关接口
+// 这个模块实现 canister 的 http_request 查询，向 Prometheus 暴露 /metrics
+
+use candid::{CandidType, Deserialize};
+use ic_cdk_macros::query;
+use serde::Serialize;
+
+// HTTP 请求
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+// HTTP 响应
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    // 纯文本响应
+    fn text(status_code: u16, content_type: &str, body: String) -> Self {
+        Self {
+            status_code,
+            headers: vec![
+                ("Content-Type".to_string(), content_type.to_string()),
+                ("Content-Length".to_string(), body.len().to_string()),
+                ("Cache-Control".to_string(), "no-store".to_string()),
+            ],
+            body: body.into_bytes(),
+        }
+    }
+}
+
+#[query]
+// 处理 HTTP 网关请求
+pub fn http_request(request: HttpRequest) -> HttpResponse {
+    // 忽略查询参数
+    let path = request.url.split('?').next().unwrap_or_default();
+
+    match (request.method.to_uppercase().as_str(), path) {
+        ("GET", "/metrics") => HttpResponse::text(
+            200,
+            "text/plain; version=0.0.4; charset=utf-8",
+            crate::monitoring::render_prometheus_metrics(),
+        ),
+        (_, "/metrics") => HttpResponse::text(405, "text/plain", "Method Not Allowed".to_string()),
+        _ => HttpResponse::text(404, "text/plain", "Not Found".to_string()),
+    }
+}
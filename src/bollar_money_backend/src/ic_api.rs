@@ -18,6 +18,39 @@ pub fn is_controller(principal: &Principal) -> bool {
     ic_cdk::api::is_controller(principal)
 }
 
+#[cfg(not(test))]
+pub fn canister_id() -> Principal {
+    ic_cdk::api::id()
+}
+
+#[cfg(not(test))]
+pub async fn raw_rand() -> crate::Result<Vec<u8>> {
+    ic_cdk::api::management_canister::main::raw_rand().await
+        .map(|(bytes,)| bytes)
+        .map_err(|(code, msg)| crate::Error::SystemError(format!("raw_rand failed: {:?} {}", code, msg)))
+}
+
+#[cfg(not(test))]
+pub fn notify_raw(canister_id: Principal, method: &str, args: &[u8]) -> crate::Result<()> {
+    ic_cdk::api::call::notify_raw(canister_id, method, args, 0)
+        .map_err(|code| crate::Error::SystemError(format!("notify failed: {:?}", code)))
+}
+
+#[cfg(not(test))]
+pub fn set_timer_interval(interval: std::time::Duration, func: impl FnMut() + 'static) -> ic_cdk_timers::TimerId {
+    ic_cdk_timers::set_timer_interval(interval, func)
+}
+
+#[cfg(not(test))]
+pub fn set_timer(delay: std::time::Duration, func: impl FnOnce() + 'static) -> ic_cdk_timers::TimerId {
+    ic_cdk_timers::set_timer(delay, func)
+}
+
+#[cfg(not(test))]
+pub fn clear_timer(timer_id: ic_cdk_timers::TimerId) {
+    ic_cdk_timers::clear_timer(timer_id)
+}
+
 #[cfg(test)]
 pub fn caller() -> Principal {
     crate::test_utils::mock::caller()
@@ -31,4 +64,33 @@ pub fn time() -> u64 {
 #[cfg(test)]
 pub fn is_controller(principal: &Principal) -> bool {
     crate::test_utils::mock::is_controller(principal)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub fn canister_id() -> Principal {
+    crate::test_utils::mock::canister_id()
+}
+
+#[cfg(test)]
+pub async fn raw_rand() -> crate::Result<Vec<u8>> {
+    Ok(crate::test_utils::mock::random_bytes())
+}
+
+#[cfg(test)]
+pub fn notify_raw(canister_id: Principal, method: &str, args: &[u8]) -> crate::Result<()> {
+    crate::test_utils::mock::notify(canister_id, method, args)
+}
+
+// 测试环境中不注册真实定时器
+#[cfg(test)]
+pub fn set_timer_interval(_interval: std::time::Duration, _func: impl FnMut() + 'static) -> ic_cdk_timers::TimerId {
+    ic_cdk_timers::TimerId::default()
+}
+
+#[cfg(test)]
+pub fn set_timer(_delay: std::time::Duration, _func: impl FnOnce() + 'static) -> ic_cdk_timers::TimerId {
+    ic_cdk_timers::TimerId::default()
+}
+
+#[cfg(test)]
+pub fn clear_timer(_timer_id: ic_cdk_timers::TimerId) {}
\ No newline at end of file
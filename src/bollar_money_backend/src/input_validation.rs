@@ -435,6 +435,13 @@ pub fn validate_bollar_amount(amount: u64, context: &str) -> Result<()> {
 }
 
 pub fn validate_bitcoin_address(address: &str, context: &str) -> Result<()> {
+    // 持久化黑名单
+    if crate::blocklist::is_address_blocked(address) {
+        return Err(Error::PermissionDenied(format!(
+            "{}: Address {} is blocked", context, address
+        )));
+    }
+    
     GLOBAL_VALIDATOR.with_borrow(|validator| validator.validate_bitcoin_address(address, context))
 }
 
@@ -443,6 +450,13 @@ pub fn validate_psbt_hex(psbt_hex: &str, context: &str) -> Result<()> {
 }
 
 pub fn validate_principal(principal: Principal, context: &str) -> Result<()> {
+    // 持久化黑名单
+    if crate::blocklist::is_principal_blocked(&principal) {
+        return Err(Error::PermissionDenied(format!(
+            "{}: Principal {} is blocked", context, principal
+        )));
+    }
+    
     GLOBAL_VALIDATOR.with_borrow(|validator| validator.validate_principal(principal, context))
 }
 
@@ -126,6 +126,337 @@ mod tests {
         assert!(pos.btc_collateral < btc_amount, "Collateral should be reduced");
     }
 
+    // 构建追加借款 PSBT: 花费池 UTXO 和一个用户手续费输入，池输出返还全部 BTC，
+    // Runestone 把 edict_amount 的 Bollar 转到 borrower 输出，其余经 pointer 回到池输出
+    fn borrow_more_psbt(pool_outpoint: &str, pool_address: &str, borrower: &str, edict_amount: u128) -> String {
+        use bitcoin::blockdata::opcodes::all::{OP_PUSHNUM_13, OP_RETURN};
+        use bitcoin::blockdata::script::{Builder, PushBytesBuf};
+        use bitcoin::{absolute::LockTime, Address, Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+        use std::str::FromStr;
+
+        let script_of = |address: &str| Address::from_str(address).unwrap().assume_checked().script_pubkey();
+        let input = |outpoint: OutPoint| TxIn {
+            previous_output: outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        };
+
+        // pointer = 0, body: Bollar (72798:1058) edict 到输出 1
+        let mut payload = Vec::new();
+        for mut value in [22u128, 0, 0, 72798, 1058, edict_amount, 1] {
+            loop {
+                let byte = (value & 0x7f) as u8;
+                value >>= 7;
+                if value == 0 {
+                    payload.push(byte);
+                    break;
+                }
+                payload.push(byte | 0x80);
+            }
+        }
+        let runestone = Builder::new()
+            .push_opcode(OP_RETURN)
+            .push_opcode(OP_PUSHNUM_13)
+            .push_slice(PushBytesBuf::try_from(payload).unwrap())
+            .into_script();
+
+        let fee_outpoint = OutPoint::from_str("1111111111111111111111111111111111111111111111111111111111111111:0").unwrap();
+        let tx = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![input(OutPoint::from_str(pool_outpoint).unwrap()), input(fee_outpoint)],
+            output: vec![
+                TxOut { value: Amount::from_sat(100_000_000), script_pubkey: script_of(pool_address) },
+                TxOut { value: Amount::from_sat(546), script_pubkey: script_of(borrower) },
+                TxOut { value: Amount::ZERO, script_pubkey: runestone },
+            ],
+        };
+
+        let mut psbt = bitcoin::psbt::Psbt::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut { value: Amount::from_sat(100_000_000), script_pubkey: script_of(pool_address) });
+        psbt.inputs[1].witness_utxo = Some(TxOut { value: Amount::from_sat(10_000), script_pubkey: script_of(borrower) });
+        hex::encode(psbt.serialize())
+    }
+
+    #[tokio::test]
+    async fn test_borrow_more_flow() {
+        // 测试追加借款: PSBT 必须把 Bollar 转给所有者，并与池状态一起提交
+        crate::test_utils::set_time(1_000_000_000_000_000_002);
+        let pool_address = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l".to_string();
+        let owner_address = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        let stranger_address = "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2";
+        crate::test_utils::init_test_pool(pool_address.clone());
+        let _ = crate::oracle::mock_price_update(3_000_000);
+        
+        let owner = crate::test_utils::test_principal(1);
+        crate::auth::link_verified_address(owner, owner_address).unwrap();
+        let position_id = format!("{}:900", pool_address);
+        crate::save_position(crate::types::Position::new(
+            position_id.clone(),
+            pool_address.clone(),
+            owner.to_string(),
+            100_000_000,
+            1_000,
+            3_000_000,
+        ));
+        let pool_outpoint = crate::get_pool(&pool_address).unwrap()
+            .current_state().unwrap().utxo.clone().unwrap().outpoint;
+        
+        // Bollar 转给非所有者地址被拒绝
+        let psbt = borrow_more_psbt(&pool_outpoint, &pool_address, stranger_address, 500);
+        assert!(execute_borrow_more(position_id.clone(), psbt, 500).await.is_err());
+        
+        // edict 数量与追加数量不符被拒绝
+        let psbt = borrow_more_psbt(&pool_outpoint, &pool_address, owner_address, 400);
+        assert!(execute_borrow_more(position_id.clone(), psbt, 500).await.is_err());
+        assert_eq!(get_position_details(position_id.clone()).unwrap().bollar_debt, 1_000);
+        
+        // 正确的 PSBT: 头寸债务和池余额一起更新
+        let psbt = borrow_more_psbt(&pool_outpoint, &pool_address, owner_address, 500);
+        assert!(execute_borrow_more(position_id.clone(), psbt.clone(), 500).await.is_ok());
+        assert_eq!(get_position_details(position_id.clone()).unwrap().bollar_debt, 1_500);
+        let pool = crate::get_pool(&pool_address).unwrap();
+        assert_eq!(pool.bollar_balance(), 49_500);
+        assert_eq!(pool.btc_balance(), 100_000_000);
+        assert_eq!(pool.current_nonce(), 1);
+        assert_ne!(pool.current_state().unwrap().utxo.as_ref().unwrap().outpoint, pool_outpoint);
+        
+        // 重放同一 PSBT 时池 UTXO 已变化，不会再次记入债务
+        assert!(execute_borrow_more(position_id.clone(), psbt, 500).await.is_err());
+        assert_eq!(get_position_details(position_id).unwrap().bollar_debt, 1_500);
+        assert_eq!(crate::get_pool(&pool_address).unwrap().bollar_balance(), 49_500);
+    }
+
+    #[test]
+    fn test_position_transfer_flow() {
+        // 测试头寸转让: 提议、取消、过期、所有者变更和接收方黑名单
+        use crate::position_management::*;
+        use crate::test_utils::{set_caller, set_time, test_principal};
+        
+        let now = 1_000_000_000_000_000_003;
+        set_time(now);
+        let pool_address = test_pool_address();
+        crate::test_utils::init_test_pool(pool_address.clone());
+        
+        let admin = test_principal(1);
+        let owner = test_principal(2);
+        let recipient = test_principal(3);
+        let stranger = test_principal(4);
+        let position_id = format!("{}:0", pool_address);
+        crate::save_position(crate::types::Position::new(
+            position_id.clone(),
+            pool_address.clone(),
+            owner.to_string(),
+            100_000_000,
+            1_000,
+            3_000_000,
+        ));
+        
+        // 只有所有者可以发起转让
+        set_caller(stranger);
+        assert!(propose_position_transfer(position_id.clone(), recipient.to_text()).is_err());
+        set_caller(owner);
+        assert!(propose_position_transfer(position_id.clone(), owner.to_text()).is_err());
+        let transfer = propose_position_transfer(position_id.clone(), recipient.to_text()).unwrap();
+        assert_eq!(transfer.to, recipient);
+        assert_eq!(get_pending_position_transfers().len(), 1);
+        set_caller(recipient);
+        assert_eq!(get_pending_position_transfers().len(), 1);
+        
+        // 非接收方不能接受，接收方不能取消
+        set_caller(stranger);
+        assert!(accept_position_transfer(position_id.clone()).is_err());
+        set_caller(recipient);
+        assert!(cancel_position_transfer(position_id.clone()).is_err());
+        
+        // 所有者取消后无法接受
+        set_caller(owner);
+        assert!(cancel_position_transfer(position_id.clone()).is_ok());
+        set_caller(recipient);
+        assert!(accept_position_transfer(position_id.clone()).is_err());
+        
+        // 过期的提议被拒绝并清除
+        set_caller(owner);
+        propose_position_transfer(position_id.clone(), recipient.to_text()).unwrap();
+        set_time(now + 7 * 24 * 60 * 60 * 1_000_000_000 + 1);
+        set_caller(recipient);
+        assert!(get_pending_position_transfers().is_empty());
+        assert!(accept_position_transfer(position_id.clone()).is_err());
+        set_time(now);
+        assert!(get_pending_position_transfers().is_empty());
+        
+        // 提议之后抵押品或债务变化时被拒绝并清除
+        set_caller(owner);
+        let transfer = propose_position_transfer(position_id.clone(), recipient.to_text()).unwrap();
+        assert_eq!((transfer.btc_collateral, transfer.bollar_debt), (100_000_000, 1_000));
+        let original = crate::get_position(&position_id).unwrap();
+        let mut drained = original.clone();
+        drained.update(50_000_000, 1_000, 3_000_000);
+        crate::save_position(drained);
+        set_caller(recipient);
+        assert!(matches!(accept_position_transfer(position_id.clone()), Err(crate::Error::InvalidState(_))));
+        assert!(get_pending_position_transfers().is_empty());
+        crate::save_position(original);
+        
+        // 提议之后所有者已变更时被拒绝并清除
+        set_caller(owner);
+        propose_position_transfer(position_id.clone(), recipient.to_text()).unwrap();
+        let mut moved = crate::get_position(&position_id).unwrap();
+        moved.owner = stranger.to_string();
+        crate::save_position(moved.clone());
+        set_caller(recipient);
+        assert!(matches!(accept_position_transfer(position_id.clone()), Err(crate::Error::InvalidState(_))));
+        assert!(get_pending_position_transfers().is_empty());
+        moved.owner = owner.to_string();
+        crate::save_position(moved);
+        
+        // 被封禁的接收方不能接受转让
+        set_caller(owner);
+        propose_position_transfer(position_id.clone(), recipient.to_text()).unwrap();
+        set_caller(admin);
+        crate::blocklist::add_to_blocklist(
+            crate::blocklist::BlockedEntity::Principal(recipient),
+            "OFAC".to_string(),
+        ).unwrap();
+        set_caller(recipient);
+        assert!(accept_position_transfer(position_id.clone()).is_err());
+        assert_eq!(crate::get_position(&position_id).unwrap().owner, owner.to_string());
+        
+        // 也不能向被封禁的接收方发起新的提议
+        set_caller(owner);
+        assert!(matches!(
+            propose_position_transfer(position_id.clone(), recipient.to_text()),
+            Err(crate::Error::PermissionDenied(_))
+        ));
+        
+        // 解封后接受成功，所有权转移
+        set_caller(admin);
+        crate::blocklist::remove_from_blocklist(
+            crate::blocklist::BlockedEntity::Principal(recipient),
+            "Delisted".to_string(),
+        ).unwrap();
+        set_caller(recipient);
+        let transferred = accept_position_transfer(position_id.clone()).unwrap();
+        assert_eq!(transferred.owner, recipient.to_string());
+        assert_eq!(crate::get_position(&position_id).unwrap().owner, recipient.to_string());
+        assert!(get_pending_position_transfers().is_empty());
+        assert!(accept_position_transfer(position_id).is_err());
+    }
+
+    #[test]
+    fn test_merge_split_positions() {
+        // 测试头寸合并与拆分: 所有权、同池限制、可铸造上限、黑名单和暂停
+        use crate::position_management::*;
+        use crate::test_utils::{set_caller, set_time, test_principal};
+        
+        set_time(1_000_000_000_000_000_004);
+        let pool_address = test_pool_address();
+        let other_pool = "bc1qotherpool".to_string();
+        crate::test_utils::init_test_pool(other_pool.clone());
+        crate::test_utils::init_test_pool(pool_address.clone());
+        let _ = crate::oracle::mock_price_update(3_000_000);
+        
+        let admin = test_principal(1);
+        let owner = test_principal(2);
+        let stranger = test_principal(3);
+        let save = |seq: u64, pool: &String, user: candid::Principal, btc: u64, bollar: u64| {
+            let id = format!("{}:{}", pool, seq);
+            crate::save_position(crate::types::Position::new(id.clone(), pool.clone(), user.to_string(), btc, bollar, 3_000_000));
+            id
+        };
+        // 0.1 BTC 在 90% 抵押率下最多铸造 270_000
+        let a = save(0, &pool_address, owner, 10_000_000, 100_000);
+        let b = save(1, &pool_address, owner, 10_000_000, 200_000);
+        let foreign = save(2, &pool_address, stranger, 10_000_000, 100_000);
+        let elsewhere = save(0, &other_pool, owner, 10_000_000, 100_000);
+        
+        // 参数和所有权校验
+        set_caller(owner);
+        assert!(merge_positions(vec![a.clone()]).is_err());
+        assert!(merge_positions(vec![a.clone(), a.clone()]).is_err());
+        assert!(merge_positions(vec![a.clone(), foreign.clone()]).is_err());
+        assert!(merge_positions(vec![a.clone(), elsewhere.clone()]).is_err());
+        
+        // 合并保留第一个头寸 ID，其余头寸被删除，相关转让提议失效
+        propose_position_transfer(b.clone(), stranger.to_text()).unwrap();
+        let merged = merge_positions(vec![a.clone(), b.clone()]).unwrap();
+        assert!(get_pending_position_transfers().is_empty());
+        assert_eq!(merged.id, a);
+        assert_eq!(merged.btc_collateral, 20_000_000);
+        assert_eq!(merged.bollar_debt, 300_000);
+        assert!(crate::get_position(&b).is_none());
+        
+        // 合并事件同时关联保留和删除的头寸
+        let by_removed = crate::events::get_events(0, None, Some(crate::events::EventFilter {
+            position_id: Some(b.clone()),
+            ..Default::default()
+        }));
+        assert!(matches!(
+            by_removed.events.last().map(|e| &e.event),
+            Some(crate::events::ProtocolEvent::PositionsMerged { position_id, merged_position_ids, btc_collateral: 20_000_000, bollar_debt: 300_000, .. })
+                if *position_id == a && *merged_position_ids == vec![b.clone()]
+        ));
+        
+        // 合并后超过可铸造上限的组合被拒绝 (与拆分使用同一规则)
+        let over_c = save(3, &pool_address, owner, 10_000_000, 280_000);
+        let over_d = save(4, &pool_address, owner, 10_000_000, 270_000);
+        assert!(merge_positions(vec![over_c.clone(), over_d.clone()]).is_err());
+        assert!(crate::get_position(&over_d).is_some());
+        
+        // 拆分后的子头寸和剩余头寸都必须在可铸造上限之内
+        set_caller(stranger);
+        assert!(split_position(a.clone(), 5_000_000, 100_000).is_err());
+        set_caller(owner);
+        assert!(split_position(a.clone(), 5_000_000, 200_000).is_err());
+        assert!(split_position(a.clone(), 20_000_000, 0).is_err());
+        let sub = split_position(a.clone(), 5_000_000, 100_000).unwrap();
+        assert_eq!(sub.owner, owner.to_string());
+        assert_eq!(sub.pool_address, pool_address);
+        let remaining = crate::get_position(&a).unwrap();
+        assert_eq!(remaining.btc_collateral, 15_000_000);
+        assert_eq!(remaining.bollar_debt, 200_000);
+        let by_sub = crate::events::get_events(0, None, Some(crate::events::EventFilter {
+            position_id: Some(sub.id.clone()),
+            ..Default::default()
+        }));
+        assert_eq!(by_sub.events.len(), 1);
+        assert!(matches!(
+            &by_sub.events[0].event,
+            crate::events::ProtocolEvent::PositionSplit {
+                position_id, btc_amount: 5_000_000, bollar_amount: 100_000,
+                remaining_btc_collateral: 15_000_000, remaining_bollar_debt: 200_000, ..
+            } if *position_id == a
+        ));
+        
+        // 被封禁的所有者不能合并或拆分
+        set_caller(admin);
+        crate::blocklist::add_to_blocklist(
+            crate::blocklist::BlockedEntity::Principal(owner),
+            "OFAC".to_string(),
+        ).unwrap();
+        set_caller(owner);
+        assert!(merge_positions(vec![a.clone(), sub.id.clone()]).is_err());
+        assert!(split_position(a.clone(), 5_000_000, 0).is_err());
+        set_caller(admin);
+        crate::blocklist::remove_from_blocklist(
+            crate::blocklist::BlockedEntity::Principal(owner),
+            "Delisted".to_string(),
+        ).unwrap();
+        
+        // 暂停该池的头寸重组后合并和拆分都被拒绝，其他池不受影响
+        crate::emergency::emergency_pause_operation(
+            crate::emergency::PauseOperation::Restructure,
+            Some(pool_address.clone()),
+            "Incident".to_string(),
+        ).unwrap();
+        set_caller(owner);
+        assert!(merge_positions(vec![a.clone(), sub.id.clone()]).is_err());
+        assert!(split_position(a.clone(), 5_000_000, 0).is_err());
+        assert!(split_position(elsewhere, 5_000_000, 0).is_ok());
+        assert_eq!(crate::get_position(&a).unwrap().btc_collateral, 15_000_000);
+    }
+
     #[tokio::test]
     async fn test_liquidation_flow() {
         // 测试清算流程
@@ -203,12 +534,12 @@ mod tests {
         
         // 1. 测试更新抵押率
         let new_collateral_ratio = 80u8;
-        let result = crate::stability::update_collateral_ratio(new_collateral_ratio);
+        let result = crate::stability::update_system_parameters(Some(new_collateral_ratio), None);
         assert!(result.is_ok(), "Should be able to update collateral ratio");
         
         // 2. 测试更新清算阈值
         let new_liquidation_threshold = 85u8;
-        let result = crate::stability::update_liquidation_threshold(new_liquidation_threshold);
+        let result = crate::stability::update_system_parameters(None, Some(new_liquidation_threshold));
         assert!(result.is_ok(), "Should be able to update liquidation threshold");
         
         // 3. 测试获取系统参数
@@ -221,23 +552,84 @@ mod tests {
         
         // 4. 测试无效参数
         let invalid_ratio = 101u8; // 超过100%
-        let result = crate::stability::update_collateral_ratio(invalid_ratio);
+        let result = crate::stability::update_system_parameters(Some(invalid_ratio), None);
         assert!(result.is_err(), "Should reject invalid collateral ratio");
         
         let invalid_threshold = 0u8; // 0%
-        let result = crate::stability::update_liquidation_threshold(invalid_threshold);
+        let result = crate::stability::update_system_parameters(None, Some(invalid_threshold));
         assert!(result.is_err(), "Should reject invalid liquidation threshold");
+        
+        // 5. 批量更新: 任一参数无效时两个参数都不修改
+        assert!(crate::stability::update_system_parameters(Some(70), Some(0)).is_err());
+        assert!(crate::stability::update_system_parameters(Some(90), Some(88)).is_err());
+        let system_params = crate::stability::get_system_parameters().unwrap();
+        assert_eq!(system_params.collateral_ratio, new_collateral_ratio);
+        assert_eq!(system_params.liquidation_threshold, new_liquidation_threshold);
+        
+        // 同时提高两个参数按最终取值校验
+        assert!(crate::stability::update_system_parameters(Some(88), Some(92)).unwrap());
+        let system_params = crate::stability::get_system_parameters().unwrap();
+        assert_eq!(system_params.collateral_ratio, 88);
+        assert_eq!(system_params.liquidation_threshold, 92);
     }
 
     #[test]
-    fn test_authentication_flow() {
+    fn test_parameter_change_timelock() {
+        // 测试参数变更的时间锁提案流程
+        use crate::governance::*;
+        
+        // 初始化测试池 (调用者为控制者)
+        let pool_address = test_pool_address();
+        crate::test_utils::init_test_pool(pool_address);
+        
+        // 1. 提交提案
+        let proposal = propose_parameter_change(Some(80), Some(85), "Reduce risk".to_string())
+            .expect("Controller should be able to propose");
+        assert_eq!(proposal.status, ProposalStatus::Queued);
+        assert_eq!(get_parameter_proposals(false).len(), 1);
+        
+        // 2. 等待期内不能执行
+        assert!(execute_parameter_change(proposal.id).is_err(), "Should not execute before eta");
+        
+        // 3. 等待期过后可以执行
+        crate::test_utils::set_time(proposal.eta);
+        let executed = execute_parameter_change(proposal.id).expect("Should execute after eta");
+        assert_eq!(executed.status, ProposalStatus::Executed);
+        
+        let params = crate::stability::get_system_parameters().unwrap();
+        assert_eq!(params.collateral_ratio, 80);
+        assert_eq!(params.liquidation_threshold, 85);
+        
+        // 4. 守护者取消提案后不能执行
+        let proposal = propose_parameter_change(Some(70), None, "Loosen".to_string()).unwrap();
+        let guardian = crate::test_utils::test_principal(9);
+        crate::access_control::grant_role(guardian, crate::access_control::Role::Guardian, "Guardian".to_string()).unwrap();
+        
+        crate::test_utils::set_caller(guardian);
+        let cancelled = cancel_parameter_change(proposal.id, "Unsafe".to_string()).unwrap();
+        assert_eq!(cancelled.status, ProposalStatus::Cancelled);
+        
+        crate::test_utils::set_caller(crate::test_utils::test_principal(1));
+        crate::test_utils::set_time(proposal.eta);
+        assert!(execute_parameter_change(proposal.id).is_err(), "Cancelled proposal should not execute");
+        assert_eq!(crate::stability::get_system_parameters().unwrap().collateral_ratio, 80);
+    }
+
+    #[tokio::test]
+    async fn test_authentication_flow() {
         // 测试用户认证流程
         
-        // 1. 测试用户认证
-        let address = "bc1qtest123456789".to_string();
-        let signature = "test_signature".to_string();
-        let message = "test_message".to_string();
+        // 1. 测试用户认证 (使用 BIP-322 签名认证挑战)
+        crate::test_utils::set_caller(crate::test_utils::test_principal(5));
+        crate::auth::ensure_session_secret().await.unwrap();
+        let address = crate::test_utils::TEST_P2WPKH_ADDRESS.to_string();
+        let message = crate::auth::request_auth_challenge(address.clone()).await.unwrap().message;
         
+        // 无效签名被拒绝，挑战已被消耗，需要重新申请
+        assert!(crate::auth::authenticate(address.clone(), "test_signature".to_string(), message.clone()).is_err());
+        let message = crate::auth::request_auth_challenge(address.clone()).await.unwrap().message;
+        
+        let signature = crate::test_utils::sign_bip322_p2wpkh(crate::test_utils::TEST_P2WPKH_WIF, &message);
         let auth_result = crate::auth::authenticate(address.clone(), signature, message);
         assert!(auth_result.is_ok(), "Authentication should succeed");
         
@@ -252,7 +644,8 @@ mod tests {
         assert!(verify_result.unwrap(), "Session should be valid");
         
         // 3. 测试会话刷新
-        let refresh_result = crate::auth::refresh_session();
+        let session_id = crate::auth::list_my_sessions()[0].session_id.clone();
+        let refresh_result = crate::auth::refresh_session(session_id);
         assert!(refresh_result.is_ok(), "Session refresh should succeed");
         
         let refreshed = refresh_result.unwrap();
@@ -265,6 +658,230 @@ mod tests {
         assert!(logout_result.unwrap(), "Logout should return true");
     }
 
+    #[tokio::test]
+    async fn test_auth_challenge_binding() {
+        // 测试认证挑战绑定调用者且只能使用一次
+        let address = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq".to_string();
+        let user = crate::test_utils::test_principal(3);
+        let attacker = crate::test_utils::test_principal(4);
+        
+        crate::test_utils::set_caller(user);
+        let challenge = crate::auth::request_auth_challenge(address.clone()).await.unwrap();
+        assert!(challenge.message.contains(&user.to_text()));
+        assert!(challenge.message.contains(&crate::ic_api::canister_id().to_text()));
+        assert!(challenge.message.contains(&challenge.nonce));
+        
+        // 未签发的消息不能用于认证
+        let result = crate::auth::authenticate(address.clone(), "c2ln".to_string(), "arbitrary message".to_string());
+        assert!(result.is_err(), "Message without a challenge should be rejected");
+        
+        // 其他 Principal 不能使用该挑战，也不能销毁它
+        crate::test_utils::set_caller(attacker);
+        let result = crate::auth::authenticate(address.clone(), "c2ln".to_string(), challenge.message.clone());
+        assert!(result.is_err(), "Challenge issued to another principal should be rejected");
+        assert_eq!(crate::auth::pending_challenges_of(&user), 1);
+        
+        // 调用者本人使用后挑战即被消费
+        crate::test_utils::set_caller(user);
+        let result = crate::auth::authenticate(address.clone(), "c2ln".to_string(), challenge.message.clone());
+        assert!(result.is_err(), "Invalid signature should be rejected");
+        assert_eq!(crate::auth::pending_challenges_of(&user), 0);
+        let result = crate::auth::authenticate(address.clone(), "c2ln".to_string(), challenge.message);
+        assert!(result.is_err(), "Consumed challenge should not be reusable");
+        
+        // nonce 不可预测，每个 Principal 保留的挑战数量有上限
+        let first = crate::auth::request_auth_challenge(address.clone()).await.unwrap();
+        let second = crate::auth::request_auth_challenge(address.clone()).await.unwrap();
+        assert_ne!(first.nonce, second.nonce);
+        for _ in 0..10 {
+            crate::auth::request_auth_challenge(address.clone()).await.unwrap();
+        }
+        assert_eq!(crate::auth::pending_challenges_of(&user), 5);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_jobs() {
+        use crate::scheduler::*;
+        use crate::test_utils::mock;
+        
+        let admin = mock::test_principal(1);
+        mock::set_controllers(vec![admin]);
+        start_jobs();
+        
+        let job = |id: JobId| get_scheduled_jobs().into_iter().find(|j| j.id == id).unwrap();
+        
+        // 所有任务默认启用并已注册定时器
+        let jobs = get_scheduled_jobs();
+        assert_eq!(jobs.len(), JobId::ALL.len());
+        assert!(jobs.iter().all(|j| j.enabled && j.scheduled && j.run_count == 0));
+        assert_eq!(job(JobId::PriceUpdate).interval_seconds, 60);
+        
+        // 普通用户不能管理任务
+        mock::set_caller(mock::test_principal(9));
+        assert!(set_job_enabled(JobId::AutoBackup, false).is_err());
+        assert!(run_job_now(JobId::LogCleanup).await.is_err());
+        
+        mock::set_caller(admin);
+        let disabled = set_job_enabled(JobId::AutoBackup, false).unwrap();
+        assert!(!disabled.enabled && !disabled.scheduled);
+        
+        assert!(set_job_interval(JobId::LogCleanup, 1).is_err());
+        assert!(set_job_interval(JobId::LogCleanup, 30 * 24 * 60 * 60).is_err());
+        assert_eq!(set_job_interval(JobId::LogCleanup, 120).unwrap().interval_seconds, 120);
+        
+        // 手动执行记录运行状态
+        let ran = run_job_now(JobId::LogCleanup).await.unwrap();
+        assert_eq!(ran.run_count, 1);
+        assert_eq!(ran.error_count, 0);
+        assert_eq!(ran.last_status, Some(JobStatus::Success));
+        assert!(!ran.running);
+        
+        // 禁用的任务也可以手动执行
+        assert_eq!(run_job_now(JobId::AutoBackup).await.unwrap().run_count, 1);
+        
+        // 运行标记由 guard 持有，guard 被释放 (包括 trap 后的清理) 时清除
+        let guard = JobRunGuard::new(JobId::PriceUpdate).unwrap();
+        assert!(job(JobId::PriceUpdate).running);
+        assert!(JobRunGuard::new(JobId::PriceUpdate).is_none());
+        assert!(run_job_now(JobId::PriceUpdate).await.is_err());
+        drop(guard);
+        assert!(!job(JobId::PriceUpdate).running);
+        
+        // 会话密钥初始化是一次性任务
+        assert!(JobId::SessionSecretInit.is_one_shot());
+        assert!(!JobId::SessionCleanup.is_one_shot());
+        assert_eq!(run_job_now(JobId::SessionSecretInit).await.unwrap().last_status, Some(JobStatus::Success));
+    }
+
+    #[test]
+    fn test_session_token_lifecycle() {
+        // 测试会话令牌的签发、验证、过期和撤销
+        let address = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        let user = crate::test_utils::test_principal(5);
+        crate::test_utils::set_caller(user);
+        
+        crate::auth::set_session_secret(crate::test_utils::mock::random_bytes()).unwrap();
+        
+        let token = crate::auth::create_session(address, user).unwrap().token;
+        let claims = crate::auth::validate_session_token(token.clone()).unwrap();
+        assert_eq!(claims.principal, user);
+        assert_eq!(claims.address, address);
+        
+        // 篡改声明后 MAC 校验失败
+        let (payload, tag) = token.split_once('.').unwrap();
+        let tampered = format!("{}A.{}", payload, tag);
+        assert!(crate::auth::validate_session_token(tampered).is_err());
+        
+        // 过期后验证失败
+        let now = crate::ic_api::time();
+        crate::test_utils::set_time(claims.expires_at);
+        assert!(crate::auth::validate_session_token(token.clone()).is_err());
+        crate::test_utils::set_time(now);
+        
+        // 递增代数后旧令牌失效，新令牌有效
+        crate::auth::revoke_session_tokens(user).unwrap();
+        assert!(crate::auth::validate_session_token(token).is_err());
+        
+        let new_token = crate::auth::create_session(address, user).unwrap().token;
+        assert!(crate::auth::validate_session_token(new_token).is_ok());
+    }
+
+    #[test]
+    fn test_multiple_sessions_per_principal() {
+        // 测试同一 principal 的多设备会话管理
+        let address = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        let user = crate::test_utils::test_principal(6);
+        crate::test_utils::set_caller(user);
+        crate::auth::set_session_secret(crate::test_utils::mock::random_bytes()).unwrap();
+        
+        // 第二个设备登录不会替换第一个设备的会话
+        let laptop = crate::auth::create_session(address, user).unwrap();
+        let phone = crate::auth::create_session(address, user).unwrap();
+        assert_ne!(laptop.session_id, phone.session_id);
+        assert_eq!(crate::auth::list_my_sessions().len(), 2);
+        assert!(crate::auth::validate_session_token(laptop.token.clone()).is_ok());
+        assert!(crate::auth::validate_session_token(phone.token.clone()).is_ok());
+        
+        // 撤销单个会话只影响该设备
+        crate::auth::revoke_session(laptop.session_id.clone()).unwrap();
+        assert!(crate::auth::validate_session_token(laptop.token).is_err());
+        assert!(crate::auth::validate_session_token(phone.token.clone()).is_ok());
+        assert!(crate::auth::revoke_session(laptop.session_id).is_err());
+        
+        // 空闲超时后会话失效
+        let now = crate::ic_api::time();
+        crate::test_utils::set_time(now + 3 * 60 * 60 * 1_000_000_000);
+        assert!(!crate::auth::is_authenticated());
+        assert!(crate::auth::validate_session_token(phone.token).is_err());
+        crate::test_utils::set_time(now);
+        
+        // 撤销所有会话
+        crate::auth::create_session(address, user).unwrap();
+        assert!(crate::auth::is_authenticated());
+        assert_eq!(crate::auth::revoke_all_sessions().unwrap(), 2);
+        assert!(crate::auth::list_my_sessions().is_empty());
+        assert!(crate::auth::get_principal_by_address(address.to_string()).is_none());
+        
+        // 刷新和查询作用于指定会话，活动会延长该会话的空闲超时
+        let laptop = crate::auth::create_session(address, user).unwrap();
+        crate::test_utils::set_time(now + 1);
+        let phone = crate::auth::create_session(address, user).unwrap();
+        crate::test_utils::set_time(now + 60 * 60 * 1_000_000_000);
+        let touched = crate::auth::touch_session(laptop.session_id.clone()).unwrap();
+        assert_eq!(touched.last_accessed, now + 60 * 60 * 1_000_000_000);
+        crate::test_utils::set_time(now + 150 * 60 * 1_000_000_000);
+        assert!(crate::auth::get_current_session(phone.session_id.clone()).is_err());
+        assert!(crate::auth::refresh_session(phone.session_id).is_err());
+        let refreshed = crate::auth::refresh_session(laptop.session_id.clone()).unwrap().token.unwrap();
+        assert_eq!(crate::auth::get_current_session(laptop.session_id.clone()).unwrap().token, refreshed);
+        
+        // 其他 principal 不能访问该会话
+        crate::test_utils::set_caller(crate::test_utils::test_principal(9));
+        assert!(crate::auth::get_current_session(laptop.session_id).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_linked_addresses() {
+        // 测试多地址关联和按地址查询头寸
+        let user = crate::test_utils::test_principal(7);
+        let other = crate::test_utils::test_principal(8);
+        let segwit = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        let legacy = "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2";
+        crate::test_utils::set_caller(user);
+        
+        crate::auth::link_verified_address(user, segwit).unwrap();
+        crate::auth::link_verified_address(user, legacy).unwrap();
+        assert_eq!(crate::auth::get_linked_addresses(user).len(), 2);
+        assert_eq!(crate::auth::get_principal_by_address(legacy.to_string()), Some(user));
+        
+        // 地址不能同时关联到其他 principal
+        assert!(crate::auth::link_verified_address(other, segwit).is_err());
+        
+        // 通过 principal 或任一关联地址查询头寸
+        let position = crate::types::Position::new(
+            "linkpool:0".to_string(),
+            "linkpool".to_string(),
+            user.to_string(),
+            100000,
+            1000,
+            3000000,
+        );
+        crate::save_position(position.clone());
+        assert_eq!(get_user_positions(user.to_text()), vec![position.clone()]);
+        assert_eq!(get_user_positions(segwit.to_string()), vec![position.clone()]);
+        assert_eq!(get_user_positions(legacy.to_string()), vec![position]);
+        
+        // 无效签名不能关联新地址
+        let address = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l".to_string();
+        let challenge = crate::auth::request_auth_challenge(address.clone()).await.unwrap();
+        assert!(crate::auth::link_address(address.clone(), "c2ln".to_string(), challenge.message).is_err());
+        assert!(crate::auth::get_principal_by_address(address).is_none());
+        
+        // 解除关联同样需要签名证明
+        assert!(crate::auth::unlink_address(legacy.to_string(), "c2ln".to_string(), "no challenge".to_string()).is_err());
+        assert_eq!(crate::auth::get_linked_addresses(user).len(), 2);
+    }
+
     #[test]
     fn test_protocol_metrics() {
         // 测试协议指标计算
@@ -321,4 +938,50 @@ mod tests {
     async fn test_cleanup() {
         cleanup_test_data();
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_restore_into_empty_state_then_deposit() {
+        use crate::backup_recovery::{create_full_backup, restore_from_backup, RecoveryOptions};
+        use crate::types::Position;
+        
+        let pool_address = test_pool_address();
+        let btc_price = 3_000_000u64;
+        crate::test_utils::init_test_pool(pool_address.clone());
+        let _ = crate::oracle::mock_price_update(btc_price);
+        let owner = crate::test_utils::test_principal(1).to_string();
+        
+        // 来源 canister 中的头寸: 直接写入，不经过本地计数器
+        let restored: Vec<Position> = (0..2).map(|seq| Position::new(
+            format!("{}:{}", pool_address, seq), pool_address.clone(), owner.clone(), 100_000_000, 1_000_000, btc_price,
+        )).collect();
+        for position in &restored {
+            crate::save_position(position.clone());
+        }
+        let backup_id = create_full_backup("source canister".to_string()).unwrap();
+        
+        // 模拟空的新 canister 后恢复
+        for position in &restored {
+            crate::delete_position(&position.id);
+        }
+        let result = restore_from_backup(RecoveryOptions {
+            backup_id,
+            restore_pools: true,
+            restore_positions: true,
+            restore_config: false,
+            verify_integrity: true,
+            create_recovery_point: false,
+            target_time: None,
+        }).unwrap();
+        assert!(result.success, "{:?}", result.errors);
+        assert_eq!(result.restored_positions, 2);
+        
+        // 新的抵押不能覆盖恢复的头寸
+        let bollar_amount = pre_deposit(pool_address.clone(), 100_000_000).unwrap().max_bollar_mint / 2;
+        let position_id = execute_deposit(pool_address.clone(), test_psbt(), bollar_amount).await.unwrap();
+        assert_eq!(position_id, format!("{}:2", pool_address));
+        for position in &restored {
+            assert_eq!(crate::get_position(&position.id).as_ref(), Some(position));
+        }
+        assert_eq!(crate::get_position(&position_id).unwrap().bollar_debt, bollar_amount);
+    }
+}
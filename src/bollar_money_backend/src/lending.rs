@@ -5,6 +5,7 @@ use crate::{Error, LogLevel, Result, types::*};
 use ic_cdk_macros::{query, update};
 use bitcoin::psbt::Psbt;
 use bitcoin::{Transaction, TxOut, Address, Amount};
+use ree_types::{CoinBalance, CoinBalances};
 use std::str::FromStr;
 
 #[query]
@@ -63,7 +64,10 @@ pub async fn execute_deposit(
     let caller = crate::ic_api::caller().to_string();
     
     // 检查紧急状态
-    check_emergency_state!("deposit");
+    check_emergency_state!("deposit", pool_address);
+    
+    // 检查黑名单
+    crate::blocklist::ensure_not_blocked(ic_api::caller(), None, "execute_deposit")?;
     
     // 检查权限
     require_permission!(
@@ -104,7 +108,7 @@ pub async fn execute_deposit(
     let btc_amount = validate_deposit_psbt(&psbt, &pool, bollar_amount)?;
     
     // 生成头寸 ID
-    let position_id = format!("{}:{}:{}", pool_address, crate::ic_api::time(), caller);
+    let position_id = crate::next_position_id(&pool_address);
     
     // 获取当前 BTC 价格
     let btc_price = crate::oracle::get_btc_price();
@@ -127,6 +131,7 @@ pub async fn execute_deposit(
     // 创建新头寸
     let position = Position::new(
         position_id.clone(),
+        pool_address.clone(),
         caller,
         btc_amount,
         bollar_amount,
@@ -153,6 +158,15 @@ pub async fn execute_deposit(
         return Err(e);
     }
     
+    crate::events::record_event(crate::events::ProtocolEvent::PositionOpened {
+        position_id: position_id.clone(),
+        owner: position.owner.clone(),
+        pool_address: pool_address.clone(),
+        btc_collateral: btc_amount,
+        bollar_debt: bollar_amount,
+        btc_price,
+    });
+    
     // 记录成功的抵押操作
     secure_log_info!(
         LogCategory::Transaction,
@@ -164,6 +178,223 @@ pub async fn execute_deposit(
     Ok(position_id)
 }
 
+#[query]
+// 预追加借款查询 - 返回在现有头寸上追加铸造 Bollar 所需的信息
+pub fn pre_borrow_more(
+    position_id: String,
+    bollar_amount: u64,
+) -> Result<BorrowMoreOffer> {
+    // 使用 catch_and_log 包装操作
+    crate::error::catch_and_log(
+        || {
+            // 获取头寸
+            let position = crate::get_position(&position_id)
+                .ok_or(Error::PositionNotFound)?;
+
+            // 验证调用者是否为头寸所有者
+            let caller = crate::ic_api::caller().to_string();
+            if position.owner != caller {
+                return Err(Error::PermissionDenied("不是头寸所有者".to_string()));
+            }
+
+            // 获取池
+            let pool = crate::get_pool(&position.pool_address)
+                .ok_or(Error::InvalidPool)?;
+
+            // 获取当前 BTC 价格
+            let btc_price = crate::oracle::get_btc_price();
+            if btc_price == 0 {
+                return Err(Error::OracleError("无效的 BTC 价格".to_string()));
+            }
+
+            // 计算可追加铸造的最大 Bollar 数量
+            let max_additional_bollar = calculate_max_additional_bollar(&pool, &position, btc_price);
+            if bollar_amount == 0 || bollar_amount > max_additional_bollar {
+                return Err(Error::InvalidArgument(format!(
+                    "无效的追加借款金额，应在 1 到 {} 之间",
+                    max_additional_bollar
+                )));
+            }
+
+            let new_bollar_debt = position.bollar_debt
+                .checked_add(bollar_amount)
+                .ok_or(Error::Overflow)?;
+
+            // 构建追加借款预处理结果
+            let offer = BorrowMoreOffer {
+                pool_utxo: pool.current_state()
+                    .and_then(|s| s.utxo.clone())
+                    .ok_or(Error::InvalidState("池 UTXO 不存在".to_string()))?,
+                nonce: pool.current_nonce(),
+                btc_price,
+                max_additional_bollar,
+                new_bollar_debt,
+                new_health_factor: crate::types::calculate_health_factor(
+                    position.btc_collateral,
+                    new_bollar_debt,
+                    btc_price
+                ),
+            };
+
+            Ok(offer)
+        },
+        LogLevel::Debug,
+        &format!("pre_borrow_more: 预追加借款查询失败, id={}", position_id)
+    )
+}
+
+#[update]
+// 在现有头寸上追加铸造 Bollar
+pub async fn execute_borrow_more(
+    position_id: String,
+    signed_psbt: String,
+    bollar_amount: u64,
+) -> Result<String> {
+    // 获取调用者身份
+    let caller = crate::ic_api::caller().to_string();
+
+    // 从头寸获取池地址
+    let pool_address = crate::get_position(&position_id)
+        .map(|position| position.pool_address)
+        .ok_or(Error::PositionNotFound)?;
+
+    // 检查紧急状态
+    check_emergency_state!("borrow", pool_address);
+
+    // 检查黑名单
+    crate::blocklist::ensure_not_blocked(ic_api::caller(), None, "execute_borrow_more")?;
+
+    // 检查权限
+    require_permission!(
+        crate::access_control::has_permission(ic_api::caller(), crate::access_control::Permission::Deposit),
+        "Deposit permission required"
+    );
+
+    // 获取组合锁，防止重入攻击
+    let _guard = crate::CombinedGuard::new(caller.clone(), pool_address.clone())
+        .ok_or(Error::SystemError("系统繁忙，请稍后重试".to_string()))?;
+
+    // 性能测量开始
+    let _measurement = crate::performance::PerformanceManager::start_measurement("execute_borrow_more");
+
+    // 验证参数
+    crate::input_validation::validate_bollar_amount(bollar_amount, "execute_borrow_more")?;
+    crate::input_validation::validate_psbt_hex(&signed_psbt, "execute_borrow_more")?;
+
+    // 获取头寸
+    let position = crate::get_position(&position_id)
+        .ok_or(Error::PositionNotFound)?;
+
+    // 验证调用者是否为头寸所有者
+    if position.owner != caller {
+        return Err(Error::PermissionDenied("不是头寸所有者".to_string()));
+    }
+
+    // 获取池
+    let pool = crate::get_pool(&pool_address)
+        .ok_or(Error::InvalidPool)?;
+
+    // 解码并验证 PSBT: 必须花费池当前 UTXO，并恰好把 bollar_amount 转给头寸所有者
+    let psbt = validate_and_parse_psbt(&signed_psbt, &position_id, bollar_amount)?;
+    let new_pool_state = validate_borrow_more_psbt(&psbt, &pool, &position, bollar_amount)?;
+
+    // 同时更新头寸债务和池状态
+    let updated_position = commit_borrow(&position, pool, new_pool_state, bollar_amount)?;
+    
+    // 记录成功的追加借款操作
+    secure_log_info!(
+        LogCategory::Transaction,
+        format!("Borrow more executed successfully: position_id={}", position_id),
+        format!("Bollar: {}, New debt: {}, Health factor: {}, User: {}",
+                bollar_amount, updated_position.bollar_debt, updated_position.health_factor, caller)
+    );
+
+    // 返回交易 ID
+    Ok(format!("borrow:{}", psbt.unsigned_tx.txid()))
+}
+
+// 在一个状态事务中记入追加借款: 增加头寸债务并提交池的新状态
+// execute_borrow_more 和 REE 的 borrow 操作共用；两者都要求花费池的当前 UTXO，
+// 因此同一笔交易只能被记入一次
+pub(crate) fn commit_borrow(
+    position: &Position,
+    mut pool: Pool,
+    new_pool_state: PoolState,
+    bollar_amount: u64,
+) -> Result<Position> {
+    // 获取当前 BTC 价格
+    let btc_price = crate::oracle::get_btc_price();
+    if btc_price == 0 {
+        return Err(Error::OracleError("无效的 BTC 价格".to_string()));
+    }
+
+    // 验证追加数量不超过当前价格下的最大值
+    let max_additional_bollar = calculate_max_additional_bollar(&pool, position, btc_price);
+    if bollar_amount > max_additional_bollar {
+        return Err(Error::InvalidArgument(format!(
+            "追加铸造数量 {} 超过最大值 {}",
+            bollar_amount,
+            max_additional_bollar
+        )));
+    }
+
+    // 验证池中有足够的 Bollar 可供发放
+    if pool.bollar_balance() < bollar_amount as u128 {
+        return Err(Error::InsufficientFunds);
+    }
+
+    let new_bollar_debt = position.bollar_debt
+        .checked_add(bollar_amount)
+        .ok_or(Error::Overflow)?;
+
+    // 所有校验完成后开始状态事务，头寸与池状态一起更新
+    let tx_id = crate::state_manager::StateManager::begin_transaction(
+        crate::state_manager::StateOperation::UpdatePosition
+    )?;
+
+    // 更新头寸债务和健康因子
+    let mut updated_position = position.clone();
+    updated_position.update(
+        position.btc_collateral,
+        new_bollar_debt,
+        btc_price,
+    );
+    crate::save_position(updated_position.clone());
+    crate::performance::cache_position(updated_position.clone());
+
+    // 提交池的新状态，池的 Bollar 余额随之减少
+    pool.commit(new_pool_state);
+    crate::save_pool(pool.clone());
+    crate::performance::cache_pool(pool);
+
+    // 验证头寸已更新
+    let saved_debt = crate::get_position(&position.id).map(|p| p.bollar_debt);
+    if saved_debt != Some(new_bollar_debt) {
+        // 回滚事务
+        if let Err(e) = crate::state_manager::StateManager::rollback_transaction(tx_id, "头寸更新失败".to_string()) {
+            secure_log_error!(LogCategory::System, format!("Transaction rollback failed: {:?}", e));
+        }
+        return Err(Error::InvalidState("头寸更新失败".to_string()));
+    }
+
+    // 提交事务
+    if let Err(e) = crate::state_manager::StateManager::commit_transaction(tx_id) {
+        secure_log_error!(LogCategory::System, format!("Transaction commit failed: {:?}", e));
+        return Err(e);
+    }
+
+    crate::events::record_event(crate::events::ProtocolEvent::Borrowed {
+        position_id: position.id.clone(),
+        owner: position.owner.clone(),
+        pool_address: updated_position.pool_address.clone(),
+        bollar_amount,
+        new_bollar_debt,
+        btc_price,
+    });
+
+    Ok(updated_position)
+}
+
 #[query]
 // 预还款查询 - 返回用户需要的信息来构建还款交易
 pub fn pre_repay(
@@ -192,8 +423,7 @@ pub fn pre_repay(
             }
             
             // 获取池
-            let pool_address = position_id.split(':').next().unwrap_or("");
-            let pool = crate::get_pool(&pool_address.to_string())
+            let pool = crate::get_pool(&position.pool_address)
                 .ok_or(Error::InvalidPool)?;
             
             // 获取当前 BTC 价格
@@ -230,13 +460,17 @@ pub async fn execute_repay(
     // 获取调用者身份
     let caller = crate::ic_api::caller().to_string();
     
-    // 从 position_id 提取池地址
-    let pool_address = position_id.split(':').next()
-        .ok_or(Error::InvalidArgument("无效的头寸ID格式".to_string()))?
-        .to_string();
+    // 从头寸获取池地址
+    let pool_address = crate::get_position(&position_id)
+        .map(|position| position.pool_address)
+        .ok_or(Error::PositionNotFound)?;
     
-    // 检查紧急状态
-    check_emergency_state!("repay");
+    // 检查紧急状态 (还款同时赎回 BTC)
+    check_emergency_state!("repay", pool_address);
+    check_emergency_state!("redeem", pool_address);
+    
+    // 检查黑名单
+    crate::blocklist::ensure_not_blocked(ic_api::caller(), None, "execute_repay")?;
     
     // 检查权限
     require_permission!(
@@ -308,6 +542,16 @@ pub async fn execute_repay(
         return Err(e);
     }
     
+    crate::events::record_event(crate::events::ProtocolEvent::Repaid {
+        position_id: position_id.clone(),
+        owner: position.owner.clone(),
+        pool_address: position.pool_address.clone(),
+        bollar_repaid: bollar_amount,
+        btc_returned: btc_return as u64,
+        remaining_debt: position.bollar_debt - bollar_amount,
+        closed: bollar_amount == position.bollar_debt,
+    });
+    
     // 返回交易 ID
     Ok(format!("repay:{}", crate::ic_api::time()))
 }
@@ -320,9 +564,9 @@ async fn init_pool() -> std::result::Result<(), String> {
 }
 
 #[query]
-// 获取用户头寸列表
+// 获取用户头寸列表，user 可以是 principal 或其关联的任一比特币地址
 pub fn get_user_positions(user: String) -> Vec<Position> {
-    crate::get_user_positions(&user)
+    crate::get_user_positions(&crate::auth::resolve_owner(&user))
 }
 
 #[query]
@@ -422,6 +666,12 @@ pub fn update_position_health_factor(position_id: String) -> Result<u64> {
     )
 }
 
+// 计算头寸在当前价格下可追加铸造的最大 Bollar 数量
+fn calculate_max_additional_bollar(pool: &Pool, position: &Position, btc_price: u64) -> u64 {
+    pool.calculate_max_bollar(position.btc_collateral, btc_price)
+        .saturating_sub(position.bollar_debt)
+}
+
 // PSBT 验证和解析函数
 fn validate_and_parse_psbt(psbt_hex: &str, context: &str, expected_amount: u64) -> Result<Psbt> {
     // 解码十六进制字符串
@@ -576,6 +826,199 @@ fn validate_deposit_psbt(psbt: &Psbt, pool: &Pool, expected_bollar: u64) -> Resu
     Ok(btc_to_pool)
 }
 
+// 验证追加借款 PSBT，返回池的新状态
+// 交易必须花费池的当前 UTXO，把全部 BTC 返还给池，并通过 Runestone 恰好向头寸所有者
+// 关联的地址转出 bollar_amount 的 Bollar，其余 Bollar 经 pointer 回到池输出
+fn validate_borrow_more_psbt(
+    psbt: &Psbt,
+    pool: &Pool,
+    position: &Position,
+    bollar_amount: u64,
+) -> Result<PoolState> {
+    let pool_utxo = pool.current_state()
+        .and_then(|s| s.utxo.clone())
+        .ok_or(Error::EmptyPool)?;
+
+    // 必须花费池的当前 UTXO
+    let spends_pool_utxo = psbt.unsigned_tx.input.iter()
+        .any(|input| input.previous_output.to_string() == pool_utxo.outpoint);
+    if !spends_pool_utxo {
+        return Err(Error::InvalidArgument("PSBT 必须花费池的当前 UTXO".to_string()));
+    }
+
+    // 恰好一个输出回到池地址，且 BTC 余额不变
+    let pool_script = Address::from_str(&pool.addr)
+        .map_err(|_| Error::InvalidArgument("无效的池地址".to_string()))?
+        .assume_checked()
+        .script_pubkey();
+    let pool_outputs: Vec<usize> = psbt.unsigned_tx.output.iter()
+        .enumerate()
+        .filter(|(_, output)| output.script_pubkey == pool_script)
+        .map(|(vout, _)| vout)
+        .collect();
+    let pool_vout = match pool_outputs.as_slice() {
+        [vout] => *vout,
+        _ => return Err(Error::InvalidArgument("PSBT 必须包含且只包含一个池地址输出".to_string())),
+    };
+    if psbt.unsigned_tx.output[pool_vout].value.to_sat() != pool_utxo.sats {
+        return Err(Error::InvalidArgument("追加借款不能改变池的 BTC 余额".to_string()));
+    }
+
+    // 头寸所有者关联的地址
+    let owner = candid::Principal::from_text(&position.owner)
+        .map_err(|_| Error::InvalidState("无效的头寸所有者".to_string()))?;
+    let owner_scripts: Vec<bitcoin::ScriptBuf> = crate::auth::get_linked_addresses(owner)
+        .into_iter()
+        .filter_map(|linked| Address::from_str(&linked.address).ok())
+        .map(|address| address.assume_checked().script_pubkey())
+        .collect();
+
+    // 解析 Runestone 并核对 Bollar 的去向
+    let runestone = psbt.unsigned_tx.output.iter()
+        .find_map(|output| decode_runestone(&output.script_pubkey).transpose())
+        .ok_or(Error::InvalidArgument("PSBT 缺少 Runestone".to_string()))??;
+    if runestone.pointer != Some(pool_vout as u32) {
+        return Err(Error::InvalidArgument("Runestone 的 pointer 必须指向池输出".to_string()));
+    }
+
+    let mut bollar_to_owner: u128 = 0;
+    for edict in &runestone.edicts {
+        if CoinId::rune(edict.block, edict.tx) != pool.meta.id {
+            continue;
+        }
+        let output = psbt.unsigned_tx.output.get(edict.output as usize)
+            .ok_or(Error::InvalidArgument("Runestone edict 输出索引无效".to_string()))?;
+        if edict.amount == 0 || !owner_scripts.contains(&output.script_pubkey) {
+            return Err(Error::InvalidArgument("Bollar 只能转给头寸所有者关联的地址".to_string()));
+        }
+        bollar_to_owner = bollar_to_owner.checked_add(edict.amount)
+            .ok_or(Error::Overflow)?;
+    }
+    if bollar_to_owner != bollar_amount as u128 {
+        return Err(Error::InvalidArgument(format!(
+            "转给所有者的 Bollar 数量 {} 与追加数量 {} 不符",
+            bollar_to_owner, bollar_amount
+        )));
+    }
+
+    // 构建池的新 UTXO 和状态
+    let remaining_bollar = pool.bollar_balance()
+        .checked_sub(bollar_amount as u128)
+        .ok_or(Error::InsufficientFunds)?;
+    let txid = psbt.unsigned_tx.txid().to_string();
+    let mut coins = CoinBalances::new();
+    coins.add_coin(&CoinBalance { id: pool.meta.id, value: remaining_bollar });
+    let utxo = Utxo::try_from(format!("{}:{}", txid, pool_vout), coins, pool_utxo.sats)
+        .map_err(|e| Error::InvalidState(format!("无法构建池 UTXO: {:?}", e)))?;
+
+    Ok(PoolState {
+        id: Some(txid.parse().map_err(|_| Error::InvalidTxid)?),
+        nonce: pool.current_nonce() + 1,
+        utxo: Some(utxo),
+        btc_price: crate::oracle::get_btc_price(),
+    })
+}
+
+// Runestone 中的一条 edict
+struct RuneEdict {
+    block: u64,
+    tx: u32,
+    amount: u128,
+    output: u32,
+}
+
+// 解析后的 Runestone (只保留借款校验需要的字段)
+struct Runestone {
+    pointer: Option<u32>,
+    edicts: Vec<RuneEdict>,
+}
+
+// 从输出脚本解析 Runestone: OP_RETURN OP_13 <数据推送...>，数据为 LEB128 整数序列
+// 不是 Runestone 的脚本返回 Ok(None)
+fn decode_runestone(script: &bitcoin::Script) -> Result<Option<Runestone>> {
+    use bitcoin::blockdata::opcodes::all::{OP_PUSHNUM_13, OP_RETURN};
+    use bitcoin::blockdata::script::Instruction;
+
+    const TAG_BODY: u128 = 0;
+    const TAG_POINTER: u128 = 22;
+    let invalid = || Error::InvalidArgument("无效的 Runestone".to_string());
+
+    let mut instructions = script.instructions();
+    match (instructions.next(), instructions.next()) {
+        (Some(Ok(Instruction::Op(OP_RETURN))), Some(Ok(Instruction::Op(OP_PUSHNUM_13)))) => {}
+        _ => return Ok(None),
+    }
+
+    let mut payload = Vec::new();
+    for instruction in instructions {
+        match instruction.map_err(|_| invalid())? {
+            Instruction::PushBytes(bytes) => payload.extend_from_slice(bytes.as_bytes()),
+            Instruction::Op(_) => return Err(invalid()),
+        }
+    }
+
+    // LEB128 解码
+    let mut integers = Vec::new();
+    let mut cursor = 0;
+    while cursor < payload.len() {
+        let mut value: u128 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = *payload.get(cursor).ok_or_else(invalid)?;
+            cursor += 1;
+            if shift > 126 {
+                return Err(invalid());
+            }
+            value |= u128::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        integers.push(value);
+    }
+
+    // 标签/值对，遇到 Body 标签后其余整数均为 edict
+    let mut runestone = Runestone { pointer: None, edicts: Vec::new() };
+    let mut index = 0;
+    let mut body: &[u128] = &[];
+    while index < integers.len() {
+        if integers[index] == TAG_BODY {
+            body = &integers[index + 1..];
+            break;
+        }
+        let value = *integers.get(index + 1).ok_or_else(invalid)?;
+        if integers[index] == TAG_POINTER {
+            runestone.pointer = Some(u32::try_from(value).map_err(|_| invalid())?);
+        }
+        index += 2;
+    }
+
+    // edict 的符文 ID 为增量编码
+    if body.len() % 4 != 0 {
+        return Err(invalid());
+    }
+    let (mut block, mut tx) = (0u64, 0u32);
+    for edict in body.chunks(4) {
+        let block_delta = u64::try_from(edict[0]).map_err(|_| invalid())?;
+        let tx_delta = u32::try_from(edict[1]).map_err(|_| invalid())?;
+        block = block.checked_add(block_delta).ok_or_else(invalid)?;
+        tx = if block_delta == 0 {
+            tx.checked_add(tx_delta).ok_or_else(invalid)?
+        } else {
+            tx_delta
+        };
+        runestone.edicts.push(RuneEdict {
+            block,
+            tx,
+            amount: edict[2],
+            output: u32::try_from(edict[3]).map_err(|_| invalid())?,
+        });
+    }
+
+    Ok(Some(runestone))
+}
+
 // 验证还款 PSBT
 fn validate_repay_psbt(psbt: &Psbt, position: &Position) -> Result<u64> {
     // 这里需要验证 PSBT 包含正确的 Bollar 代币输入
@@ -11,6 +11,7 @@ mod error_tests;
 mod exchange_tests;
 mod stability;
 mod auth;
+mod bip322;
 mod integration_tests;
 mod e2e_tests;
 mod ic_api;
@@ -22,8 +23,16 @@ mod state_manager;
 mod medium_priority_tests;
 mod input_validation;
 mod monitoring;
+mod alert_notifier;
+mod scheduler;
+mod audit;
+mod events;
 mod performance;
 mod backup_recovery;
+mod position_management;
+mod governance;
+mod blocklist;
+mod http;
 
 #[cfg(test)]
 mod test_utils;
@@ -48,6 +57,13 @@ const SCHNORR_KEY_NAME: &str = "bollar_key_1";
 
 // 使用 error.rs 中定义的 Error 类型
 
+// 健康因子索引的参考价格 (USD cents)
+// 健康因子与价格成正比，按固定参考价格建立索引，查询时再按当前价格换算
+const HEALTH_INDEX_REFERENCE_PRICE: u64 = 10_000_000; // $100,000.00
+
+// 健康因子索引桶宽度
+const HEALTH_INDEX_BUCKET_WIDTH: u64 = 10;
+
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 
 thread_local! {
@@ -83,6 +99,27 @@ thread_local! {
         )
     );
     
+    // 头寸序号计数器 (pool_address -> 下一个序号)
+    static POSITION_COUNTERS: RefCell<StableBTreeMap<String, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(4))),
+        )
+    );
+    
+    // 按所有者索引头寸 (owner|position_id)
+    static POSITIONS_BY_OWNER: RefCell<StableBTreeMap<String, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(5))),
+        )
+    );
+    
+    // 按健康因子桶索引头寸 (bucket|position_id)
+    static POSITIONS_BY_HEALTH: RefCell<StableBTreeMap<String, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(6))),
+        )
+    );
+    
     // 正在执行交易的池
     static EXECUTING_POOLS: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
 }
@@ -99,9 +136,20 @@ pub(crate) fn get_pool(addr: &String) -> Option<Pool> {
 
 // 保存资金池
 pub(crate) fn save_pool(pool: Pool) {
-    POOLS.with_borrow_mut(|p| {
-        p.insert(pool.addr.clone(), pool);
+    let previous = POOLS.with_borrow_mut(|p| {
+        p.insert(pool.addr.clone(), pool.clone())
     });
+    
+    backup_recovery::BackupManager::record_change(backup_recovery::EntityType::Pool, &pool.addr, previous.as_ref(), Some(&pool));
+}
+
+// 删除资金池 (仅用于从备份恢复)
+pub(crate) fn delete_pool(addr: &String) {
+    let removed = POOLS.with_borrow_mut(|p| p.remove(addr));
+    
+    if let Some(removed) = removed {
+        backup_recovery::BackupManager::record_change(backup_recovery::EntityType::Pool, addr, Some(&removed), None);
+    }
 }
 
 // 获取所有头寸
@@ -116,26 +164,173 @@ pub(crate) fn get_position(position_id: &String) -> Option<Position> {
 
 // 获取用户的所有头寸
 pub(crate) fn get_user_positions(user: &String) -> Vec<Position> {
-    POSITIONS.with_borrow(|p| {
-        p.iter()
-            .filter(|(_, pos)| pos.owner == *user)
-            .map(|(_, pos)| pos.clone())
+    let prefix = owner_index_key(user, "");
+    let position_ids: Vec<String> = POSITIONS_BY_OWNER.with_borrow(|index| {
+        index.range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, _)| key[prefix.len()..].to_string())
             .collect()
-    })
+    });
+    
+    position_ids.iter().filter_map(get_position).collect()
+}
+
+// 获取在当前价格下可能可清算的头寸 (候选集，调用方需按池的清算阈值再次确认)
+pub(crate) fn get_liquidation_candidates(btc_price: u64, max_liquidation_threshold: u8) -> Vec<Position> {
+    if btc_price == 0 {
+        return vec![];
+    }
+    
+    // 当前价格下 health < threshold 等价于参考价格下 health < threshold * REF / price
+    // 多取两个单位以覆盖整数除法的舍入误差
+    let reference_cutoff = (max_liquidation_threshold as u128)
+        * (HEALTH_INDEX_REFERENCE_PRICE as u128) / (btc_price as u128) + 2;
+    let end_bucket = (reference_cutoff / HEALTH_INDEX_BUCKET_WIDTH as u128).min(u64::MAX as u128) as u64;
+    let end_key = format!("{:020}|", end_bucket.saturating_add(1));
+    
+    let position_ids: Vec<String> = POSITIONS_BY_HEALTH.with_borrow(|index| {
+        index.range(..end_key)
+            .filter_map(|(key, _)| key.split_once('|').map(|(_, id)| id.to_string()))
+            .collect()
+    });
+    
+    position_ids.iter().filter_map(get_position).collect()
 }
 
 // 保存头寸
 pub(crate) fn save_position(position: Position) {
-    POSITIONS.with_borrow_mut(|p| {
-        p.insert(position.id.clone(), position);
+    let previous = POSITIONS.with_borrow_mut(|p| {
+        p.insert(position.id.clone(), position.clone())
     });
+    
+    if let Some(previous) = &previous {
+        unindex_position(previous);
+    }
+    index_position(&position);
+    
+    backup_recovery::BackupManager::record_change(backup_recovery::EntityType::Position, &position.id, previous.as_ref(), Some(&position));
 }
 
 // 删除头寸
 pub(crate) fn delete_position(position_id: &String) {
-    POSITIONS.with_borrow_mut(|p| {
-        p.remove(position_id);
+    let removed = POSITIONS.with_borrow_mut(|p| {
+        p.remove(position_id)
     });
+    
+    if let Some(removed) = removed {
+        unindex_position(&removed);
+        backup_recovery::BackupManager::record_change(backup_recovery::EntityType::Position, position_id, Some(&removed), None);
+    }
+}
+
+// 所有者索引键
+fn owner_index_key(owner: &str, position_id: &str) -> String {
+    format!("{}|{}", owner, position_id)
+}
+
+// 健康因子索引键 (桶号补零，保证字典序与数值序一致)
+fn health_index_key(position: &Position) -> String {
+    let reference_health = types::calculate_health_factor(
+        position.btc_collateral,
+        position.bollar_debt,
+        HEALTH_INDEX_REFERENCE_PRICE
+    );
+    format!("{:020}|{}", reference_health / HEALTH_INDEX_BUCKET_WIDTH, position.id)
+}
+
+// 添加头寸索引
+fn index_position(position: &Position) {
+    POSITIONS_BY_OWNER.with_borrow_mut(|index| {
+        index.insert(owner_index_key(&position.owner, &position.id), ());
+    });
+    POSITIONS_BY_HEALTH.with_borrow_mut(|index| {
+        index.insert(health_index_key(position), ());
+    });
+}
+
+// 移除头寸索引
+fn unindex_position(position: &Position) {
+    POSITIONS_BY_OWNER.with_borrow_mut(|index| {
+        index.remove(&owner_index_key(&position.owner, &position.id));
+    });
+    POSITIONS_BY_HEALTH.with_borrow_mut(|index| {
+        index.remove(&health_index_key(position));
+    });
+}
+
+// 重建头寸二级索引
+pub(crate) fn rebuild_position_indexes() {
+    for index in [&POSITIONS_BY_OWNER, &POSITIONS_BY_HEALTH] {
+        index.with_borrow_mut(|index| {
+            let keys: Vec<String> = index.iter().map(|(key, _)| key).collect();
+            for key in keys {
+                index.remove(&key);
+            }
+        });
+    }
+    
+    POSITIONS.with_borrow(|p| {
+        for (_, position) in p.iter() {
+            index_position(&position);
+        }
+    });
+}
+
+// 为资金池分配下一个头寸 ID (格式: pool_address:序号)
+// 跳过已存在的 ID: 从备份恢复或导入的头寸不经过计数器，不能被新头寸覆盖
+pub(crate) fn next_position_id(pool_address: &String) -> String {
+    POSITION_COUNTERS.with_borrow_mut(|counters| {
+        let mut seq = counters.get(pool_address).unwrap_or(0);
+        while POSITIONS.with_borrow(|p| p.contains_key(&format!("{}:{}", pool_address, seq))) {
+            seq += 1;
+        }
+        counters.insert(pool_address.clone(), seq + 1);
+        format!("{}:{}", pool_address, seq)
+    })
+}
+
+// 迁移旧头寸: 从旧格式 ID (pool_address:timestamp:caller) 中补全 pool_address 字段
+pub(crate) fn migrate_positions() -> u64 {
+    let legacy: Vec<Position> = POSITIONS.with_borrow(|p| {
+        p.iter()
+            .filter(|(_, pos)| pos.pool_address.is_empty())
+            .map(|(_, pos)| pos.clone())
+            .collect()
+    });
+    
+    let mut migrated = 0;
+    for mut position in legacy {
+        let pool_address = position.id.split(':').next().unwrap_or("").to_string();
+        if pool_address.is_empty() {
+            continue;
+        }
+        position.pool_address = pool_address;
+        save_position(position);
+        migrated += 1;
+    }
+    migrated
+}
+
+#[ic_cdk_macros::init]
+// 初始化时登记默认指标和告警规则并启动后台任务
+fn init() {
+    monitoring::MonitoringManager::initialize_default_metrics();
+    scheduler::start_jobs();
+}
+
+#[ic_cdk_macros::post_upgrade]
+// 升级后执行数据迁移并重新启动后台任务 (定时器不会跨升级保留)
+fn post_upgrade() {
+    let migrated = migrate_positions();
+    if migrated > 0 {
+        ic_cdk::println!("Migrated {} legacy positions", migrated);
+    }
+    
+    rebuild_position_indexes();
+    
+    // 指标只保存在堆内存中，升级后重新登记；告警规则保存在稳定内存中，不会被覆盖
+    monitoring::MonitoringManager::initialize_default_metrics();
+    scheduler::start_jobs();
 }
 
 // 保存交易记录
@@ -10,21 +10,20 @@ const LIQUIDATION_BONUS_PERCENT: u8 = 10; // 10% 奖励
 #[query]
 // 获取可清算头寸列表
 pub fn get_liquidatable_positions() -> Vec<LiquidationOffer> {
-    // 获取所有头寸
-    let positions = crate::get_positions();
-    
     // 获取当前 BTC 价格
     let btc_price = crate::oracle::get_btc_price();
     if btc_price == 0 {
         return vec![];
     }
     
+    // 通过健康因子索引获取候选头寸
+    let positions = crate::get_liquidation_candidates(btc_price, max_liquidation_threshold());
+    
     // 筛选可清算的头寸
     positions.iter()
         .filter_map(|position| {
             // 获取池信息以获取清算阈值
-            let pool_address = position.id.split(':').next().unwrap_or("");
-            let pool = crate::get_pool(&pool_address.to_string())?;
+            let pool = crate::get_pool(&position.pool_address)?;
             
             // 重新计算当前健康因子
             let current_health_factor = crate::types::calculate_health_factor(
@@ -73,8 +72,7 @@ pub fn pre_liquidate(
                 .ok_or(Error::PositionNotFound)?;
             
             // 获取池信息以获取清算阈值
-            let pool_address = position_id.split(':').next().unwrap_or("");
-            let pool = crate::get_pool(&pool_address.to_string())
+            let pool = crate::get_pool(&position.pool_address)
                 .ok_or(Error::InvalidPool)?;
             
             // 获取当前 BTC 价格
@@ -136,6 +134,12 @@ pub async fn execute_liquidate(
 ) -> Result<String> {
     // 检查紧急状态
     check_emergency_state!("liquidate");
+    if let Some(position) = crate::get_position(&position_id) {
+        check_emergency_state!("liquidate", position.pool_address);
+    }
+    
+    // 检查黑名单
+    crate::blocklist::ensure_not_blocked(ic_api::caller(), None, "execute_liquidate")?;
     
     // 检查权限
     require_permission!(
@@ -152,8 +156,7 @@ pub async fn execute_liquidate(
         .ok_or(Error::PositionNotFound)?;
     
     // 获取池信息以获取清算阈值
-    let pool_address = position_id.split(':').next().unwrap_or("");
-    let pool = crate::get_pool(&pool_address.to_string())
+    let pool = crate::get_pool(&position.pool_address)
         .ok_or(Error::InvalidPool)?;
     
     // 获取当前 BTC 价格
@@ -233,6 +236,17 @@ pub async fn execute_liquidate(
         return Err(e);
     }
     
+    crate::events::record_event(crate::events::ProtocolEvent::Liquidated {
+        position_id: position_id.clone(),
+        owner: position.owner.clone(),
+        pool_address: position.pool_address.clone(),
+        liquidator: caller.clone(),
+        bollar_repaid: bollar_repay_amount,
+        btc_seized: liquidator_btc_with_bonus as u64,
+        remaining_debt: position.bollar_debt - bollar_repay_amount,
+        closed: bollar_repay_amount == position.bollar_debt,
+    });
+    
     // 记录清算事件
     secure_log_info!(
         LogCategory::Liquidation,
@@ -255,6 +269,14 @@ pub fn calculate_health_factor(
     crate::types::calculate_health_factor(btc_collateral, bollar_debt, btc_price)
 }
 
+// 获取所有资金池中最高的清算阈值
+pub(crate) fn max_liquidation_threshold() -> u8 {
+    crate::get_pools().iter()
+        .map(|pool| pool.liquidation_threshold)
+        .max()
+        .unwrap_or(0)
+}
+
 // 计算清算奖励 (使用安全数学运算)
 pub fn calculate_liquidation_reward(
     bollar_repay_amount: u64,
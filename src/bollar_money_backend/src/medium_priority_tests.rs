@@ -134,6 +134,204 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_emergency_multisig_resume() {
+        use crate::emergency::*;
+        use crate::test_utils::mock;
+        
+        let operator1 = mock::test_principal(1);
+        let operator2 = mock::test_principal(2);
+        mock::set_controllers(vec![operator1]);
+        mock::set_caller(operator1);
+        add_emergency_operator(operator2.to_string()).unwrap();
+        
+        // 单个操作员即可暂停
+        emergency_pause("Incident".to_string()).unwrap();
+        assert_eq!(get_emergency_state().state, EmergencyState::Paused);
+        
+        // 恢复需要第二个操作员审批
+        let pending = emergency_resume("Resolved".to_string()).unwrap();
+        assert!(!pending.executed);
+        assert_eq!(get_emergency_state().state, EmergencyState::Paused);
+        assert!(approve_emergency_action(pending.id).is_err(), "Proposer cannot approve twice");
+        
+        mock::set_caller(operator2);
+        let approved = approve_emergency_action(pending.id).unwrap();
+        assert!(approved.executed);
+        assert_eq!(get_emergency_state().state, EmergencyState::Normal);
+        assert!(get_pending_emergency_actions().is_empty());
+        
+        // 过期的审批不能再被执行
+        let pending = set_maintenance_mode(2, "Upgrade".to_string()).unwrap();
+        mock::set_time(pending.expires_at + 1);
+        mock::set_caller(operator1);
+        assert!(approve_emergency_action(pending.id).is_err());
+        assert_eq!(get_emergency_state().state, EmergencyState::Normal);
+        
+        // 阈值必须大于 1 且不超过操作员人数
+        let operator3 = mock::test_principal(3);
+        let operator4 = mock::test_principal(4);
+        assert!(set_emergency_multisig_threshold(1).is_err());
+        assert!(set_emergency_multisig_threshold(2).is_err());
+        add_emergency_operator(operator3.to_string()).unwrap();
+        assert!(set_emergency_multisig_threshold(3).is_err());
+        set_emergency_multisig_threshold(2).unwrap();
+        
+        // 较新的暂停使之前发起的恢复提案失效
+        emergency_pause("Incident".to_string()).unwrap();
+        let stale = emergency_resume("Resolved".to_string()).unwrap();
+        mock::set_caller(operator2);
+        emergency_pause("Second incident".to_string()).unwrap();
+        mock::set_caller(operator3);
+        assert!(approve_emergency_action(stale.id).is_err());
+        assert_eq!(get_emergency_state().state, EmergencyState::Paused);
+        
+        // 已移除操作员的审批在执行时不再计入
+        mock::set_caller(operator2);
+        let pending = emergency_resume("Resolved".to_string()).unwrap();
+        mock::set_caller(operator1);
+        assert!(remove_emergency_operator(operator3.to_string()).is_err(), "Cannot drop below threshold");
+        add_emergency_operator(operator4.to_string()).unwrap();
+        remove_emergency_operator(operator2.to_string()).unwrap();
+        mock::set_caller(operator3);
+        assert!(!approve_emergency_action(pending.id).unwrap().executed);
+        assert_eq!(get_emergency_state().state, EmergencyState::Paused);
+        mock::set_caller(operator4);
+        assert!(approve_emergency_action(pending.id).unwrap().executed);
+        assert_eq!(get_emergency_state().state, EmergencyState::Normal);
+    }
+
+    #[test]
+    fn test_pause_flags_scoping() {
+        use crate::emergency::*;
+        
+        // 初始化测试池 (调用者为控制者)
+        let pool = "bc1qpausedpool".to_string();
+        crate::test_utils::init_test_pool(pool.clone());
+        
+        // 只暂停一个池的存款
+        emergency_pause_operation(PauseOperation::Deposit, Some(pool.clone()), "Pool incident".to_string()).unwrap();
+        assert!(!is_operation_allowed_for_pool("deposit".to_string(), pool.clone()));
+        assert!(!is_operation_allowed_for_pool("borrow".to_string(), pool.clone()));
+        assert!(is_operation_allowed_for_pool("deposit".to_string(), "bc1qotherpool".to_string()));
+        assert!(is_operation_allowed("deposit".to_string()));
+        
+        // REE 路径的 execute_tx 同样遵守池级暂停
+        assert!(crate::exchange::ensure_action_allowed("borrow", &pool).is_err());
+        assert!(crate::exchange::ensure_action_allowed("repay", &pool).is_ok());
+        assert!(crate::exchange::ensure_action_allowed("borrow", "bc1qotherpool").is_ok());
+        
+        // 全局暂停清算可以与池级存款暂停共存
+        emergency_partial_pause("liquidate".to_string(), "Oracle issue".to_string()).unwrap();
+        assert!(!is_operation_allowed("liquidate".to_string()));
+        assert!(!is_operation_allowed_for_pool("deposit".to_string(), pool.clone()));
+        assert!(is_operation_allowed_for_pool("repay".to_string(), pool.clone()));
+        assert_eq!(get_emergency_state().state, EmergencyState::Normal);
+        assert_eq!(get_pause_flags().len(), 2);
+        
+        // "withdraw" 作为赎回的别名，暂停后 execute_repay 检查的 "redeem" 同样被禁止
+        emergency_partial_pause("withdraw".to_string(), "Custody check".to_string()).unwrap();
+        assert!(!is_operation_allowed_for_pool("redeem".to_string(), pool.clone()));
+        assert_eq!(get_pause_flags().len(), 3);
+        
+        // 解除暂停需要第二个操作员审批
+        let pending = emergency_resume_operation(PauseOperation::Deposit, Some(pool.clone()), "Fixed".to_string()).unwrap();
+        assert!(!pending.executed);
+        assert!(!is_operation_allowed_for_pool("deposit".to_string(), pool.clone()));
+        let operator2 = crate::test_utils::test_principal(2);
+        add_emergency_operator(operator2.to_string()).unwrap();
+        crate::test_utils::set_caller(operator2);
+        let resumed = approve_emergency_action(pending.id).unwrap();
+        assert!(resumed.executed);
+        assert!(is_operation_allowed_for_pool("deposit".to_string(), pool));
+        assert!(!is_operation_allowed("liquidate".to_string()));
+    }
+
+    #[test]
+    fn test_role_assignment() {
+        use crate::test_utils::mock;
+        
+        let admin = mock::test_principal(1);
+        let operator = mock::test_principal(2);
+        mock::set_controllers(vec![admin]);
+        
+        // 非超级管理员不能授予角色
+        mock::set_caller(operator);
+        assert!(grant_role(operator, Role::Liquidator, "self grant".to_string()).is_err());
+        assert!(!has_permission(operator, Permission::Liquidate));
+        
+        // 控制者默认是超级管理员，可以授予角色
+        mock::set_caller(admin);
+        assert!(grant_role(operator, Role::Liquidator, "Appointed".to_string()).is_ok());
+        assert!(has_permission(operator, Permission::Liquidate));
+        assert!(get_effective_permissions(operator).contains(&Permission::Deposit));
+        
+        // 撤销后权限消失
+        assert!(revoke_role(operator, Role::Liquidator, "Rotated".to_string()).is_ok());
+        assert!(!has_permission(operator, Permission::Liquidate));
+        assert!(revoke_role(operator, Role::Liquidator, "Rotated".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_blocklist_enforcement() {
+        use crate::blocklist::*;
+        use crate::test_utils::mock;
+        
+        let admin = mock::test_principal(1);
+        let user = mock::test_principal(9);
+        let sanctioned = mock::test_principal(10);
+        let address = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        mock::set_controllers(vec![admin]);
+        
+        // 普通用户不能管理黑名单
+        mock::set_caller(user);
+        assert!(add_to_blocklist(BlockedEntity::Principal(sanctioned), "OFAC".to_string()).is_err());
+        assert!(get_blocklist().is_err());
+        
+        // 批量导入，支持换行、逗号和注释
+        mock::set_caller(admin);
+        let data = format!("# sanctions list\n{}\n{}, not-an-entry\n{}", sanctioned, address, sanctioned);
+        let result = import_blocklist(data, "OFAC".to_string()).unwrap();
+        assert_eq!(result.added, 2);
+        assert_eq!(result.skipped, 1);
+        assert_eq!(result.invalid, vec!["not-an-entry".to_string()]);
+        assert!(is_blocked(sanctioned.to_text()));
+        assert!(is_blocked(address.to_string()));
+        assert_eq!(get_blocklist().unwrap().len(), 2);
+        
+        // 被封禁的 principal 和地址在执行路径上被拒绝
+        assert!(ensure_not_blocked(sanctioned, None, "execute_deposit").is_err());
+        assert!(ensure_not_blocked(user, Some(address), "authenticate").is_err());
+        assert!(ensure_not_blocked(user, None, "execute_deposit").is_ok());
+        assert!(crate::input_validation::validate_bitcoin_address(address, "test").is_err());
+        
+        // 关联了被封禁地址的 principal 同样被拒绝
+        crate::auth::link_verified_address(user, address).unwrap();
+        assert!(ensure_not_blocked(user, None, "execute_repay").is_err());
+        
+        // 认证入口同样检查黑名单
+        mock::set_caller(sanctioned);
+        assert!(crate::auth::authenticate(address.to_string(), "c2ln".to_string(), "msg".to_string()).is_err());
+        
+        // 被封禁的 principal 不能关联新的地址
+        let other_address = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+        assert!(matches!(
+            crate::auth::link_address(other_address.to_string(), "c2ln".to_string(), "msg".to_string()),
+            Err(crate::Error::PermissionDenied(msg)) if msg.contains("link_address")
+        ));
+        
+        // REE 交易按发起地址解析出的所有者检查: 未封禁的关联地址不能绕过 principal 封禁
+        crate::auth::link_verified_address(sanctioned, other_address).unwrap();
+        assert!(crate::exchange::ensure_owner_not_blocked(other_address, "execute_tx").is_err());
+        assert!(crate::exchange::ensure_owner_not_blocked("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", "execute_tx").is_ok());
+        
+        // 解封后恢复
+        mock::set_caller(admin);
+        assert!(remove_from_blocklist(BlockedEntity::Address(address.to_string()), "Delisted".to_string()).is_ok());
+        assert!(ensure_not_blocked(user, None, "execute_repay").is_ok());
+        assert!(remove_from_blocklist(BlockedEntity::Address(address.to_string()), "Delisted".to_string()).is_err());
+    }
+
     #[test]
     fn test_state_snapshot_creation() {
         // 测试状态快照创建
@@ -245,6 +443,91 @@ mod tests {
         assert_eq!(metric.data_points.len(), 2);
     }
 
+    #[test]
+    fn test_prometheus_metrics_endpoint() {
+        use crate::http::*;
+        use crate::monitoring::*;
+        use crate::performance::PerformanceManager;
+        
+        MonitoringManager::initialize_default_metrics();
+        MonitoringManager::increment_counter("transaction_count", None);
+        MonitoringManager::increment_counter("transaction_count", None);
+        MonitoringManager::record_metric("btc_price", 3_000_000.0, None);
+        PerformanceManager::start_measurement("execute_deposit").finish(false, Some("test".to_string()));
+        
+        let request = |method: &str, url: &str| HttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: vec![],
+            body: vec![],
+        };
+        
+        let response = http_request(request("GET", "/metrics?format=prometheus"));
+        assert_eq!(response.status_code, 200);
+        assert!(response.headers.iter().any(|(k, v)| k == "Content-Type" && v.starts_with("text/plain; version=0.0.4")));
+        
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("# TYPE bollar_transaction_count_total counter\nbollar_transaction_count_total 2\n"));
+        assert!(body.contains("bollar_btc_price 3000000\n"));
+        assert!(body.contains("# TYPE bollar_protocol_tvl_sats gauge"));
+        assert!(body.contains("bollar_protocol_liquidatable_positions "));
+        assert!(body.contains("bollar_oracle_price_age_seconds "));
+        assert!(body.contains("bollar_endpoint_latency_seconds_bucket{endpoint=\"execute_deposit\",le=\"+Inf\"} 1\n"));
+        assert!(body.contains("bollar_endpoint_errors_total{endpoint=\"execute_deposit\"} 1\n"));
+        
+        // 没有数据点的指标不输出样本
+        assert!(!body.contains("bollar_error_rate "));
+        
+        assert_eq!(http_request(request("POST", "/metrics")).status_code, 405);
+        assert_eq!(http_request(request("GET", "/")).status_code, 404);
+    }
+
+    #[test]
+    fn test_prometheus_counters_are_cumulative() {
+        use crate::http::*;
+        use crate::monitoring::*;
+        use crate::performance::*;
+        use crate::test_utils::mock;
+        
+        let admin = mock::test_principal(1);
+        mock::set_controllers(vec![admin]);
+        mock::set_caller(admin);
+        update_performance_config(PerformanceConfig {
+            max_performance_records: 2,
+            ..PerformanceConfig::default()
+        }).unwrap();
+        
+        MonitoringManager::initialize_default_metrics();
+        let labels = |status: &str| Some(std::collections::HashMap::from([("status".to_string(), status.to_string())]));
+        MonitoringManager::increment_counter("transaction_count", labels("ok"));
+        MonitoringManager::increment_counter("transaction_count", labels("ok"));
+        MonitoringManager::increment_counter("transaction_count", labels("failed"));
+        MonitoringManager::record_metric("api_response_time", 10.0, None);
+        MonitoringManager::record_metric("api_response_time", 20.0, None);
+        
+        // 超过保留条数的旧记录被丢弃后，累计值不应减少
+        for _ in 0..5 {
+            PerformanceManager::start_measurement("execute_deposit").finish(false, Some("test".to_string()));
+        }
+        assert_eq!(PerformanceManager::get_performance_metrics().total_requests, 2);
+        
+        let body = String::from_utf8(http_request(HttpRequest {
+            method: "GET".to_string(),
+            url: "/metrics?format=prometheus".to_string(),
+            headers: vec![],
+            body: vec![],
+        }).body).unwrap();
+        assert!(body.contains("bollar_endpoint_latency_seconds_bucket{endpoint=\"execute_deposit\",le=\"+Inf\"} 5\n"));
+        assert!(body.contains("bollar_endpoint_latency_seconds_count{endpoint=\"execute_deposit\"} 5\n"));
+        assert!(body.contains("bollar_endpoint_errors_total{endpoint=\"execute_deposit\"} 5\n"));
+        
+        // 不同标签组合各自累加
+        assert!(body.contains("bollar_transaction_count_total{status=\"failed\"} 1\n"));
+        assert!(body.contains("bollar_transaction_count_total{status=\"ok\"} 2\n"));
+        assert!(body.contains("bollar_api_response_time_sum 30\n"));
+        assert!(body.contains("bollar_api_response_time_count 2\n"));
+    }
+
     #[test]
     fn test_backup_metadata() {
         use crate::backup_recovery::*;
@@ -266,6 +549,399 @@ mod tests {
         assert_eq!(metadata.size_bytes, 1024);
     }
 
+    #[tokio::test]
+    async fn test_backup_chunked_export_import() {
+        use crate::backup_recovery::*;
+        use crate::test_utils::mock;
+        
+        mock::init_test_pool("pool_export".to_string());
+        let admin = mock::test_principal(1);
+        
+        let backup_id = create_full_backup("off-canister copy".to_string()).unwrap();
+        let export = prepare_backup_export(backup_id.clone(), None).await.unwrap();
+        assert_eq!(export.backup_id, backup_id);
+        assert!(!export.encrypted);
+        assert_eq!(export.source_canister, mock::canister_id());
+        
+        let mut bytes = Vec::new();
+        for index in 0..export.total_chunks {
+            bytes.extend(get_backup_export_chunk(backup_id.clone(), index).unwrap());
+        }
+        assert_eq!(bytes.len() as u64, export.total_bytes);
+        assert!(get_backup_export_chunk(backup_id.clone(), export.total_chunks).is_err());
+        
+        // 非管理员不能导出
+        mock::set_caller(mock::test_principal(9));
+        assert!(get_backup_export_chunk(backup_id.clone(), 0).is_err());
+        mock::set_caller(admin);
+        
+        // 以不同大小的分块乱序上传
+        let split = [bytes.len() / 3, 2 * bytes.len() / 3];
+        let parts = [&bytes[..split[0]], &bytes[split[0]..split[1]], &bytes[split[1]..]];
+        let manifest = BackupImportManifest {
+            total_bytes: export.total_bytes,
+            total_chunks: 3,
+            checksum: export.checksum.clone(),
+        };
+        
+        // 被篡改的分块无法通过校验
+        let corrupted = begin_backup_import(manifest.clone()).unwrap();
+        let mut tampered = parts[1].to_vec();
+        tampered[0] ^= 0xff;
+        upload_backup_chunk(corrupted.import_id.clone(), 0, parts[0].to_vec()).unwrap();
+        upload_backup_chunk(corrupted.import_id.clone(), 1, tampered).unwrap();
+        upload_backup_chunk(corrupted.import_id.clone(), 2, parts[2].to_vec()).unwrap();
+        assert!(finalize_backup_import(corrupted.import_id.clone(), None).is_err());
+        cancel_backup_import(corrupted.import_id).unwrap();
+        
+        let import = begin_backup_import(manifest).unwrap();
+        for index in [2u32, 0] {
+            upload_backup_chunk(import.import_id.clone(), index, parts[index as usize].to_vec()).unwrap();
+        }
+        assert!(matches!(finalize_backup_import(import.import_id.clone(), None), Err(crate::Error::InvalidState(_))));
+        let status = upload_backup_chunk(import.import_id.clone(), 1, parts[1].to_vec()).unwrap();
+        assert_eq!(status.received_chunks, 3);
+        assert_eq!(status.received_bytes, export.total_bytes);
+        
+        let imported = finalize_backup_import(import.import_id.clone(), None).unwrap();
+        assert_eq!(imported.metadata.id, backup_id);
+        assert_eq!(imported.source_canister, export.source_canister);
+        assert!(imported.warnings.iter().any(|w| w.contains("already exists")));
+        assert!(cancel_backup_import(import.import_id).is_err());
+        
+        let result = restore_from_backup(RecoveryOptions {
+            backup_id,
+            restore_pools: true,
+            restore_positions: true,
+            restore_config: false,
+            verify_integrity: true,
+            create_recovery_point: false,
+            target_time: None,
+        }).unwrap();
+        assert!(result.success, "{:?}", result.errors);
+        assert_eq!(result.restored_pools, 1);
+    }
+
+    #[tokio::test]
+    async fn test_backup_checksum_and_encryption() {
+        use crate::backup_recovery::*;
+        use crate::test_utils::mock;
+        use crate::types::Position;
+        
+        mock::init_test_pool("pool_crypto".to_string());
+        let owner = mock::test_principal(1).to_string();
+        for i in 0..5 {
+            crate::save_position(Position::new(
+                format!("pool_crypto:{}", i), "pool_crypto".to_string(), owner.clone(), 100_000_000, 1_000_000 + i, 3_000_000,
+            ));
+        }
+        
+        // 相同数据的校验和与 HashMap 迭代顺序无关
+        let first = create_full_backup("first".to_string()).unwrap();
+        mock::set_time(mock::time() + 1);
+        let second = create_full_backup("second".to_string()).unwrap();
+        let checksums: Vec<String> = get_backups().into_iter()
+            .filter(|b| b.id == first || b.id == second)
+            .map(|b| b.checksum)
+            .collect();
+        assert_eq!(checksums.len(), 2);
+        assert_eq!(checksums[0], checksums[1]);
+        
+        // 明文归档中的数据被篡改后无法导入
+        let plain = BackupManager::export_archive(&first, None).unwrap();
+        let ArchiveEnvelope::Plain(mut archive) = ciborium::de::from_reader(plain.as_slice()).unwrap() else {
+            panic!("expected plain archive");
+        };
+        archive.backup.positions.get_mut("pool_crypto:0").unwrap().bollar_debt = 1;
+        let mut tampered = vec![];
+        ciborium::ser::into_writer(&ArchiveEnvelope::Plain(archive), &mut tampered).unwrap();
+        assert!(BackupManager::import_archive(&tampered, None).is_err());
+        assert!(BackupManager::import_archive(&plain, None).is_ok());
+        
+        // 加密导出
+        let key = vec![7u8; 32];
+        assert!(prepare_backup_export(first.clone(), Some(vec![7u8; 16])).await.is_err());
+        let export = prepare_backup_export(first.clone(), Some(key.clone())).await.unwrap();
+        assert!(export.encrypted);
+        let encrypted: Vec<u8> = (0..export.total_chunks)
+            .flat_map(|index| get_backup_export_chunk(first.clone(), index).unwrap())
+            .collect();
+        
+        assert!(BackupManager::import_archive(&encrypted, None).is_err());
+        assert!(BackupManager::import_archive(&encrypted, Some(&[8u8; 32])).is_err());
+        
+        let ArchiveEnvelope::Encrypted { nonce, mut ciphertext } = ciborium::de::from_reader(encrypted.as_slice()).unwrap() else {
+            panic!("expected encrypted archive");
+        };
+        let last = ciphertext.len() - 1;
+        ciphertext[last] ^= 0x01;
+        let mut tampered = vec![];
+        ciborium::ser::into_writer(&ArchiveEnvelope::Encrypted { nonce, ciphertext }, &mut tampered).unwrap();
+        assert!(BackupManager::import_archive(&tampered, Some(&key)).is_err());
+        
+        let imported = BackupManager::import_archive(&encrypted, Some(&key)).unwrap();
+        assert_eq!(imported.metadata.checksum, checksums[0]);
+        
+        // 要求加密后不能导出明文
+        set_backup_encryption_required(true).unwrap();
+        assert!(prepare_backup_export(first, None).await.is_err());
+    }
+
+    #[test]
+    fn test_incremental_backup_point_in_time_restore() {
+        use crate::backup_recovery::*;
+        use crate::test_utils::mock;
+        use crate::types::Position;
+        
+        const MINUTE: u64 = 60 * 1_000_000_000;
+        
+        mock::init_test_pool("pool_pitr".to_string());
+        let owner = mock::test_principal(1).to_string();
+        let t0 = mock::time();
+        
+        let full = create_full_backup("base".to_string()).unwrap();
+        assert!(create_incremental_backup("missing".to_string(), "orphan".to_string()).is_err());
+        
+        mock::set_time(t0 + MINUTE);
+        let mut first = Position::new("pool_pitr:0".to_string(), "pool_pitr".to_string(), owner.clone(), 100_000_000, 2_000_000, 3_000_000);
+        crate::save_position(first.clone());
+        let inc1 = create_incremental_backup(full.clone(), "first".to_string()).unwrap();
+        
+        // 第二个增量以第一个增量为基础，只包含之后的变更
+        mock::set_time(t0 + 2 * MINUTE);
+        first.update(100_000_000, 1_500_000, 3_000_000);
+        crate::save_position(first.clone());
+        let second = Position::new("pool_pitr:1".to_string(), "pool_pitr".to_string(), owner, 100_000_000, 1_000_000, 3_000_000);
+        crate::save_position(second.clone());
+        let inc2 = create_incremental_backup(inc1, "second".to_string()).unwrap();
+        
+        mock::set_time(t0 + 3 * MINUTE);
+        crate::delete_position(&first.id);
+        
+        let options = |target_time: Option<u64>| RecoveryOptions {
+            backup_id: inc2.clone(),
+            restore_pools: false,
+            restore_positions: true,
+            restore_config: false,
+            verify_integrity: true,
+            create_recovery_point: false,
+            target_time,
+        };
+        
+        // 恢复到第一个头寸刚创建的时间点
+        let result = restore_from_backup(options(Some(t0 + MINUTE))).unwrap();
+        assert!(result.success, "{:?}", result.errors);
+        assert_eq!(result.replayed_changes, 1);
+        assert_eq!(crate::get_position(&first.id).unwrap().bollar_debt, 2_000_000);
+        assert!(crate::get_position(&second.id).is_none());
+        
+        // 回放完整的备份链
+        let result = restore_from_backup(options(None)).unwrap();
+        assert!(result.success, "{:?}", result.errors);
+        assert_eq!(result.replayed_changes, 3);
+        assert_eq!(crate::get_position(&first.id).unwrap().bollar_debt, 1_500_000);
+        assert_eq!(crate::get_position(&second.id), Some(second));
+        
+        // 目标时间点不能早于完整备份
+        let result = restore_from_backup(options(Some(t0 - 1))).unwrap();
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn test_preview_restore_diff() {
+        use crate::backup_recovery::*;
+        use crate::test_utils::mock;
+        use crate::types::{PoolState, Position};
+        
+        const MINUTE: u64 = 60 * 1_000_000_000;
+        
+        mock::init_test_pool("pool_preview".to_string());
+        let owner = mock::test_principal(1).to_string();
+        let t0 = mock::time();
+        let position = |id: &str, debt: u64| Position::new(
+            id.to_string(), "pool_preview".to_string(), owner.clone(), 100_000_000, debt, 3_000_000,
+        );
+        
+        let mut kept = position("pool_preview:0", 2_000_000);
+        crate::save_position(kept.clone());
+        let closed = position("pool_preview:1", 1_200_000);
+        crate::save_position(closed.clone());
+        let full = create_full_backup("before".to_string()).unwrap();
+        
+        // 备份之后: 修改、新建和删除头寸，并提交新的池状态
+        mock::set_time(t0 + MINUTE);
+        kept.update(100_000_000, 1_500_000, 3_000_000);
+        crate::save_position(kept.clone());
+        let opened = position("pool_preview:2", 1_000_000);
+        crate::save_position(opened.clone());
+        crate::delete_position(&closed.id);
+        let mut pool = crate::get_pool(&"pool_preview".to_string()).unwrap();
+        pool.commit(PoolState { id: None, nonce: 1, utxo: None, btc_price: 3_100_000 });
+        crate::save_pool(pool);
+        let incremental = create_incremental_backup(full, "after".to_string()).unwrap();
+        
+        let options = RecoveryOptions {
+            backup_id: incremental,
+            restore_pools: true,
+            restore_positions: true,
+            restore_config: false,
+            verify_integrity: true,
+            create_recovery_point: false,
+            target_time: Some(t0),
+        };
+        let preview = preview_restore(options.clone()).unwrap();
+        
+        assert_eq!(preview.replayed_changes, 0);
+        assert_eq!(preview.positions_added, vec![closed.clone()]);
+        assert_eq!(preview.positions_removed, vec![opened.clone()]);
+        assert_eq!(preview.positions_changed.len(), 1);
+        assert_eq!(preview.positions_changed[0].current.bollar_debt, 1_500_000);
+        assert_eq!(preview.positions_changed[0].restored.bollar_debt, 2_000_000);
+        
+        assert_eq!(preview.current_bollar_supply, 2_500_000);
+        assert_eq!(preview.restored_bollar_supply, 3_200_000);
+        assert_eq!(preview.bollar_supply_delta, 700_000);
+        assert_eq!(preview.btc_locked_delta, 0);
+        
+        assert_eq!(preview.pools_changed.len(), 1);
+        let pool_diff = &preview.pools_changed[0];
+        assert_eq!(pool_diff.current_nonce, Some(1));
+        assert_eq!(pool_diff.restored_nonce, Some(0));
+        assert!(pool_diff.current_utxo.is_none() && pool_diff.restored_utxo.is_some());
+        
+        // 预览不修改任何数据
+        assert_eq!(crate::get_position(&kept.id).unwrap().bollar_debt, 1_500_000);
+        assert!(crate::get_position(&opened.id).is_some());
+        assert!(crate::get_position(&closed.id).is_none());
+        assert_eq!(crate::get_pool(&"pool_preview".to_string()).unwrap().states.last().unwrap().nonce, 1);
+        
+        // 预览与实际恢复的结果一致
+        let result = restore_from_backup(options.clone()).unwrap();
+        assert!(result.success, "{:?}", result.errors);
+        
+        // 恢复记录事件，按被删除的头寸可以查到
+        let events = crate::events::get_events(0, None, Some(crate::events::EventFilter {
+            position_id: Some(opened.id.clone()),
+            ..Default::default()
+        })).events;
+        match events.last().map(|e| &e.event) {
+            Some(crate::events::ProtocolEvent::StateRestored { target_time, restored_pools, restored_positions, removed_positions, .. }) => {
+                assert_eq!(*target_time, Some(t0));
+                assert_eq!(restored_pools, &vec!["pool_preview".to_string()]);
+                assert_eq!(restored_positions, &vec![kept.id.clone(), closed.id.clone()]);
+                assert_eq!(removed_positions, &vec![opened.id.clone()]);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        
+        let after = preview_restore(options.clone()).unwrap();
+        assert!(after.positions_added.is_empty() && after.positions_removed.is_empty() && after.positions_changed.is_empty());
+        assert!(after.pools_changed.is_empty());
+        
+        assert!(preview_restore(RecoveryOptions { backup_id: "missing".to_string(), ..options }).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restore_removes_later_state_and_exports_chain() {
+        use crate::backup_recovery::*;
+        use crate::test_utils::mock;
+        use crate::types::Position;
+        
+        const MINUTE: u64 = 60 * 1_000_000_000;
+        
+        mock::init_test_pool("pool_chain".to_string());
+        let owner = mock::test_principal(1).to_string();
+        let t0 = mock::time();
+        let position = |id: &str| Position::new(
+            id.to_string(), "pool_chain".to_string(), owner.clone(), 100_000_000, 1_000_000, 3_000_000,
+        );
+        let options = |backup_id: &String| RecoveryOptions {
+            backup_id: backup_id.clone(),
+            restore_pools: true,
+            restore_positions: true,
+            restore_config: false,
+            verify_integrity: true,
+            create_recovery_point: false,
+            target_time: None,
+        };
+        
+        let first = position("pool_chain:0");
+        crate::save_position(first.clone());
+        let full = create_full_backup("base".to_string()).unwrap();
+        
+        mock::set_time(t0 + MINUTE);
+        let second = position("pool_chain:1");
+        crate::save_position(second.clone());
+        let incremental = create_incremental_backup(full.clone(), "tip".to_string()).unwrap();
+        
+        // 备份链末端之后创建的头寸和资金池
+        mock::set_time(t0 + 2 * MINUTE);
+        let third = position("pool_chain:2");
+        crate::save_position(third.clone());
+        crate::save_pool(mock::create_test_pool("pool_chain_late".to_string()));
+        
+        // 恢复普通完整备份会删除之后创建的头寸和资金池
+        let preview = preview_restore(options(&full)).unwrap();
+        let mut removed: Vec<String> = preview.positions_removed.iter().map(|p| p.id.clone()).collect();
+        removed.sort();
+        assert_eq!(removed, vec![second.id.clone(), third.id.clone()]);
+        assert_eq!(preview.pools_removed, vec!["pool_chain_late".to_string()]);
+        
+        let result = restore_from_backup(options(&full)).unwrap();
+        assert!(result.success, "{:?}", result.errors);
+        assert!(crate::get_position(&first.id).is_some());
+        assert!(crate::get_position(&second.id).is_none());
+        assert!(crate::get_position(&third.id).is_none());
+        assert!(crate::get_pool(&"pool_chain_late".to_string()).is_none());
+        
+        // 恢复备份链末端: 末端之后创建的头寸不会保留
+        crate::save_position(third.clone());
+        let result = restore_from_backup(options(&incremental)).unwrap();
+        assert!(result.success, "{:?}", result.errors);
+        assert!(crate::get_position(&second.id).is_some());
+        assert!(crate::get_position(&third.id).is_none());
+        
+        // 导出增量备份时归档包含整条备份链
+        let export = prepare_backup_export(incremental.clone(), None).await.unwrap();
+        assert_eq!(export.backup_id, incremental);
+        let bytes = BackupManager::export_archive(&incremental, None).unwrap();
+        let mut archive = match ciborium::de::from_reader::<ArchiveEnvelope, _>(bytes.as_slice()).unwrap() {
+            ArchiveEnvelope::Plain(archive) => archive,
+            ArchiveEnvelope::Encrypted { .. } => panic!("archive should not be encrypted"),
+        };
+        assert_eq!(archive.backup.metadata.id, full);
+        assert_eq!(archive.incrementals.len(), 1);
+        assert_eq!(archive.incrementals[0].metadata.id, incremental);
+        
+        let encode = |archive: &BackupArchive| {
+            let mut bytes = Vec::new();
+            ciborium::ser::into_writer(&ArchiveEnvelope::Plain(archive.clone()), &mut bytes).unwrap();
+            bytes
+        };
+        
+        // 重新导入已有的备份链只产生警告
+        let imported = BackupManager::import_archive(&bytes, None).unwrap();
+        assert_eq!(imported.metadata.id, incremental);
+        assert_eq!(imported.warnings.iter().filter(|w| w.contains("already exists")).count(), 2);
+        
+        // 断开的备份链和被篡改的增量备份都不能导入
+        let mut broken = archive.clone();
+        broken.incrementals[0].base_backup_id = "missing".to_string();
+        assert!(BackupManager::import_archive(&encode(&broken), None).is_err());
+        let mut tampered = archive.clone();
+        tampered.incrementals[0].changes.clear();
+        assert!(BackupManager::import_archive(&encode(&tampered), None).is_err());
+        
+        // 导入新的增量备份后可以直接从其恢复
+        archive.incrementals[0].metadata.id = "imported_tip".to_string();
+        let imported = BackupManager::import_archive(&encode(&archive), None).unwrap();
+        assert_eq!(imported.metadata.id, "imported_tip");
+        crate::delete_position(&second.id);
+        let result = restore_from_backup(options(&"imported_tip".to_string())).unwrap();
+        assert!(result.success, "{:?}", result.errors);
+        assert!(crate::get_position(&second.id).is_some());
+    }
+
     #[test]
     fn test_alert_conditions() {
         use crate::monitoring::*;
@@ -286,6 +962,361 @@ mod tests {
         assert_eq!(rule.severity, AlertSeverity::Warning);
     }
 
+    #[test]
+    fn test_alert_rule_crud_and_evaluation() {
+        use crate::monitoring::*;
+        use crate::test_utils::mock;
+        
+        const SECOND: u64 = 1_000_000_000;
+        let admin = mock::test_principal(1);
+        mock::set_controllers(vec![admin]);
+        mock::set_time(1_000 * SECOND);
+        MonitoringManager::initialize_default_metrics();
+        
+        let rule = |id: &str, metric: &str, condition: AlertCondition, threshold: f64, duration_seconds: u64| AlertRule {
+            id: id.to_string(),
+            metric_name: metric.to_string(),
+            condition,
+            threshold,
+            duration_seconds,
+            severity: AlertSeverity::Warning,
+            message: format!("{} fired", id),
+            enabled: true,
+        };
+        let is_active = |id: &str| get_active_alerts().iter().any(|a| a.rule_id == id);
+        
+        // 默认规则使用百分比变化而不是绝对价格
+        assert_eq!(
+            get_alert_rule("extreme_btc_price_change".to_string()).unwrap().condition,
+            AlertCondition::PercentChangeGreaterThan { window_seconds: 300 }
+        );
+        
+        // 再次初始化 (升级后) 不覆盖运维人员修改或删除的规则
+        mock::set_caller(admin);
+        let mut tuned = get_alert_rule("high_error_rate".to_string()).unwrap();
+        tuned.threshold = 8.0;
+        update_alert_rule(tuned).unwrap();
+        delete_alert_rule("high_liquidation_rate".to_string()).unwrap();
+        MonitoringManager::initialize_default_metrics();
+        assert_eq!(get_alert_rule("high_error_rate".to_string()).unwrap().threshold, 8.0);
+        assert!(get_alert_rule("high_liquidation_rate".to_string()).is_none());
+        
+        // 普通用户不能管理规则
+        mock::set_caller(mock::test_principal(9));
+        assert!(create_alert_rule(rule("high_memory", "system_memory_usage", AlertCondition::GreaterThan, 100.0, 60)).is_err());
+        
+        mock::set_caller(admin);
+        create_alert_rule(rule("high_memory", "system_memory_usage", AlertCondition::GreaterThan, 100.0, 60)).unwrap();
+        assert!(create_alert_rule(rule("high_memory", "system_memory_usage", AlertCondition::GreaterThan, 100.0, 60)).is_err());
+        assert!(create_alert_rule(rule("bad_window", "btc_price", AlertCondition::MovingAverageLessThan { window_seconds: 0 }, 1.0, 0)).is_err());
+        assert!(create_alert_rule(rule("huge_window", "btc_price", AlertCondition::MovingAverageLessThan { window_seconds: u64::MAX }, 1.0, 0)).is_err());
+        assert!(create_alert_rule(rule("huge_duration", "btc_price", AlertCondition::GreaterThan, 1.0, u64::MAX)).is_err());
+        
+        // 条件需要持续满足 duration_seconds 才触发
+        MonitoringManager::record_metric("system_memory_usage", 200.0, None);
+        MonitoringManager::check_alert_rules();
+        assert!(!is_active("high_memory"));
+        
+        mock::set_time(1_030 * SECOND);
+        MonitoringManager::record_metric("system_memory_usage", 200.0, None);
+        MonitoringManager::check_alert_rules();
+        assert!(!is_active("high_memory"));
+        
+        mock::set_time(1_061 * SECOND);
+        MonitoringManager::check_alert_rules();
+        assert!(is_active("high_memory"));
+        
+        // 删除规则会解决其活跃告警
+        delete_alert_rule("high_memory".to_string()).unwrap();
+        assert!(!is_active("high_memory"));
+        assert!(get_alert_rule("high_memory".to_string()).is_none());
+        
+        // 百分比变化
+        create_alert_rule(rule("price_jump", "btc_price", AlertCondition::PercentChangeGreaterThan { window_seconds: 300 }, 10.0, 0)).unwrap();
+        MonitoringManager::record_metric("btc_price", 3_000_000.0, None);
+        mock::set_time(1_100 * SECOND);
+        MonitoringManager::record_metric("btc_price", 3_030_000.0, None);
+        MonitoringManager::check_alert_rules();
+        assert!(!is_active("price_jump"));
+        
+        mock::set_time(1_120 * SECOND);
+        MonitoringManager::record_metric("btc_price", 3_600_000.0, None);
+        MonitoringManager::check_alert_rules();
+        assert!(is_active("price_jump"));
+        
+        // 缺少数据
+        create_alert_rule(rule("no_tx", "transaction_count", AlertCondition::Absent, 0.0, 600)).unwrap();
+        MonitoringManager::check_alert_rules();
+        assert!(is_active("no_tx"));
+        
+        MonitoringManager::increment_counter("transaction_count", None);
+        MonitoringManager::check_alert_rules();
+        assert!(!is_active("no_tx"));
+    }
+
+    #[test]
+    fn test_alert_subscriber_delivery() {
+        use crate::alert_notifier::*;
+        use crate::monitoring::*;
+        use crate::test_utils::mock;
+        
+        let admin = mock::test_principal(1);
+        let ops_bot = mock::test_principal(20);
+        let flaky = mock::test_principal(21);
+        mock::set_controllers(vec![admin]);
+        let mut now = 2_000_000_000_000_000_000u64;
+        mock::set_time(now);
+        
+        let alert = |id: &str, severity: AlertSeverity| AlertEvent {
+            id: id.to_string(),
+            rule_id: id.to_string(),
+            metric_name: "btc_price".to_string(),
+            current_value: 1.0,
+            threshold: 0.0,
+            severity,
+            message: "test".to_string(),
+            triggered_at: now,
+            resolved_at: None,
+            acknowledged: false,
+        };
+        let sent_to = |canister| mock::sent_notifications().into_iter()
+            .filter(|(target, _, _)| *target == canister)
+            .collect::<Vec<_>>();
+        
+        // 只有管理员可以注册订阅者
+        mock::set_caller(mock::test_principal(9));
+        assert!(subscribe_alerts(ops_bot, "on_alert".to_string(), AlertSeverity::Critical).is_err());
+        
+        mock::set_caller(admin);
+        assert!(subscribe_alerts(ops_bot, "bad method".to_string(), AlertSeverity::Critical).is_err());
+        subscribe_alerts(ops_bot, "on_alert".to_string(), AlertSeverity::Critical).unwrap();
+        subscribe_alerts(flaky, "handle_alert".to_string(), AlertSeverity::Warning).unwrap();
+        assert_eq!(get_alert_subscriptions().unwrap().len(), 2);
+        mock::set_failing_canisters(vec![flaky]);
+        
+        // 低于最低级别的告警不推送
+        enqueue_alert(&alert("warn", AlertSeverity::Warning));
+        assert!(sent_to(ops_bot).is_empty());
+        
+        enqueue_alert(&alert("page", AlertSeverity::Critical));
+        let sent = sent_to(ops_bot);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].1, "on_alert");
+        let event: AlertEvent = candid::decode_one(&sent[0].2).unwrap();
+        assert_eq!(event.id, "page");
+        
+        // 入队失败的投递进入重试队列，退避时间未到时不重试
+        assert!(get_alert_dead_letters(None).unwrap().is_empty());
+        let pending = get_pending_alert_retries(None).unwrap();
+        assert_eq!(pending.len(), 2);
+        assert!(pending.iter().all(|d| d.subscriber == flaky && d.attempts == 1 && d.failed_at == now));
+        assert_eq!(process_alert_retries(), DeadLetterRetryResult::default());
+        
+        // 退避时间按失败次数翻倍: 30 秒后第二次投递，再 60 秒后第三次
+        now += 30 * 1_000_000_000;
+        mock::set_time(now);
+        assert_eq!(process_alert_retries(), DeadLetterRetryResult { delivered: 0, failed: 2, dropped: 0 });
+        assert!(get_pending_alert_retries(None).unwrap().iter().all(|d| d.attempts == 2 && d.failed_at == now));
+        now += 30 * 1_000_000_000;
+        mock::set_time(now);
+        assert_eq!(process_alert_retries(), DeadLetterRetryResult::default());
+        now += 30 * 1_000_000_000;
+        mock::set_time(now);
+        assert_eq!(process_alert_retries().failed, 2);
+        
+        // 达到最大投递次数后进入死信列表
+        for _ in 0..2 {
+            now += 60 * 60 * 1_000_000_000;
+            mock::set_time(now);
+            assert_eq!(process_alert_retries().failed, 2);
+        }
+        assert!(get_pending_alert_retries(None).unwrap().is_empty());
+        let dead = get_alert_dead_letters(None).unwrap();
+        assert_eq!(dead.len(), 2);
+        assert!(dead.iter().all(|d| d.subscriber == flaky && d.attempts == 5));
+        assert!(sent_to(flaky).is_empty());
+        
+        // 手动重投仍然失败时留在死信列表并累计次数
+        assert_eq!(retry_dead_letters().unwrap(), DeadLetterRetryResult { delivered: 0, failed: 2, dropped: 0 });
+        let dead = get_alert_dead_letters(None).unwrap();
+        assert_eq!(dead.len(), 2);
+        assert!(dead.iter().all(|d| d.attempts == 6 && d.failed_at == now));
+        
+        // 恢复后重新投递死信
+        mock::set_failing_canisters(vec![]);
+        assert_eq!(retry_dead_letters().unwrap(), DeadLetterRetryResult { delivered: 2, failed: 0, dropped: 0 });
+        assert_eq!(sent_to(flaky).len(), 2);
+        assert!(get_alert_dead_letters(None).unwrap().is_empty());
+        
+        // 暂时失败的投递由定时重试送达
+        mock::set_failing_canisters(vec![flaky]);
+        enqueue_alert(&alert("blip", AlertSeverity::Warning));
+        mock::set_failing_canisters(vec![]);
+        now += 30 * 1_000_000_000;
+        mock::set_time(now);
+        assert_eq!(process_alert_retries(), DeadLetterRetryResult { delivered: 1, failed: 0, dropped: 0 });
+        assert_eq!(sent_to(flaky).len(), 3);
+        assert!(get_pending_alert_retries(None).unwrap().is_empty());
+        
+        // 订阅者可以自行取消订阅
+        mock::set_failing_canisters(vec![flaky]);
+        enqueue_alert(&alert("late", AlertSeverity::Warning));
+        mock::set_caller(flaky);
+        unsubscribe_alerts(flaky).unwrap();
+        mock::set_caller(admin);
+        assert_eq!(get_alert_subscriptions().unwrap().len(), 1);
+        
+        // 订阅已取消的投递被丢弃并计入结果
+        now += 30 * 1_000_000_000;
+        mock::set_time(now);
+        assert_eq!(process_alert_retries(), DeadLetterRetryResult { delivered: 0, failed: 0, dropped: 1 });
+        assert!(get_pending_alert_retries(None).unwrap().is_empty());
+        assert!(get_alert_dead_letters(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_hash_chained_audit_log() {
+        use crate::audit::*;
+        use crate::secure_logging::LogCategory;
+        use crate::test_utils::mock;
+        
+        let admin = mock::test_principal(1);
+        mock::set_controllers(vec![admin]);
+        
+        let start = audit_log_len();
+        let base_time = 3_000_000_000_000_000_000u64;
+        for i in 0..5u64 {
+            mock::set_time(base_time + i * 1_000_000_000);
+            let user = if i % 2 == 0 { "alice" } else { "bob" };
+            crate::error::audit_log("test_operation", user, &format!("step={}", i));
+        }
+        assert_eq!(audit_log_len(), start + 5);
+        
+        // 查询需要管理员权限
+        mock::set_caller(mock::test_principal(9));
+        assert!(get_audit_log(AuditLogQuery::default()).is_err());
+        mock::set_caller(admin);
+        
+        // 按 principal 过滤
+        let page = get_audit_log(AuditLogQuery {
+            from_seq: Some(start),
+            principal: Some("alice".to_string()),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(page.entries.len(), 3);
+        assert!(page.entries.iter().all(|e| e.category == LogCategory::Audit && e.operation == "test_operation"));
+        
+        // 按时间范围过滤并分页
+        let query = AuditLogQuery {
+            from_time: Some(base_time + 1_000_000_000),
+            to_time: Some(base_time + 3_000_000_000),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let first = get_audit_log(query.clone()).unwrap();
+        assert_eq!(first.entries.iter().map(|e| e.details.as_str()).collect::<Vec<_>>(), vec!["step=1", "step=2"]);
+        let second = get_audit_log(AuditLogQuery { from_seq: first.next_seq, ..query }).unwrap();
+        assert_eq!(second.entries.len(), 1);
+        assert_eq!(second.entries[0].details, "step=3");
+        assert!(second.next_seq.is_none());
+        
+        // 完整的哈希链可以通过校验
+        let verification = verify_audit_chain(None, None);
+        assert!(verification.valid);
+        assert_eq!(verification.verified_entries, verification.total_entries);
+        
+        // 篡改或删除记录都会被检测到
+        let entries = get_audit_log(AuditLogQuery { from_seq: Some(start), ..Default::default() }).unwrap().entries;
+        let prev_hash = entries[0].prev_hash.clone();
+        assert_eq!(verify_chain_segment(&prev_hash, start, &entries), None);
+        
+        let mut tampered = entries.clone();
+        tampered[2].details = "step=99".to_string();
+        assert_eq!(verify_chain_segment(&prev_hash, start, &tampered), Some(start + 2));
+        
+        let mut removed = entries.clone();
+        removed.remove(1);
+        assert_eq!(verify_chain_segment(&prev_hash, start, &removed), Some(start + 1));
+        
+        // 审计记录保留完整的地址和交易 ID，不被脱敏
+        let details = format!("entity=bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq, txid={}", "ab".repeat(32));
+        crate::error::audit_log("add_to_blocklist", "alice", &details);
+        let last = get_audit_log(AuditLogQuery { from_seq: Some(audit_log_len() - 1), ..Default::default() }).unwrap();
+        assert_eq!(last.entries[0].details, details);
+    }
+
+    #[test]
+    fn test_protocol_event_stream() {
+        use crate::emergency::{emergency_pause_operation, PauseOperation};
+        use crate::events::*;
+        use crate::test_utils::mock;
+        
+        let admin = mock::test_principal(1);
+        mock::set_controllers(vec![admin]);
+        mock::set_caller(admin);
+        
+        let start = event_count();
+        let opened = |id: &str, pool: &str| ProtocolEvent::PositionOpened {
+            position_id: id.to_string(),
+            owner: admin.to_string(),
+            pool_address: pool.to_string(),
+            btc_collateral: 100_000_000,
+            bollar_debt: 1_000_000,
+            btc_price: 3_000_000,
+        };
+        
+        assert_eq!(record_event(opened("pool_a:1", "pool_a")), start);
+        record_event(ProtocolEvent::Repaid {
+            position_id: "pool_a:1".to_string(),
+            owner: admin.to_string(),
+            pool_address: "pool_a".to_string(),
+            bollar_repaid: 1_000_000,
+            btc_returned: 100_000_000,
+            remaining_debt: 0,
+            closed: true,
+        });
+        record_event(opened("pool_b:1", "pool_b"));
+        
+        // 状态变更操作自动记录事件
+        emergency_pause_operation(PauseOperation::Deposit, Some("pool_b".to_string()), "incident".to_string()).unwrap();
+        assert_eq!(event_count(), start + 4);
+        
+        // 增量分页，序号连续递增
+        let first = get_events(start, Some(2), None);
+        assert_eq!(first.events.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![start, start + 1]);
+        assert_eq!(first.next_seq, start + 2);
+        assert_eq!(first.latest_seq, Some(start + 3));
+        
+        let second = get_events(first.next_seq, None, None);
+        assert_eq!(second.events.len(), 2);
+        assert_eq!(second.next_seq, start + 4);
+        assert!(matches!(
+            &second.events[1].event,
+            ProtocolEvent::OperationPaused { operation: PauseOperation::Deposit, pool_address: Some(pool), .. } if pool == "pool_b"
+        ));
+        assert!(get_events(second.next_seq, None, None).events.is_empty());
+        
+        // 按头寸、资金池和事件类型过滤
+        let by_position = get_events(start, None, Some(EventFilter {
+            position_id: Some("pool_a:1".to_string()),
+            ..Default::default()
+        }));
+        assert_eq!(by_position.events.len(), 2);
+        
+        let by_pool = get_events(start, None, Some(EventFilter {
+            pool_address: Some("pool_b".to_string()),
+            ..Default::default()
+        }));
+        assert_eq!(by_pool.events.len(), 2);
+        
+        let by_kind = get_events(start, None, Some(EventFilter {
+            kinds: Some(vec![EventKind::PositionOpened]),
+            ..Default::default()
+        }));
+        assert_eq!(by_kind.events.iter().map(|e| e.event.kind()).collect::<Vec<_>>(),
+                   vec![EventKind::PositionOpened, EventKind::PositionOpened]);
+    }
+
     #[test]
     fn test_performance_measurement() {
         use crate::performance::*;
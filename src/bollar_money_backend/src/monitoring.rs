@@ -4,9 +4,18 @@
 use crate::{Error, Result, types::*, secure_logging::*, ic_api};
 use candid::{CandidType, Deserialize};
 use ic_cdk_macros::{query, update};
+use ic_stable_structures::{
+    StableBTreeMap, Storable,
+    memory_manager::MemoryId,
+    storable::Bound,
+};
 use serde::Serialize;
+use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+// 告警规则持续时间和窗口长度的上限 (秒)
+const MAX_ALERT_WINDOW_SECONDS: u64 = 30 * 24 * 60 * 60; // 30天
 
 // 指标类型
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
@@ -107,8 +116,22 @@ pub struct AlertRule {
     pub enabled: bool,
 }
 
+impl Storable for AlertRule {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode AlertRule")
+    }
+}
+
 // 告警条件
-#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub enum AlertCondition {
     GreaterThan,
     LessThan,
@@ -116,6 +139,10 @@ pub enum AlertCondition {
     NotEqual,
     GreaterThanOrEqual,
     LessThanOrEqual,
+    PercentChangeGreaterThan { window_seconds: u64 },  // 窗口内变化百分比的绝对值超过阈值
+    MovingAverageGreaterThan { window_seconds: u64 },  // 窗口内平均值高于阈值
+    MovingAverageLessThan { window_seconds: u64 },     // 窗口内平均值低于阈值
+    Absent,                                            // duration_seconds 内没有数据点
 }
 
 // 告警严重程度
@@ -170,8 +197,18 @@ thread_local! {
     // 指标存储
     static METRICS: RefCell<HashMap<String, Metric>> = RefCell::new(HashMap::new());
     
-    // 告警规则
-    static ALERT_RULES: RefCell<HashMap<String, AlertRule>> = RefCell::new(HashMap::new());
+    // 计数器累计值 ((指标名, 排序后的标签) -> 累计值)，不随 data_points 窗口截断而减少
+    static COUNTER_TOTALS: RefCell<BTreeMap<(String, Vec<(String, String)>), f64>> = RefCell::new(BTreeMap::new());
+    
+    // Histogram/Timer 累计观测 (指标名 -> (累计和, 累计次数))
+    static SUMMARY_TOTALS: RefCell<BTreeMap<String, (f64, u64)>> = RefCell::new(BTreeMap::new());
+    
+    // 告警规则 (规则 ID -> AlertRule)，存放在稳定内存中以便跨升级保留
+    static ALERT_RULES: RefCell<StableBTreeMap<String, AlertRule, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(27))),
+        )
+    );
     
     // 活跃告警
     static ACTIVE_ALERTS: RefCell<HashMap<String, AlertEvent>> = RefCell::new(HashMap::new());
@@ -179,6 +216,9 @@ thread_local! {
     // 告警历史
     static ALERT_HISTORY: RefCell<VecDeque<AlertEvent>> = RefCell::new(VecDeque::new());
     
+    // 条件已满足但尚未持续满 duration_seconds 的规则 (规则 ID -> 开始满足的时间)
+    static PENDING_ALERTS: RefCell<HashMap<String, u64>> = RefCell::new(HashMap::new());
+    
     // 系统健康状态
     static SYSTEM_HEALTH: RefCell<SystemHealth> = RefCell::new(SystemHealth {
         overall_status: HealthStatus::Unknown,
@@ -223,7 +263,12 @@ impl MonitoringManager {
     }
     
     // 初始化默认告警规则
+    // 只在规则表为空时 (首次安装) 写入，升级后保留运维人员修改或删除的规则
     fn initialize_default_alert_rules() {
+        if !ALERT_RULES.with_borrow(|rules| rules.is_empty()) {
+            return;
+        }
+        
         let default_rules = vec![
             AlertRule {
                 id: "high_liquidation_rate".to_string(),
@@ -258,7 +303,7 @@ impl MonitoringManager {
             AlertRule {
                 id: "extreme_btc_price_change".to_string(),
                 metric_name: "btc_price".to_string(),
-                condition: AlertCondition::GreaterThan,
+                condition: AlertCondition::PercentChangeGreaterThan { window_seconds: 300 }, // 5分钟窗口
                 threshold: 10.0, // 10% 变化
                 duration_seconds: 60, // 1分钟
                 severity: AlertSeverity::Critical,
@@ -278,6 +323,13 @@ impl MonitoringManager {
     pub fn record_metric(name: &str, value: f64, labels: Option<HashMap<String, String>>) {
         METRICS.with_borrow_mut(|metrics| {
             if let Some(metric) = metrics.get_mut(name) {
+                if matches!(metric.metric_type, MetricType::Histogram | MetricType::Timer) {
+                    SUMMARY_TOTALS.with_borrow_mut(|totals| {
+                        let entry = totals.entry(name.to_string()).or_insert((0.0, 0));
+                        entry.0 += value;
+                        entry.1 += 1;
+                    });
+                }
                 metric.add_data_point(value, labels);
             }
         });
@@ -287,8 +339,14 @@ impl MonitoringManager {
     pub fn increment_counter(name: &str, labels: Option<HashMap<String, String>>) {
         METRICS.with_borrow_mut(|metrics| {
             if let Some(metric) = metrics.get_mut(name) {
-                let current_value = metric.latest_value().unwrap_or(0.0);
-                metric.add_data_point(current_value + 1.0, labels);
+                // 每个标签组合独立累加
+                let key = (name.to_string(), sorted_labels(labels.as_ref()));
+                let total = COUNTER_TOTALS.with_borrow_mut(|totals| {
+                    let total = totals.entry(key).or_insert(0.0);
+                    *total += 1.0;
+                    *total
+                });
+                metric.add_data_point(total, labels);
             }
         });
     }
@@ -297,42 +355,128 @@ impl MonitoringManager {
     pub fn check_alert_rules() {
         let current_time = ic_api::time();
         
-        ALERT_RULES.with_borrow(|rules| {
-            for rule in rules.values() {
-                if !rule.enabled {
-                    continue;
-                }
-                
-                Self::evaluate_alert_rule(rule, current_time);
-            }
+        let rules: Vec<AlertRule> = ALERT_RULES.with_borrow(|rules| {
+            rules.iter().map(|(_, rule)| rule).filter(|rule| rule.enabled).collect()
         });
+        
+        for rule in &rules {
+            Self::evaluate_alert_rule(rule, current_time);
+        }
     }
     
     // 评估告警规则
+    // 条件需要持续满足 duration_seconds 才会触发告警 (Absent 条件的窗口本身就是 duration_seconds)
     fn evaluate_alert_rule(rule: &AlertRule, current_time: u64) {
-        let metric_value = METRICS.with_borrow(|metrics| {
-            metrics.get(&rule.metric_name)
-                .and_then(|m| m.latest_value())
+        let evaluation = METRICS.with_borrow(|metrics| {
+            Self::evaluate_condition(rule, metrics.get(&rule.metric_name), current_time)
         });
         
-        let Some(value) = metric_value else {
+        // 数据不足时保持当前状态
+        let Some((condition_met, value)) = evaluation else {
             return;
         };
         
-        let condition_met = match rule.condition {
-            AlertCondition::GreaterThan => value > rule.threshold,
-            AlertCondition::LessThan => value < rule.threshold,
+        if !condition_met {
+            PENDING_ALERTS.with_borrow_mut(|pending| pending.remove(&rule.id));
+            Self::resolve_alert(&rule.id, current_time);
+            return;
+        }
+        
+        let since = PENDING_ALERTS.with_borrow_mut(|pending| {
+            *pending.entry(rule.id.clone()).or_insert(current_time)
+        });
+        
+        let held_for_ns = current_time.saturating_sub(since);
+        if rule.condition == AlertCondition::Absent || held_for_ns >= rule.duration_seconds.saturating_mul(1_000_000_000) {
+            Self::trigger_alert(rule, value, current_time);
+        }
+    }
+    
+    // 计算规则条件，返回 (是否满足, 观测值)，数据不足时返回 None
+    fn evaluate_condition(rule: &AlertRule, metric: Option<&Metric>, current_time: u64) -> Option<(bool, f64)> {
+        let compare = |value: f64| match rule.condition {
+            AlertCondition::GreaterThan | AlertCondition::MovingAverageGreaterThan { .. } => value > rule.threshold,
+            AlertCondition::LessThan | AlertCondition::MovingAverageLessThan { .. } => value < rule.threshold,
             AlertCondition::Equal => (value - rule.threshold).abs() < f64::EPSILON,
             AlertCondition::NotEqual => (value - rule.threshold).abs() >= f64::EPSILON,
             AlertCondition::GreaterThanOrEqual => value >= rule.threshold,
             AlertCondition::LessThanOrEqual => value <= rule.threshold,
+            AlertCondition::PercentChangeGreaterThan { .. } => value > rule.threshold,
+            AlertCondition::Absent => false,
         };
         
-        if condition_met {
-            Self::trigger_alert(rule, value, current_time);
-        } else {
-            Self::resolve_alert(&rule.id, current_time);
+        match rule.condition {
+            AlertCondition::Absent => {
+                let window_start = current_time.saturating_sub(rule.duration_seconds.saturating_mul(1_000_000_000));
+                let count = metric.map(|m| {
+                    m.data_points.iter().filter(|dp| dp.timestamp >= window_start).count()
+                }).unwrap_or(0);
+                Some((count == 0, count as f64))
+            }
+            AlertCondition::PercentChangeGreaterThan { window_seconds } => {
+                let metric = metric?;
+                let window_start = current_time.saturating_sub(window_seconds.saturating_mul(1_000_000_000));
+                
+                // 基准值: 窗口开始前的最后一个数据点，没有则取窗口内第一个数据点
+                let base = metric.data_points.iter()
+                    .rev()
+                    .find(|dp| dp.timestamp <= window_start)
+                    .or_else(|| metric.data_points.iter().find(|dp| dp.timestamp > window_start))?;
+                let latest = metric.data_points.back()?;
+                
+                if base.value == 0.0 || latest.timestamp <= base.timestamp {
+                    return None;
+                }
+                
+                let change_percent = ((latest.value - base.value) / base.value).abs() * 100.0;
+                Some((compare(change_percent), change_percent))
+            }
+            AlertCondition::MovingAverageGreaterThan { window_seconds }
+            | AlertCondition::MovingAverageLessThan { window_seconds } => {
+                let average = metric?.average_value(Some(window_seconds.saturating_mul(1_000_000_000)))?;
+                Some((compare(average), average))
+            }
+            _ => {
+                let value = metric?.latest_value()?;
+                Some((compare(value), value))
+            }
+        }
+    }
+    
+    // 验证告警规则
+    fn validate_alert_rule(rule: &AlertRule) -> Result<()> {
+        validate_param!(!rule.id.trim().is_empty(), "告警规则 ID 不能为空");
+        validate_param!(!rule.metric_name.trim().is_empty(), "指标名称不能为空");
+        validate_param!(!rule.message.trim().is_empty(), "告警消息不能为空");
+        validate_param!(rule.threshold.is_finite(), "告警阈值必须是有限数值");
+        validate_param!(
+            rule.duration_seconds <= MAX_ALERT_WINDOW_SECONDS,
+            format!("duration_seconds 不能超过 {} 秒", MAX_ALERT_WINDOW_SECONDS)
+        );
+        
+        match rule.condition {
+            AlertCondition::PercentChangeGreaterThan { window_seconds }
+            | AlertCondition::MovingAverageGreaterThan { window_seconds }
+            | AlertCondition::MovingAverageLessThan { window_seconds } => {
+                validate_param!(window_seconds > 0, "窗口长度必须大于 0");
+                validate_param!(
+                    window_seconds <= MAX_ALERT_WINDOW_SECONDS,
+                    format!("窗口长度不能超过 {} 秒", MAX_ALERT_WINDOW_SECONDS)
+                );
+            }
+            AlertCondition::Absent => {
+                validate_param!(rule.duration_seconds > 0, "Absent 条件的 duration_seconds 必须大于 0");
+            }
+            _ => {}
         }
+        
+        Ok(())
+    }
+    
+    // 清除规则的待定和活跃告警状态
+    fn clear_alert_state(rule_id: &str, current_time: u64) {
+        PENDING_ALERTS.with_borrow_mut(|pending| pending.remove(rule_id));
+        Self::resolve_alert(rule_id, current_time);
     }
     
     // 触发告警
@@ -378,6 +522,9 @@ impl MonitoringManager {
                            rule.metric_name, current_value, rule.threshold)),
                 None,
             );
+            
+            // 推送给订阅者
+            crate::alert_notifier::enqueue_alert(&alert_event);
         }
     }
     
@@ -531,8 +678,12 @@ impl MonitoringManager {
         let emergency_state = crate::emergency::get_emergency_state();
         let mut metrics = HashMap::new();
         
+        let pause_flags = crate::emergency::get_pause_flags();
+        metrics.insert("pause_flags".to_string(), pause_flags.len() as f64);
+        
         let status = match emergency_state.state {
-            crate::emergency::EmergencyState::Normal => HealthStatus::Healthy,
+            crate::emergency::EmergencyState::Normal if pause_flags.is_empty() => HealthStatus::Healthy,
+            crate::emergency::EmergencyState::Normal => HealthStatus::Warning,
             crate::emergency::EmergencyState::MaintenanceMode => HealthStatus::Warning,
             _ => HealthStatus::Critical,
         };
@@ -603,6 +754,82 @@ pub fn get_alert_history(limit: Option<usize>) -> Vec<AlertEvent> {
     })
 }
 
+#[query]
+// 获取所有告警规则
+pub fn get_alert_rules() -> Vec<AlertRule> {
+    // 稳定 BTreeMap 按规则 ID 有序
+    ALERT_RULES.with_borrow(|rules| rules.iter().map(|(_, rule)| rule).collect())
+}
+
+#[query]
+// 获取单个告警规则
+pub fn get_alert_rule(rule_id: String) -> Option<AlertRule> {
+    ALERT_RULES.with_borrow(|rules| rules.get(&rule_id))
+}
+
+#[update]
+// 创建告警规则
+pub fn create_alert_rule(rule: AlertRule) -> Result<AlertRule> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, crate::access_control::Permission::SystemAdmin)?;
+    
+    MonitoringManager::validate_alert_rule(&rule)?;
+    
+    if ALERT_RULES.with_borrow(|rules| rules.contains_key(&rule.id)) {
+        return Err(Error::InvalidArgument(format!("告警规则 {} 已存在", rule.id)));
+    }
+    
+    ALERT_RULES.with_borrow_mut(|rules| {
+        rules.insert(rule.id.clone(), rule.clone());
+    });
+    
+    crate::error::audit_log("create_alert_rule", &caller.to_string(), &format!("{:?}", rule));
+    
+    Ok(rule)
+}
+
+#[update]
+// 更新告警规则
+pub fn update_alert_rule(rule: AlertRule) -> Result<AlertRule> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, crate::access_control::Permission::SystemAdmin)?;
+    
+    MonitoringManager::validate_alert_rule(&rule)?;
+    
+    if !ALERT_RULES.with_borrow(|rules| rules.contains_key(&rule.id)) {
+        return Err(Error::InvalidArgument(format!("告警规则 {} 不存在", rule.id)));
+    }
+    
+    ALERT_RULES.with_borrow_mut(|rules| {
+        rules.insert(rule.id.clone(), rule.clone());
+    });
+    
+    // 规则变化后重新开始计时
+    MonitoringManager::clear_alert_state(&rule.id, ic_api::time());
+    
+    crate::error::audit_log("update_alert_rule", &caller.to_string(), &format!("{:?}", rule));
+    
+    Ok(rule)
+}
+
+#[update]
+// 删除告警规则
+pub fn delete_alert_rule(rule_id: String) -> Result<()> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, crate::access_control::Permission::SystemAdmin)?;
+    
+    let removed = ALERT_RULES.with_borrow_mut(|rules| rules.remove(&rule_id));
+    if removed.is_none() {
+        return Err(Error::InvalidArgument(format!("告警规则 {} 不存在", rule_id)));
+    }
+    
+    MonitoringManager::clear_alert_state(&rule_id, ic_api::time());
+    
+    crate::error::audit_log("delete_alert_rule", &caller.to_string(), &format!("rule_id={}", rule_id));
+    
+    Ok(())
+}
+
 #[update]
 // 确认告警
 pub fn acknowledge_alert(alert_id: String) -> Result<()> {
@@ -624,6 +851,170 @@ pub fn acknowledge_alert(alert_id: String) -> Result<()> {
     })
 }
 
+// Prometheus 指标名前缀
+const PROMETHEUS_PREFIX: &str = "bollar";
+
+// 以 Prometheus 文本格式渲染所有指标
+pub fn render_prometheus_metrics() -> String {
+    let mut out = String::new();
+    
+    // 已登记的指标 (按名称排序，保证输出稳定)
+    let mut metrics: Vec<Metric> = METRICS.with_borrow(|metrics| metrics.values().cloned().collect());
+    metrics.sort_by(|a, b| a.name.cmp(&b.name));
+    for metric in &metrics {
+        render_metric(&mut out, metric);
+    }
+    
+    // 协议实时指标
+    let protocol = crate::lending::get_protocol_metrics();
+    let oracle_age_seconds = crate::oracle::price_age_ms() as f64 / 1000.0;
+    let protocol_gauges = [
+        ("protocol_tvl_sats", "Total BTC collateral locked in the protocol", protocol.total_btc_locked as f64),
+        ("protocol_bollar_supply_cents", "Total Bollar debt outstanding", protocol.total_bollar_supply as f64),
+        ("protocol_positions", "Number of open positions", protocol.positions_count as f64),
+        ("protocol_liquidatable_positions", "Number of positions below the liquidation threshold", protocol.liquidatable_positions_count as f64),
+        ("protocol_btc_price_cents", "Current BTC price from the oracle", protocol.btc_price as f64),
+        ("oracle_price_age_seconds", "Seconds since the oracle price was last updated", oracle_age_seconds),
+    ];
+    for (name, help, value) in protocol_gauges {
+        let name = prometheus_name(name);
+        write_header(&mut out, &name, help, "gauge");
+        write_sample(&mut out, &name, &[], value);
+    }
+    
+    // 各端点延迟直方图
+    let latencies = crate::performance::PerformanceManager::get_endpoint_latencies();
+    if !latencies.is_empty() {
+        let name = prometheus_name("endpoint_latency_seconds");
+        write_header(&mut out, &name, "Request latency per endpoint", "histogram");
+        for latency in &latencies {
+            let endpoint = latency.endpoint.as_str();
+            for (bound, count) in crate::performance::LATENCY_BUCKETS_SECONDS.iter().zip(&latency.bucket_counts) {
+                let le = bound.to_string();
+                write_sample(&mut out, &format!("{}_bucket", name), &[("endpoint", endpoint), ("le", &le)], *count as f64);
+            }
+            write_sample(&mut out, &format!("{}_bucket", name), &[("endpoint", endpoint), ("le", "+Inf")], latency.count as f64);
+            write_sample(&mut out, &format!("{}_sum", name), &[("endpoint", endpoint)], latency.sum_seconds);
+            write_sample(&mut out, &format!("{}_count", name), &[("endpoint", endpoint)], latency.count as f64);
+        }
+        
+        let name = prometheus_name("endpoint_errors_total");
+        write_header(&mut out, &name, "Failed requests per endpoint", "counter");
+        for latency in &latencies {
+            write_sample(&mut out, &name, &[("endpoint", &latency.endpoint)], latency.error_count as f64);
+        }
+    }
+    
+    out
+}
+
+// 渲染单个已登记的指标
+fn render_metric(out: &mut String, metric: &Metric) {
+    let help = format!("{} ({})", metric.description, metric.unit);
+    match metric.metric_type {
+        MetricType::Counter => {
+            // 导出累计值，保证计数器单调递增
+            let name = prometheus_name(&format!("{}_total", metric.name));
+            let totals: Vec<(Vec<(String, String)>, f64)> = COUNTER_TOTALS.with_borrow(|totals| {
+                totals.iter()
+                    .filter(|((metric_name, _), _)| *metric_name == metric.name)
+                    .map(|((_, labels), value)| (labels.clone(), *value))
+                    .collect()
+            });
+            if totals.is_empty() {
+                return;
+            }
+            write_header(out, &name, &help, "counter");
+            for (labels, value) in totals {
+                let labels: Vec<(&str, &str)> = labels.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+                write_sample(out, &name, &labels, value);
+            }
+        }
+        MetricType::Gauge => {
+            if metric.data_points.is_empty() {
+                return;
+            }
+            let name = prometheus_name(&metric.name);
+            write_header(out, &name, &help, "gauge");
+            
+            // 每个标签组合输出最新值
+            let mut latest: Vec<(Vec<(String, String)>, f64)> = Vec::new();
+            for point in metric.data_points.iter() {
+                let labels = sorted_labels(Some(&point.labels));
+                match latest.iter_mut().find(|(l, _)| *l == labels) {
+                    Some(entry) => entry.1 = point.value,
+                    None => latest.push((labels, point.value)),
+                }
+            }
+            for (labels, value) in latest {
+                let labels: Vec<(&str, &str)> = labels.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+                write_sample(out, &name, &labels, value);
+            }
+        }
+        MetricType::Histogram | MetricType::Timer => {
+            // 累计观测值汇总为 summary (不受 data_points 窗口截断影响)
+            let Some((sum, count)) = SUMMARY_TOTALS.with_borrow(|totals| totals.get(&metric.name).copied()) else {
+                return;
+            };
+            let name = prometheus_name(&metric.name);
+            write_header(out, &name, &help, "summary");
+            write_sample(out, &format!("{}_sum", name), &[], sum);
+            write_sample(out, &format!("{}_count", name), &[], count as f64);
+        }
+    }
+}
+
+// 标签按键排序，作为标签组合的稳定标识
+fn sorted_labels(labels: Option<&HashMap<String, String>>) -> Vec<(String, String)> {
+    let mut labels: Vec<(String, String)> = labels
+        .map(|labels| labels.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default();
+    labels.sort();
+    labels
+}
+
+// 生成合法的 Prometheus 指标名
+fn prometheus_name(name: &str) -> String {
+    let sanitized: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    format!("{}_{}", PROMETHEUS_PREFIX, sanitized)
+}
+
+// 输出 HELP/TYPE 行
+fn write_header(out: &mut String, name: &str, help: &str, prometheus_type: &str) {
+    let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+    out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, prometheus_type));
+}
+
+// 输出一个样本行
+fn write_sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let rendered: Vec<String> = labels.iter()
+            .map(|(key, value)| format!("{}=\"{}\"", key, escape_label_value(value)))
+            .collect();
+        out.push_str(&format!("{{{}}}", rendered.join(",")));
+    }
+    out.push_str(&format!(" {}\n", format_value(value)));
+}
+
+// 转义标签值
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// 格式化样本值
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf".to_string() } else { "-Inf".to_string() }
+    } else {
+        value.to_string()
+    }
+}
+
 // 收集协议指标
 pub fn collect_protocol_metrics() {
     let metrics = crate::lending::get_protocol_metrics();
@@ -642,12 +1033,4 @@ pub fn collect_protocol_metrics() {
             .sum::<f64>() / positions.len() as f64;
         MonitoringManager::record_metric("average_health_factor", avg_health_factor, None);
     }
-}
-
-// 心跳函数中的监控任务
-#[ic_cdk_macros::heartbeat]
-async fn monitoring_heartbeat() {
-    collect_protocol_metrics();
-    MonitoringManager::check_alert_rules();
-    MonitoringManager::update_system_health();
 }
\ No newline at end of file
@@ -82,6 +82,12 @@ pub fn is_price_valid() -> bool {
     })
 }
 
+// 获取价格数据的年龄 (毫秒)
+pub fn price_age_ms() -> u64 {
+    let now = current_time_millis();
+    PRICE_DATA.with_borrow(|data| now.saturating_sub(data.timestamp))
+}
+
 // 存储价格数据
 fn store_price_data(data: PriceData) {
     PRICE_DATA.with_borrow_mut(|p| {
@@ -123,6 +129,11 @@ async fn fetch_price_from_oracle() -> Result<PriceData> {
 // 定期更新价格的任务
 #[update]
 pub async fn update_price() -> Result<()> {
+    // 价格更新被暂停时保留当前价格
+    if crate::emergency::is_operation_paused(crate::emergency::PauseOperation::OracleUpdate, None) {
+        return Ok(());
+    }
+    
     // 检查是否需要更新
     if !needs_update() {
         return Ok(());
@@ -201,40 +212,21 @@ fn update_all_positions_health_factor(btc_price: u64) {
     }
 }
 
-// 心跳函数，定期更新价格
-#[ic_cdk_macros::heartbeat]
-async fn heartbeat() {
-    // 尝试更新价格
-    match update_price().await {
-        Ok(_) => {
-            // 价格更新成功
-        }
-        Err(e) => {
-            // 记录错误
-            log_error(
-                LogLevel::Error,
-                &e,
-                Some("Heartbeat price update failed")
-            );
-        }
-    }
-    
-    // 检查是否有可清算的头寸
-    check_liquidatable_positions();
-}
-
 // 检查可清算的头寸
-fn check_liquidatable_positions() {
-    // 获取所有头寸
-    let positions = crate::get_positions();
-    
+pub(crate) fn check_liquidatable_positions() {
     // 获取当前 BTC 价格
-    let _btc_price = get_btc_price();
+    let btc_price = get_btc_price();
+    
+    // 通过健康因子索引获取候选头寸
+    let positions = crate::get_liquidation_candidates(
+        btc_price,
+        crate::liquidation::max_liquidation_threshold()
+    );
     
     // 检查每个头寸是否可清算
     for position in positions {
         // 获取池信息以获取清算阈值
-        if let Some(pool) = crate::get_pool(&position.owner) {
+        if let Some(pool) = crate::get_pool(&position.pool_address) {
             // 检查头寸是否可清算
             if position.is_liquidatable(pool.liquidation_threshold) {
                 // 记录可清算的头寸
@@ -6,7 +6,7 @@ use candid::{CandidType, Deserialize};
 use ic_cdk_macros::{query, update};
 use serde::Serialize;
 use std::cell::RefCell;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::time::Instant;
 
 // 性能指标
@@ -22,6 +22,19 @@ pub struct PerformanceMetrics {
     pub cpu_usage_percent: f64,
 }
 
+// 端点延迟统计 (直方图)
+#[derive(Clone, Debug, Default)]
+pub struct EndpointLatency {
+    pub endpoint: String,
+    pub count: u64,
+    pub error_count: u64,
+    pub sum_seconds: f64,
+    pub bucket_counts: Vec<u64>,     // 与 LATENCY_BUCKETS_SECONDS 对应的累计计数
+}
+
+// 延迟直方图桶上界 (秒)
+pub const LATENCY_BUCKETS_SECONDS: [f64; 8] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
 // 请求性能数据
 #[derive(Clone, Debug)]
 pub struct RequestPerformance {
@@ -169,6 +182,9 @@ thread_local! {
     // 性能数据存储
     static PERFORMANCE_DATA: RefCell<VecDeque<RequestPerformance>> = RefCell::new(VecDeque::new());
     
+    // 各端点累计延迟直方图 (记录时累加，不受 PERFORMANCE_DATA 容量截断影响，保证 Prometheus 计数单调)
+    static ENDPOINT_LATENCY_TOTALS: RefCell<BTreeMap<String, EndpointLatency>> = RefCell::new(BTreeMap::new());
+    
     // 缓存系统
     static POSITION_CACHE: RefCell<LRUCache<Position>> = RefCell::new(LRUCache::new(1000));
     static POOL_CACHE: RefCell<LRUCache<Pool>> = RefCell::new(LRUCache::new(100));
@@ -241,6 +257,26 @@ impl PerformanceManager {
             }
         });
         
+        ENDPOINT_LATENCY_TOTALS.with_borrow_mut(|totals| {
+            let entry = totals.entry(endpoint.clone()).or_insert_with(|| EndpointLatency {
+                endpoint: endpoint.clone(),
+                bucket_counts: vec![0; LATENCY_BUCKETS_SECONDS.len()],
+                ..Default::default()
+            });
+            
+            let seconds = duration_ns as f64 / 1_000_000_000.0;
+            entry.count += 1;
+            entry.sum_seconds += seconds;
+            if !success {
+                entry.error_count += 1;
+            }
+            for (i, bound) in LATENCY_BUCKETS_SECONDS.iter().enumerate() {
+                if seconds <= *bound {
+                    entry.bucket_counts[i] += 1;
+                }
+            }
+        });
+        
         // 检查是否为慢请求
         let config = PERFORMANCE_CONFIG.with_borrow(|config| config.clone());
         if config.enable_performance_logging {
@@ -298,6 +334,11 @@ impl PerformanceManager {
         })
     }
     
+    // 按端点返回累计延迟直方图 (自 canister 启动以来，升级后归零)
+    pub fn get_endpoint_latencies() -> Vec<EndpointLatency> {
+        ENDPOINT_LATENCY_TOTALS.with_borrow(|totals| totals.values().cloned().collect())
+    }
+    
     // 估算内存使用量
     fn estimate_memory_usage() -> u64 {
         let positions_count = crate::get_positions().len();
@@ -528,13 +569,8 @@ pub fn warm_up_cache() -> Result<()> {
     Ok(())
 }
 
-// 心跳函数中的性能管理任务
-#[ic_cdk_macros::heartbeat]
-async fn performance_heartbeat() {
-    // 定期清理过期缓存
-    PerformanceManager::cleanup_caches();
-    
-    // 记录性能指标到监控系统
+// 记录性能指标到监控系统
+pub fn record_performance_metrics() {
     let metrics = PerformanceManager::get_performance_metrics();
     crate::monitoring::MonitoringManager::record_metric("avg_response_time", metrics.avg_response_time_ms, None);
     crate::monitoring::MonitoringManager::record_metric("error_rate", 
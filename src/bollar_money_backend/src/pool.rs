@@ -127,6 +127,81 @@ impl Pool {
         )
     }
 
+    // 验证追加借款交易
+    // 池的 BTC 保持不变，只向借款人转出 Bollar，返回新状态、被花费的池 UTXO 和转出数量
+    pub fn validate_borrow(
+        &self,
+        txid: Txid,
+        nonce: u64,
+        pool_utxo_spent: Vec<String>,
+        pool_utxo_received: Vec<Utxo>,
+        input_coins: Vec<InputCoin>,
+        output_coins: Vec<OutputCoin>,
+    ) -> Result<(PoolState, Utxo, u64)> {
+        crate::error::catch_and_log(
+            || {
+                // 检查池状态
+                let current_utxo = self.current_state()
+                    .and_then(|s| s.utxo.clone())
+                    .ok_or(Error::EmptyPool)?;
+                
+                // 检查 nonce
+                let current_nonce = self.current_nonce();
+                if nonce != current_nonce {
+                    return Err(Error::PoolStateExpired(current_nonce));
+                }
+                
+                // 必须且只能花费池的当前 UTXO
+                if pool_utxo_spent != vec![current_utxo.outpoint.clone()] {
+                    return Err(Error::InvalidState("必须花费池的当前 UTXO".to_string()));
+                }
+                
+                // 借款不会向池转入任何代币
+                if !input_coins.is_empty() {
+                    return Err(Error::InvalidArgument("追加借款不应包含输入代币".to_string()));
+                }
+                
+                // 转出的只能是 Bollar
+                let mut bollar_amount: u128 = 0;
+                for output in &output_coins {
+                    if output.coin.id != self.meta.id {
+                        return Err(Error::InvalidArgument("追加借款只能转出 Bollar".to_string()));
+                    }
+                    bollar_amount = bollar_amount.checked_add(output.coin.value)
+                        .ok_or(Error::Overflow)?;
+                }
+                if bollar_amount == 0 {
+                    return Err(Error::InvalidArgument("追加借款数量不能为 0".to_string()));
+                }
+                let bollar_amount = u64::try_from(bollar_amount).map_err(|_| Error::Overflow)?;
+                
+                // 池收到的新 UTXO: BTC 不变，Bollar 恰好减少转出数量
+                let [received]: [Utxo; 1] = pool_utxo_received.try_into()
+                    .map_err(|_| Error::InvalidState("池必须收到且只收到一个 UTXO".to_string()))?;
+                if received.sats != current_utxo.sats {
+                    return Err(Error::InvalidState("追加借款不能改变池的 BTC 余额".to_string()));
+                }
+                let expected_bollar = self.bollar_balance()
+                    .checked_sub(bollar_amount as u128)
+                    .ok_or(Error::InsufficientFunds)?;
+                if received.coins.value_of(&self.meta.id) != expected_bollar {
+                    return Err(Error::InvalidState("池的 Bollar 余额变化与转出数量不符".to_string()));
+                }
+                
+                let new_state = PoolState {
+                    id: Some(txid),
+                    nonce: current_nonce + 1,
+                    utxo: Some(received),
+                    btc_price: crate::oracle::get_btc_price(),
+                };
+                
+                Ok((new_state, current_utxo, bollar_amount))
+            },
+            LogLevel::Warning,
+            &format!("validate_borrow: 追加借款验证失败, addr={}", self.addr)
+        )
+    }
+
     // 计算可铸造的最大 Bollar 数量 (使用安全数学运算)
     pub fn calculate_max_bollar(&self, btc_amount: u64, btc_price: u64) -> u64 {
         match crate::safe_math::safe_calculate_max_bollar(btc_amount, btc_price, self.collateral_ratio) {
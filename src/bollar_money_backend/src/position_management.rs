@@ -0,0 +1,495 @@
+// position_management.rs - 头寸管理
+// 这个模块实现头寸的合并、拆分和所有权转让功能
+
+use crate::{Error, Result, types::*, ic_api};
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::{
+    StableBTreeMap, Storable,
+    memory_manager::MemoryId,
+    storable::Bound,
+};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+// 单次合并的最大头寸数量
+const MAX_MERGE_POSITIONS: usize = 50;
+
+// 转让提议有效期 (纳秒)
+const TRANSFER_PROPOSAL_VALIDITY_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7天
+
+// 待接受的头寸转让
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct PendingPositionTransfer {
+    pub position_id: String,
+    pub from: String,              // 当前所有者
+    pub to: Principal,             // 接收方 Principal
+    pub proposed_at: u64,
+    pub expires_at: u64,
+    #[serde(default)]
+    pub btc_collateral: u64,       // 提议时的抵押品，接受时必须一致
+    #[serde(default)]
+    pub bollar_debt: u64,          // 提议时的债务，接受时必须一致
+}
+
+impl Storable for PendingPositionTransfer {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode PendingPositionTransfer")
+    }
+}
+
+thread_local! {
+    // 待接受的转让 (position_id -> PendingPositionTransfer)，存放在稳定内存中以便跨升级保留
+    static PENDING_TRANSFERS: RefCell<StableBTreeMap<String, PendingPositionTransfer, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(25))),
+        )
+    );
+}
+
+#[update]
+// 合并同一所有者在同一资金池中的多个头寸
+// 合并后保留第一个头寸 ID，其余头寸被删除
+pub fn merge_positions(position_ids: Vec<String>) -> Result<Position> {
+    let caller = ic_api::caller().to_string();
+
+    // 验证参数
+    validate_param!(position_ids.len() >= 2, "至少需要两个头寸才能合并");
+    validate_param!(position_ids.len() <= MAX_MERGE_POSITIONS,
+                    format!("单次最多合并 {} 个头寸", MAX_MERGE_POSITIONS));
+    let unique_ids: HashSet<&String> = position_ids.iter().collect();
+    validate_param!(unique_ids.len() == position_ids.len(), "头寸 ID 不能重复");
+
+    // 获取所有头寸并验证所有权
+    let mut positions = Vec::with_capacity(position_ids.len());
+    for id in &position_ids {
+        let position = crate::get_position(id).ok_or(Error::PositionNotFound)?;
+        if position.owner != caller {
+            return Err(Error::PermissionDenied("不是头寸所有者".to_string()));
+        }
+        positions.push(position);
+    }
+
+    // 所有头寸必须属于同一个资金池
+    let pool_address = positions[0].pool_address.clone();
+    if positions.iter().any(|p| p.pool_address != pool_address) {
+        return Err(Error::InvalidArgument("只能合并同一资金池中的头寸".to_string()));
+    }
+
+    // 检查紧急状态
+    check_emergency_state!("merge", pool_address);
+
+    // 检查黑名单
+    crate::blocklist::ensure_not_blocked(ic_api::caller(), None, "merge_positions")?;
+
+    // 检查权限
+    require_permission!(
+        crate::access_control::has_permission(ic_api::caller(), crate::access_control::Permission::Deposit),
+        "Deposit permission required"
+    );
+
+    // 获取组合锁，防止重入攻击
+    let _guard = crate::CombinedGuard::new(caller.clone(), pool_address.clone())
+        .ok_or(Error::SystemError("系统繁忙，请稍后重试".to_string()))?;
+
+    let pool = crate::get_pool(&pool_address).ok_or(Error::InvalidPool)?;
+
+    // 获取当前 BTC 价格
+    let btc_price = crate::oracle::get_btc_price();
+    if btc_price == 0 {
+        return Err(Error::OracleError("无效的 BTC 价格".to_string()));
+    }
+
+    // 汇总抵押品和债务
+    let (total_collateral, total_debt) = sum_positions(&positions)?;
+
+    // 合并后的头寸必须在可铸造上限之内 (与拆分使用相同规则)
+    check_within_mint_limit(&pool, total_collateral, total_debt, btc_price)?;
+
+    // 开始状态事务
+    let tx_id = crate::state_manager::StateManager::begin_transaction(
+        crate::state_manager::StateOperation::MergePositions
+    )?;
+
+    // 更新目标头寸并删除其余头寸
+    let mut merged = positions[0].clone();
+    merged.update(total_collateral, total_debt, btc_price);
+    crate::save_position(merged.clone());
+    crate::performance::cache_position(merged.clone());
+
+    for position in positions.iter().skip(1) {
+        crate::delete_position(&position.id);
+    }
+
+    // 参与合并的头寸已变更或被删除，其转让提议一并失效
+    PENDING_TRANSFERS.with_borrow_mut(|transfers| {
+        for position in &positions {
+            transfers.remove(&position.id);
+        }
+    });
+
+    // 提交事务
+    if let Err(e) = crate::state_manager::StateManager::commit_transaction(tx_id) {
+        secure_log_error!(LogCategory::System, format!("Merge transaction commit failed: {:?}", e));
+        return Err(e);
+    }
+
+    crate::events::record_event(crate::events::ProtocolEvent::PositionsMerged {
+        position_id: merged.id.clone(),
+        merged_position_ids: position_ids[1..].to_vec(),
+        owner: caller.clone(),
+        pool_address: pool_address.clone(),
+        btc_collateral: total_collateral,
+        bollar_debt: total_debt,
+        btc_price,
+    });
+
+    secure_log_info!(
+        LogCategory::Transaction,
+        format!("Positions merged into {}", merged.id),
+        format!("Merged: {:?}, BTC: {}, Bollar: {}, User: {}",
+                position_ids, total_collateral, total_debt, caller)
+    );
+
+    crate::error::audit_log(
+        "merge_positions",
+        &caller,
+        &format!("merged_into={}, position_ids={:?}, btc={}, bollar={}",
+                 merged.id, position_ids, total_collateral, total_debt)
+    );
+
+    Ok(merged)
+}
+
+#[update]
+// 从现有头寸中拆分出一个子头寸
+// 拆分后的两个头寸都必须满足抵押率要求
+pub fn split_position(
+    position_id: String,
+    btc_amount: u64,
+    bollar_amount: u64,
+) -> Result<Position> {
+    let caller = ic_api::caller().to_string();
+
+    // 获取头寸并验证所有权
+    let position = crate::get_position(&position_id).ok_or(Error::PositionNotFound)?;
+    if position.owner != caller {
+        return Err(Error::PermissionDenied("不是头寸所有者".to_string()));
+    }
+
+    // 检查紧急状态
+    check_emergency_state!("split", position.pool_address);
+
+    // 检查黑名单
+    crate::blocklist::ensure_not_blocked(ic_api::caller(), None, "split_position")?;
+
+    // 检查权限
+    require_permission!(
+        crate::access_control::has_permission(ic_api::caller(), crate::access_control::Permission::Deposit),
+        "Deposit permission required"
+    );
+
+    // 验证拆分数量
+    validate_param!(btc_amount < position.btc_collateral, "拆分的 BTC 数量必须小于头寸抵押数量");
+    validate_param!(bollar_amount <= position.bollar_debt, "拆分的 Bollar 数量不能超过头寸债务");
+    validate_param!(btc_amount >= MIN_BTC_VALUE,
+                    format!("拆分的 BTC 数量不能小于 {} satoshis", MIN_BTC_VALUE));
+    validate_param!(position.btc_collateral - btc_amount >= MIN_BTC_VALUE,
+                    format!("剩余的 BTC 数量不能小于 {} satoshis", MIN_BTC_VALUE));
+
+    let pool_address = position.pool_address.clone();
+
+    // 获取组合锁，防止重入攻击
+    let _guard = crate::CombinedGuard::new(caller.clone(), pool_address.clone())
+        .ok_or(Error::SystemError("系统繁忙，请稍后重试".to_string()))?;
+
+    let pool = crate::get_pool(&pool_address).ok_or(Error::InvalidPool)?;
+
+    // 获取当前 BTC 价格
+    let btc_price = crate::oracle::get_btc_price();
+    if btc_price == 0 {
+        return Err(Error::OracleError("无效的 BTC 价格".to_string()));
+    }
+
+    // 拆分后的两个头寸都必须在可铸造上限之内
+    let remaining_collateral = position.btc_collateral - btc_amount;
+    let remaining_debt = position.bollar_debt - bollar_amount;
+    check_within_mint_limit(&pool, btc_amount, bollar_amount, btc_price)?;
+    check_within_mint_limit(&pool, remaining_collateral, remaining_debt, btc_price)?;
+
+    // 生成子头寸 ID
+    let new_position_id = crate::next_position_id(&pool_address);
+
+    // 开始状态事务
+    let tx_id = crate::state_manager::StateManager::begin_transaction(
+        crate::state_manager::StateOperation::SplitPosition
+    )?;
+
+    // 更新原头寸
+    let mut remaining = position.clone();
+    remaining.update(remaining_collateral, remaining_debt, btc_price);
+    crate::save_position(remaining.clone());
+    crate::performance::cache_position(remaining);
+
+    // 创建子头寸
+    let sub_position = Position::new(
+        new_position_id.clone(),
+        pool_address.clone(),
+        caller.clone(),
+        btc_amount,
+        bollar_amount,
+        btc_price,
+    );
+    crate::save_position(sub_position.clone());
+    crate::performance::cache_position(sub_position.clone());
+
+    // 提交事务
+    if let Err(e) = crate::state_manager::StateManager::commit_transaction(tx_id) {
+        secure_log_error!(LogCategory::System, format!("Split transaction commit failed: {:?}", e));
+        return Err(e);
+    }
+
+    crate::events::record_event(crate::events::ProtocolEvent::PositionSplit {
+        position_id: position_id.clone(),
+        new_position_id: new_position_id.clone(),
+        owner: caller.clone(),
+        pool_address: pool_address.clone(),
+        btc_amount,
+        bollar_amount,
+        remaining_btc_collateral: remaining_collateral,
+        remaining_bollar_debt: remaining_debt,
+        btc_price,
+    });
+
+    secure_log_info!(
+        LogCategory::Transaction,
+        format!("Position {} split into {}", position_id, new_position_id),
+        format!("BTC: {}, Bollar: {}, User: {}", btc_amount, bollar_amount, caller)
+    );
+
+    crate::error::audit_log(
+        "split_position",
+        &caller,
+        &format!("position_id={}, new_position_id={}, btc={}, bollar={}",
+                 position_id, new_position_id, btc_amount, bollar_amount)
+    );
+
+    Ok(sub_position)
+}
+
+#[update]
+// 提议将头寸转让给另一个 Principal
+// new_owner 可以是 Principal 文本，也可以是已认证的比特币地址
+pub fn propose_position_transfer(position_id: String, new_owner: String) -> Result<PendingPositionTransfer> {
+    let caller = ic_api::caller().to_string();
+
+    // 检查紧急状态
+    check_emergency_state!("transfer");
+
+    // 获取头寸并验证所有权
+    let position = crate::get_position(&position_id).ok_or(Error::PositionNotFound)?;
+    if position.owner != caller {
+        return Err(Error::PermissionDenied("不是头寸所有者".to_string()));
+    }
+
+    // 解析接收方
+    let to = resolve_transfer_recipient(&new_owner)?;
+    crate::input_validation::validate_principal(to, "propose_position_transfer")?;
+    validate_param!(to.to_string() != caller, "不能将头寸转让给自己");
+
+    // 被封禁的所有者不能转出头寸，被封禁的接收方不能被提议
+    crate::blocklist::ensure_not_blocked(ic_api::caller(), None, "propose_position_transfer")?;
+    crate::blocklist::ensure_not_blocked(to, None, "propose_position_transfer")?;
+
+    let now = ic_api::time();
+    let transfer = PendingPositionTransfer {
+        position_id: position_id.clone(),
+        from: caller.clone(),
+        to,
+        proposed_at: now,
+        expires_at: now + TRANSFER_PROPOSAL_VALIDITY_NS,
+        btc_collateral: position.btc_collateral,
+        bollar_debt: position.bollar_debt,
+    };
+
+    // 新的提议会替换该头寸之前的提议
+    PENDING_TRANSFERS.with_borrow_mut(|transfers| {
+        transfers.insert(position_id.clone(), transfer.clone());
+    });
+
+    crate::error::audit_log(
+        "propose_position_transfer",
+        &caller,
+        &format!("position_id={}, to={}", position_id, to)
+    );
+
+    Ok(transfer)
+}
+
+#[update]
+// 接收方接受头寸转让
+pub fn accept_position_transfer(position_id: String) -> Result<Position> {
+    let caller = ic_api::caller();
+
+    // 检查紧急状态
+    check_emergency_state!("transfer");
+
+    // 获取转让提议
+    let transfer = PENDING_TRANSFERS.with_borrow(|transfers| transfers.get(&position_id))
+        .ok_or(Error::InvalidArgument("没有待接受的转让".to_string()))?;
+
+    if transfer.to != caller {
+        return Err(Error::PermissionDenied("不是转让接收方".to_string()));
+    }
+
+    // 检查黑名单 (接收方及其关联地址)
+    crate::blocklist::ensure_not_blocked(caller, None, "accept_position_transfer")?;
+
+    if ic_api::time() > transfer.expires_at {
+        PENDING_TRANSFERS.with_borrow_mut(|transfers| {
+            transfers.remove(&position_id);
+        });
+        return Err(Error::InvalidArgument("转让提议已过期".to_string()));
+    }
+
+    // 头寸在提议之后不能已被其他操作转移
+    let position = crate::get_position(&position_id).ok_or(Error::PositionNotFound)?;
+    if position.owner != transfer.from {
+        PENDING_TRANSFERS.with_borrow_mut(|transfers| {
+            transfers.remove(&position_id);
+        });
+        return Err(Error::InvalidState("头寸所有者已变更".to_string()));
+    }
+
+    // 提议之后抵押品或债务发生变化 (拆分、追加借款、还款等) 时，接收方需要重新确认
+    if position.btc_collateral != transfer.btc_collateral || position.bollar_debt != transfer.bollar_debt {
+        PENDING_TRANSFERS.with_borrow_mut(|transfers| {
+            transfers.remove(&position_id);
+        });
+        return Err(Error::InvalidState("头寸在转让提议之后已变更".to_string()));
+    }
+
+    let pool_address = position.pool_address.clone();
+
+    // 获取组合锁，防止重入攻击
+    let _guard = crate::CombinedGuard::new(transfer.from.clone(), pool_address)
+        .ok_or(Error::SystemError("系统繁忙，请稍后重试".to_string()))?;
+
+    // 开始状态事务
+    let tx_id = crate::state_manager::StateManager::begin_transaction(
+        crate::state_manager::StateOperation::TransferPosition
+    )?;
+
+    let mut transferred = position.clone();
+    transferred.owner = caller.to_string();
+    transferred.last_updated_at = ic_api::time();
+    crate::save_position(transferred.clone());
+    crate::performance::cache_position(transferred.clone());
+
+    PENDING_TRANSFERS.with_borrow_mut(|transfers| {
+        transfers.remove(&position_id);
+    });
+
+    // 提交事务
+    if let Err(e) = crate::state_manager::StateManager::commit_transaction(tx_id) {
+        secure_log_error!(LogCategory::System, format!("Transfer transaction commit failed: {:?}", e));
+        return Err(e);
+    }
+
+    crate::events::record_event(crate::events::ProtocolEvent::PositionTransferred {
+        position_id: position_id.clone(),
+        pool_address: transferred.pool_address.clone(),
+        from: transfer.from.clone(),
+        to: caller.to_string(),
+    });
+
+    crate::error::audit_log(
+        "accept_position_transfer",
+        &caller.to_string(),
+        &format!("position_id={}, from={}", position_id, transfer.from)
+    );
+
+    Ok(transferred)
+}
+
+#[update]
+// 当前所有者取消转让提议
+pub fn cancel_position_transfer(position_id: String) -> Result<()> {
+    let caller = ic_api::caller().to_string();
+
+    let removed = PENDING_TRANSFERS.with_borrow_mut(|transfers| {
+        match transfers.get(&position_id) {
+            Some(transfer) if transfer.from == caller => transfers.remove(&position_id),
+            _ => None,
+        }
+    });
+
+    if removed.is_none() {
+        return Err(Error::InvalidArgument("没有可取消的转让".to_string()));
+    }
+
+    crate::error::audit_log(
+        "cancel_position_transfer",
+        &caller,
+        &format!("position_id={}", position_id)
+    );
+
+    Ok(())
+}
+
+#[query]
+// 获取与调用者相关的待处理转让 (作为转出方或接收方)
+pub fn get_pending_position_transfers() -> Vec<PendingPositionTransfer> {
+    let caller = ic_api::caller();
+    let now = ic_api::time();
+
+    PENDING_TRANSFERS.with_borrow(|transfers| {
+        transfers.iter()
+            .map(|(_, t)| t)
+            .filter(|t| t.expires_at >= now)
+            .filter(|t| t.to == caller || t.from == caller.to_string())
+            .collect()
+    })
+}
+
+// 解析转让接收方，支持 Principal 文本或已认证的比特币地址
+fn resolve_transfer_recipient(new_owner: &str) -> Result<Principal> {
+    if let Ok(principal) = Principal::from_text(new_owner) {
+        return Ok(principal);
+    }
+
+    crate::auth::get_principal_by_address(new_owner.to_string())
+        .ok_or(Error::InvalidArgument("接收方地址未绑定任何 Principal".to_string()))
+}
+
+// 汇总多个头寸的抵押品和债务
+fn sum_positions(positions: &[Position]) -> Result<(u64, u64)> {
+    positions.iter().try_fold((0u64, 0u64), |(collateral, debt), p| {
+        Ok((
+            crate::safe_math::safe_add(collateral, p.btc_collateral)?,
+            crate::safe_math::safe_add(debt, p.bollar_debt)?,
+        ))
+    })
+}
+
+// 检查债务是否在抵押品可铸造的上限之内
+fn check_within_mint_limit(pool: &Pool, btc_collateral: u64, bollar_debt: u64, btc_price: u64) -> Result<()> {
+    let max_bollar = pool.calculate_max_bollar(btc_collateral, btc_price);
+    if bollar_debt > max_bollar {
+        return Err(Error::InvalidArgument(format!(
+            "债务 {} 超过抵押品 {} satoshis 可铸造的最大值 {}",
+            bollar_debt, btc_collateral, max_bollar
+        )));
+    }
+    Ok(())
+}
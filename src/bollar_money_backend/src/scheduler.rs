@@ -0,0 +1,427 @@
+// scheduler.rs - 定时任务调度器
+// 这个模块基于 ic_cdk_timers 实现统一的后台任务调度，支持按任务设置间隔、启用/禁用，并记录运行状态和错误次数
+
+use crate::{Error, Result, ic_api};
+use crate::access_control::Permission;
+use candid::{CandidType, Deserialize};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::{
+    StableBTreeMap, Storable,
+    memory_manager::MemoryId,
+    storable::Bound,
+};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// 任务间隔下限 (秒)
+const MIN_JOB_INTERVAL_SECONDS: u64 = 5;
+
+// 任务间隔上限 (7天)
+const MAX_JOB_INTERVAL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+// 后台任务
+#[derive(CandidType, Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JobId {
+    PriceUpdate,              // 更新 BTC 价格
+    LiquidationScan,          // 扫描可清算头寸
+    SessionSecretInit,        // 初始化会话密钥 (一次性任务)
+    SessionCleanup,           // 清理过期会话
+    ProtocolMetrics,          // 采集协议和性能指标，更新系统健康状态
+    AlertEvaluation,          // 评估告警规则并推送告警
+    AlertRetry,               // 按指数退避重新投递失败的告警
+    AutoResume,               // 维护模式自动恢复
+    EmergencyActionCleanup,   // 清理过期的紧急操作提案
+    LogCleanup,               // 清理过期日志
+    CacheCleanup,             // 清理过期缓存
+    AutoSnapshot,             // 自动状态快照
+    AutoBackup,               // 自动备份
+}
+
+impl JobId {
+    // 所有任务
+    pub const ALL: [JobId; 13] = [
+        JobId::PriceUpdate,
+        JobId::LiquidationScan,
+        JobId::SessionSecretInit,
+        JobId::SessionCleanup,
+        JobId::ProtocolMetrics,
+        JobId::AlertEvaluation,
+        JobId::AlertRetry,
+        JobId::AutoResume,
+        JobId::EmergencyActionCleanup,
+        JobId::LogCleanup,
+        JobId::CacheCleanup,
+        JobId::AutoSnapshot,
+        JobId::AutoBackup,
+    ];
+
+    // 默认执行间隔 (秒)
+    pub fn default_interval_seconds(&self) -> u64 {
+        match self {
+            JobId::PriceUpdate => 60,
+            JobId::LiquidationScan => 60,
+            // 一次性任务失败后的重试间隔
+            JobId::SessionSecretInit => 30,
+            JobId::SessionCleanup => 10 * 60,
+            JobId::ProtocolMetrics => 60,
+            JobId::AlertEvaluation => 30,
+            // 实际重试时间由每条投递的退避时间决定
+            JobId::AlertRetry => 30,
+            JobId::AutoResume => 60,
+            JobId::EmergencyActionCleanup => 10 * 60,
+            JobId::LogCleanup => 60 * 60,
+            JobId::CacheCleanup => 5 * 60,
+            // 快照和备份任务内部还会按各自配置的间隔判断是否需要执行
+            JobId::AutoSnapshot => 10 * 60,
+            JobId::AutoBackup => 10 * 60,
+        }
+    }
+
+    // 一次性任务只在 init/post_upgrade 后执行，失败时按间隔重试直到成功
+    pub fn is_one_shot(&self) -> bool {
+        matches!(self, JobId::SessionSecretInit)
+    }
+
+    // 存储键
+    fn key(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+// 任务配置 (持久化，升级后保留)
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct JobConfig {
+    pub interval_seconds: u64,
+    pub enabled: bool,
+}
+
+impl Storable for JobConfig {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![];
+        let _ = ciborium::ser::into_writer(self, &mut bytes);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        ciborium::de::from_reader(bytes.as_ref()).expect("Failed to decode JobConfig")
+    }
+}
+
+// 任务运行结果
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum JobStatus {
+    Success,
+    Failed,
+}
+
+// 任务运行状态
+#[derive(CandidType, Clone, Debug, Default, Deserialize, Serialize)]
+pub struct JobRunState {
+    pub running: bool,
+    pub last_started_at: Option<u64>,
+    pub last_finished_at: Option<u64>,
+    pub last_status: Option<JobStatus>,
+    pub last_error: Option<String>,
+    pub run_count: u64,
+    pub error_count: u64,
+}
+
+// 任务信息
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct ScheduledJob {
+    pub id: JobId,
+    pub interval_seconds: u64,
+    pub enabled: bool,
+    pub scheduled: bool,        // 当前是否已注册定时器
+    pub running: bool,
+    pub last_started_at: Option<u64>,
+    pub last_finished_at: Option<u64>,
+    pub last_status: Option<JobStatus>,
+    pub last_error: Option<String>,
+    pub run_count: u64,
+    pub error_count: u64,
+}
+
+thread_local! {
+    // 任务配置 (任务名 -> JobConfig)，没有记录的任务使用默认配置
+    static JOB_CONFIGS: RefCell<StableBTreeMap<String, JobConfig, crate::Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(14))),
+        )
+    );
+
+    // 任务运行状态
+    static JOB_STATES: RefCell<BTreeMap<JobId, JobRunState>> = RefCell::new(BTreeMap::new());
+
+    // 已注册的定时器
+    static JOB_TIMERS: RefCell<BTreeMap<JobId, ic_cdk_timers::TimerId>> = RefCell::new(BTreeMap::new());
+}
+
+// 获取任务配置
+pub fn job_config(job: JobId) -> JobConfig {
+    JOB_CONFIGS.with_borrow(|configs| configs.get(&job.key()))
+        .unwrap_or(JobConfig {
+            interval_seconds: job.default_interval_seconds(),
+            enabled: true,
+        })
+}
+
+// 保存任务配置
+fn save_job_config(job: JobId, config: JobConfig) {
+    JOB_CONFIGS.with_borrow_mut(|configs| {
+        configs.insert(job.key(), config);
+    });
+}
+
+// 为所有启用的任务注册定时器 (在 init 和 post_upgrade 中调用)
+pub fn start_jobs() {
+    for job in JobId::ALL {
+        schedule_job(job);
+    }
+}
+
+// 按当前配置重新注册任务的定时器，一次性任务立即执行一次
+fn schedule_job(job: JobId) {
+    if let Some(timer_id) = JOB_TIMERS.with_borrow_mut(|timers| timers.remove(&job)) {
+        ic_api::clear_timer(timer_id);
+    }
+
+    let config = job_config(job);
+    if !config.enabled {
+        return;
+    }
+
+    if job.is_one_shot() {
+        schedule_one_shot(job, Duration::ZERO);
+        return;
+    }
+
+    let timer_id = ic_api::set_timer_interval(Duration::from_secs(config.interval_seconds), move || {
+        ic_cdk::spawn(run_job(job));
+    });
+    JOB_TIMERS.with_borrow_mut(|timers| {
+        timers.insert(job, timer_id);
+    });
+}
+
+// 注册一次性任务的定时器，执行失败时按任务间隔重新注册
+fn schedule_one_shot(job: JobId, delay: Duration) {
+    let timer_id = ic_api::set_timer(delay, move || {
+        ic_cdk::spawn(async move {
+            JOB_TIMERS.with_borrow_mut(|timers| timers.remove(&job));
+            if run_job(job).await.is_err() && job_config(job).enabled {
+                schedule_one_shot(job, Duration::from_secs(job_config(job).interval_seconds));
+            }
+        });
+    });
+    JOB_TIMERS.with_borrow_mut(|timers| {
+        timers.insert(job, timer_id);
+    });
+}
+
+// 任务运行标记，离开作用域时清除 running
+// 任务在 await 之后 trap 时 ic_cdk 会在清理阶段释放 future，guard 随之被 drop，任务不会永远卡在运行中
+#[must_use]
+pub(crate) struct JobRunGuard(JobId);
+
+impl JobRunGuard {
+    pub(crate) fn new(job: JobId) -> Option<Self> {
+        JOB_STATES.with_borrow_mut(|states| {
+            let state = states.entry(job).or_default();
+            if state.running {
+                return None;
+            }
+            state.running = true;
+            state.last_started_at = Some(ic_api::time());
+            Some(JobRunGuard(job))
+        })
+    }
+}
+
+impl Drop for JobRunGuard {
+    fn drop(&mut self) {
+        JOB_STATES.with_borrow_mut(|states| {
+            states.entry(self.0).or_default().running = false;
+        });
+    }
+}
+
+// 执行任务并记录运行状态，上一次运行尚未结束时跳过
+pub async fn run_job(job: JobId) -> Result<()> {
+    let _guard = JobRunGuard::new(job)
+        .ok_or(Error::InvalidState(format!("Job {:?} is already running", job)))?;
+
+    let result = execute_job(job).await;
+
+    JOB_STATES.with_borrow_mut(|states| {
+        let state = states.entry(job).or_default();
+        state.last_finished_at = Some(ic_api::time());
+        state.run_count += 1;
+        match &result {
+            Ok(()) => {
+                state.last_status = Some(JobStatus::Success);
+                state.last_error = None;
+            }
+            Err(e) => {
+                state.last_status = Some(JobStatus::Failed);
+                state.last_error = Some(e.to_string());
+                state.error_count += 1;
+            }
+        }
+    });
+
+    if let Err(e) = &result {
+        crate::secure_logging::secure_log(
+            crate::secure_logging::SecureLogLevel::Error,
+            crate::secure_logging::LogCategory::System,
+            format!("Scheduled job {:?} failed", job),
+            Some(e.to_string()),
+            None,
+        );
+    }
+
+    result
+}
+
+// 任务实际执行的逻辑
+async fn execute_job(job: JobId) -> Result<()> {
+    match job {
+        JobId::PriceUpdate => crate::oracle::update_price().await,
+        JobId::LiquidationScan => {
+            crate::oracle::check_liquidatable_positions();
+            Ok(())
+        }
+        JobId::SessionSecretInit => crate::auth::ensure_session_secret().await,
+        JobId::SessionCleanup => {
+            crate::auth::cleanup_expired_sessions();
+            Ok(())
+        }
+        JobId::ProtocolMetrics => {
+            crate::monitoring::collect_protocol_metrics();
+            crate::performance::record_performance_metrics();
+            crate::monitoring::MonitoringManager::update_system_health();
+            Ok(())
+        }
+        JobId::AlertEvaluation => {
+            crate::monitoring::MonitoringManager::check_alert_rules();
+            Ok(())
+        }
+        JobId::AlertRetry => {
+            crate::alert_notifier::process_alert_retries();
+            Ok(())
+        }
+        JobId::AutoResume => {
+            crate::emergency::check_auto_resume();
+            Ok(())
+        }
+        JobId::EmergencyActionCleanup => {
+            crate::emergency::remove_expired_emergency_actions();
+            Ok(())
+        }
+        JobId::LogCleanup => {
+            crate::secure_logging::cleanup_expired_logs();
+            Ok(())
+        }
+        JobId::CacheCleanup => {
+            crate::performance::PerformanceManager::cleanup_caches();
+            Ok(())
+        }
+        JobId::AutoSnapshot => {
+            crate::state_manager::auto_snapshot_task();
+            Ok(())
+        }
+        JobId::AutoBackup => {
+            crate::backup_recovery::BackupManager::auto_backup_task();
+            Ok(())
+        }
+    }
+}
+
+// 汇总任务信息
+fn job_info(job: JobId) -> ScheduledJob {
+    let config = job_config(job);
+    let state = JOB_STATES.with_borrow(|states| states.get(&job).cloned().unwrap_or_default());
+    let scheduled = JOB_TIMERS.with_borrow(|timers| timers.contains_key(&job));
+
+    ScheduledJob {
+        id: job,
+        interval_seconds: config.interval_seconds,
+        enabled: config.enabled,
+        scheduled,
+        running: state.running,
+        last_started_at: state.last_started_at,
+        last_finished_at: state.last_finished_at,
+        last_status: state.last_status,
+        last_error: state.last_error,
+        run_count: state.run_count,
+        error_count: state.error_count,
+    }
+}
+
+#[query]
+// 获取所有后台任务的配置和运行状态
+pub fn get_scheduled_jobs() -> Vec<ScheduledJob> {
+    JobId::ALL.iter().map(|job| job_info(*job)).collect()
+}
+
+#[update]
+// 启用或禁用后台任务 (需要系统维护权限)
+pub fn set_job_enabled(job: JobId, enabled: bool) -> Result<ScheduledJob> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, Permission::SystemMaintenance)?;
+
+    let mut config = job_config(job);
+    config.enabled = enabled;
+    save_job_config(job, config);
+    schedule_job(job);
+
+    crate::error::audit_log(
+        "set_job_enabled",
+        &caller.to_string(),
+        &format!("job={:?}, enabled={}", job, enabled)
+    );
+
+    Ok(job_info(job))
+}
+
+#[update]
+// 修改后台任务的执行间隔 (需要系统维护权限)
+pub fn set_job_interval(job: JobId, interval_seconds: u64) -> Result<ScheduledJob> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, Permission::SystemMaintenance)?;
+    validate_param!(
+        (MIN_JOB_INTERVAL_SECONDS..=MAX_JOB_INTERVAL_SECONDS).contains(&interval_seconds),
+        format!("任务间隔必须在 {} 到 {} 秒之间", MIN_JOB_INTERVAL_SECONDS, MAX_JOB_INTERVAL_SECONDS)
+    );
+
+    let mut config = job_config(job);
+    config.interval_seconds = interval_seconds;
+    save_job_config(job, config);
+    schedule_job(job);
+
+    crate::error::audit_log(
+        "set_job_interval",
+        &caller.to_string(),
+        &format!("job={:?}, interval_seconds={}", job, interval_seconds)
+    );
+
+    Ok(job_info(job))
+}
+
+#[update]
+// 立即执行一次后台任务 (需要系统维护权限，禁用的任务也可以手动执行)
+pub async fn run_job_now(job: JobId) -> Result<ScheduledJob> {
+    let caller = ic_api::caller();
+    crate::access_control::check_permission(caller, Permission::SystemMaintenance)?;
+
+    crate::error::audit_log("run_job_now", &caller.to_string(), &format!("job={:?}", job));
+
+    run_job(job).await?;
+
+    Ok(job_info(job))
+}
@@ -148,6 +148,16 @@ pub fn secure_log(
         }
     });
     
+    // 审计、安全和紧急类日志同时写入持久化审计日志
+    if matches!(entry.category, LogCategory::Audit | LogCategory::Security | LogCategory::Emergency) {
+        crate::audit::append_audit_entry(
+            entry.category.clone(),
+            &entry.message,
+            &entry.principal.map(|p| p.to_string()).unwrap_or_default(),
+            entry.context.as_deref().unwrap_or_default()
+        );
+    }
+    
     // 输出到系统日志（仅非敏感信息）
     let safe_message = if entry.sensitive_data_hash.is_some() {
         format!("{} [SENSITIVE_DATA_HASH: {}]", 
@@ -487,10 +497,4 @@ macro_rules! secure_log_critical {
             None,
         )
     };
-}
-
-// 心跳函数中的清理任务
-#[ic_cdk_macros::heartbeat]
-async fn secure_logging_heartbeat() {
-    cleanup_expired_logs();
 }
\ No newline at end of file
@@ -2,121 +2,66 @@
 // 这个模块实现抵押率和清算阈值管理功能
 
 use crate::{Error, LogLevel, Result, error::catch_and_log};
-use ic_cdk_macros::{query, update};
+use ic_cdk_macros::query;
 use candid::{CandidType, Deserialize};
 use serde::Serialize;
 
-#[update]
-// 更新抵押率
-pub fn update_collateral_ratio(new_ratio: u8) -> Result<bool> {
-    // 使用 catch_and_log 包装操作
-    catch_and_log(
-        || {
-            // 验证调用者是否为控制者
-            let caller = crate::ic_api::caller();
-            if !crate::ic_api::is_controller(&caller) {
-                return Err(Error::PermissionDenied("Not authorized".to_string()));
-            }
-            
-            // 验证抵押率参数
-            if new_ratio == 0 || new_ratio > 100 {
-                return Err(Error::InvalidArgument(format!(
-                    "无效的抵押率: {}，应在 1-100 之间",
-                    new_ratio
-                )));
-            }
-            
-            // 获取所有池并更新抵押率
-            let pools = crate::get_pools();
-            let mut updated = false;
-            
-            for mut pool in pools {
-                // 检查清算阈值是否仍然有效
-                if new_ratio >= pool.liquidation_threshold {
-                    return Err(Error::InvalidArgument(format!(
-                        "抵押率 {} 不能大于或等于清算阈值 {}",
-                        new_ratio,
-                        pool.liquidation_threshold
-                    )));
-                }
-                
-                // 更新抵押率
-                pool.update_collateral_ratio(new_ratio);
-                crate::save_pool(pool);
-                updated = true;
-            }
-            
-            if updated {
-                ic_cdk::println!(
-                    "Collateral ratio updated to {}% by {}",
-                    new_ratio,
-                    caller
-                );
-            }
-            
-            Ok(updated)
-        },
-        LogLevel::Error,
-        &format!("update_collateral_ratio: 更新抵押率失败, new_ratio={}", new_ratio)
-    )
-}
-
-#[update]
-// 更新清算阈值
-pub fn update_liquidation_threshold(new_threshold: u8) -> Result<bool> {
-    // 使用 catch_and_log 包装操作
-    catch_and_log(
-        || {
-            // 验证调用者是否为控制者
-            let caller = crate::ic_api::caller();
-            if !crate::ic_api::is_controller(&caller) {
-                return Err(Error::PermissionDenied("Not authorized".to_string()));
-            }
-            
-            // 验证清算阈值参数
-            if new_threshold == 0 || new_threshold > 100 {
-                return Err(Error::InvalidArgument(format!(
-                    "无效的清算阈值: {}，应在 1-100 之间",
-                    new_threshold
-                )));
-            }
-            
-            // 获取所有池并更新清算阈值
-            let pools = crate::get_pools();
-            let mut updated = false;
-            
-            for mut pool in pools {
-                // 检查抵押率是否仍然有效
-                if pool.collateral_ratio >= new_threshold {
-                    return Err(Error::InvalidArgument(format!(
-                        "清算阈值 {} 不能小于或等于抵押率 {}",
-                        new_threshold,
-                        pool.collateral_ratio
-                    )));
-                }
-                
-                // 更新清算阈值
-                pool.update_liquidation_threshold(new_threshold);
-                crate::save_pool(pool);
-                updated = true;
-            }
-            
-            if updated {
-                // 重新计算所有头寸的健康因子，因为清算阈值变化可能影响清算状态
-                update_all_positions_liquidation_status(new_threshold);
-                
-                ic_cdk::println!(
-                    "Liquidation threshold updated to {}% by {}",
-                    new_threshold,
-                    caller
-                );
-            }
-            
-            Ok(updated)
-        },
-        LogLevel::Error,
-        &format!("update_liquidation_threshold: 更新清算阈值失败, new_threshold={}", new_threshold)
-    )
+// 先按最终取值校验所有池，全部通过后再写入，任一校验失败时不修改任何池
+fn apply_parameters(collateral_ratio: Option<u8>, liquidation_threshold: Option<u8>) -> Result<bool> {
+    // 验证抵押率参数
+    if let Some(ratio) = collateral_ratio.filter(|r| *r == 0 || *r > 100) {
+        return Err(Error::InvalidArgument(format!(
+            "无效的抵押率: {}，应在 1-100 之间",
+            ratio
+        )));
+    }
+    
+    // 验证清算阈值参数
+    if let Some(threshold) = liquidation_threshold.filter(|t| *t == 0 || *t > 100) {
+        return Err(Error::InvalidArgument(format!(
+            "无效的清算阈值: {}，应在 1-100 之间",
+            threshold
+        )));
+    }
+    
+    // 检查每个池更新后仍满足 抵押率 < 清算阈值
+    let pools = crate::get_pools();
+    for pool in &pools {
+        let ratio = collateral_ratio.unwrap_or(pool.collateral_ratio);
+        let threshold = liquidation_threshold.unwrap_or(pool.liquidation_threshold);
+        if ratio >= threshold {
+            return Err(Error::InvalidArgument(format!(
+                "抵押率 {} 不能大于或等于清算阈值 {} (资金池 {})",
+                ratio, threshold, pool.addr
+            )));
+        }
+    }
+    
+    if pools.is_empty() {
+        return Ok(false);
+    }
+    
+    for mut pool in pools {
+        if let Some(ratio) = collateral_ratio {
+            pool.update_collateral_ratio(ratio);
+        }
+        if let Some(threshold) = liquidation_threshold {
+            pool.update_liquidation_threshold(threshold);
+        }
+        crate::save_pool(pool);
+    }
+    
+    if let Some(ratio) = collateral_ratio {
+        ic_cdk::println!("Collateral ratio updated to {}%", ratio);
+    }
+    if let Some(threshold) = liquidation_threshold {
+        // 重新计算所有头寸的健康因子，因为清算阈值变化可能影响清算状态
+        update_all_positions_liquidation_status(threshold);
+        
+        ic_cdk::println!("Liquidation threshold updated to {}%", threshold);
+    }
+    
+    Ok(true)
 }
 
 #[query]
@@ -145,8 +90,8 @@ pub fn get_system_parameters() -> Result<SystemParameters> {
     )
 }
 
-#[update]
-// 批量更新系统参数
+// 批量更新系统参数 (仅由治理提案执行，见 governance.rs)
+// 两个参数一起校验后再写入，不会出现只应用了其中一个的情况
 pub fn update_system_parameters(
     collateral_ratio: Option<u8>,
     liquidation_threshold: Option<u8>,
@@ -154,28 +99,19 @@ pub fn update_system_parameters(
     // 使用 catch_and_log 包装操作
     catch_and_log(
         || {
-            // 验证调用者是否为控制者
-            let caller = crate::ic_api::caller();
-            if !crate::ic_api::is_controller(&caller) {
-                return Err(Error::PermissionDenied("Not authorized".to_string()));
-            }
+            let (current_ratio, current_threshold) = crate::get_pools().first()
+                .map(|p| (p.collateral_ratio, p.liquidation_threshold))
+                .unwrap_or((0, 0));
             
-            let mut updated = false;
+            let updated = apply_parameters(collateral_ratio, liquidation_threshold)?;
             
-            // 如果提供了抵押率，则更新
-            if let Some(ratio) = collateral_ratio {
-                match update_collateral_ratio(ratio) {
-                    Ok(result) => updated = updated || result,
-                    Err(e) => return Err(e),
-                }
-            }
-            
-            // 如果提供了清算阈值，则更新
-            if let Some(threshold) = liquidation_threshold {
-                match update_liquidation_threshold(threshold) {
-                    Ok(result) => updated = updated || result,
-                    Err(e) => return Err(e),
-                }
+            if updated {
+                crate::events::record_event(crate::events::ProtocolEvent::ParametersChanged {
+                    old_collateral_ratio: current_ratio,
+                    new_collateral_ratio: collateral_ratio.unwrap_or(current_ratio),
+                    old_liquidation_threshold: current_threshold,
+                    new_liquidation_threshold: liquidation_threshold.unwrap_or(current_threshold),
+                });
             }
             
             Ok(updated)
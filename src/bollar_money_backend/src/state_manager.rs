@@ -19,6 +19,9 @@ pub enum StateOperation {
     UpdatePrice,
     Liquidation,
     Emergency,
+    MergePositions,
+    SplitPosition,
+    TransferPosition,
 }
 
 // 状态快照
@@ -347,8 +350,8 @@ impl StateManager {
             }
             
             // 验证头寸所属的池是否存在
-            let pool_addr = position.id.split(':').next().unwrap_or("");
-            if !pools.iter().any(|p| p.addr == pool_addr) {
+            let pool_addr = &position.pool_address;
+            if !pools.iter().any(|p| p.addr == *pool_addr) {
                 result.errors.push(format!("Position {} references non-existent pool {}", 
                                          position.id, pool_addr));
                 result.valid = false;
@@ -498,10 +501,4 @@ pub fn auto_snapshot_task() {
             );
         }
     }
-}
-
-// 心跳函数中的状态管理任务
-#[ic_cdk_macros::heartbeat]
-async fn state_manager_heartbeat() {
-    auto_snapshot_task();
 }
\ No newline at end of file
@@ -12,6 +12,9 @@ pub mod mock {
         static MOCK_TIME: RefCell<u64> = RefCell::new(1_000_000_000_000_000_000); // 默认时间戳
         static MOCK_CALLER: RefCell<Principal> = RefCell::new(Principal::anonymous());
         static MOCK_CONTROLLERS: RefCell<Vec<Principal>> = RefCell::new(vec![Principal::anonymous()]);
+        static MOCK_NOTIFICATIONS: RefCell<Vec<(Principal, String, Vec<u8>)>> = RefCell::new(Vec::new());
+        static MOCK_FAILING_CANISTERS: RefCell<Vec<Principal>> = RefCell::new(Vec::new());
+        static MOCK_RAND_COUNTER: RefCell<u64> = RefCell::new(0);
     }
 
     /// 设置模拟时间
@@ -39,6 +42,41 @@ pub mod mock {
         MOCK_CONTROLLERS.with(|c| *c.borrow_mut() = controllers);
     }
 
+    /// 获取模拟的 canister ID
+    pub fn canister_id() -> Principal {
+        Principal::from_slice(&[0xCA, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x01])
+    }
+
+    /// 获取模拟的随机字节 (对应 raw_rand)，每次调用返回不同的确定性字节
+    pub fn random_bytes() -> Vec<u8> {
+        use sha2::{Digest, Sha256};
+        
+        let counter = MOCK_RAND_COUNTER.with(|c| {
+            *c.borrow_mut() += 1;
+            *c.borrow()
+        });
+        Sha256::digest(counter.to_be_bytes()).to_vec()
+    }
+
+    /// 模拟单向调用，目标在失败列表中时返回错误
+    pub fn notify(canister_id: Principal, method: &str, args: &[u8]) -> crate::Result<()> {
+        if MOCK_FAILING_CANISTERS.with(|f| f.borrow().contains(&canister_id)) {
+            return Err(crate::Error::SystemError("notify failed: SysTransient".to_string()));
+        }
+        MOCK_NOTIFICATIONS.with(|n| n.borrow_mut().push((canister_id, method.to_string(), args.to_vec())));
+        Ok(())
+    }
+
+    /// 设置单向调用会失败的 canister
+    pub fn set_failing_canisters(canisters: Vec<Principal>) {
+        MOCK_FAILING_CANISTERS.with(|f| *f.borrow_mut() = canisters);
+    }
+
+    /// 获取已发送的单向调用
+    pub fn sent_notifications() -> Vec<(Principal, String, Vec<u8>)> {
+        MOCK_NOTIFICATIONS.with(|n| n.borrow().clone())
+    }
+
     /// 检查是否为控制者
     pub fn is_controller(principal: &Principal) -> bool {
         MOCK_CONTROLLERS.with(|controllers| {
@@ -136,6 +174,41 @@ pub mod mock {
         // 实际实现中，这会通过 oracle 模块处理
         Ok(())
     }
+
+    /// BIP-322 测试向量的私钥，对应地址 TEST_P2WPKH_ADDRESS
+    pub const TEST_P2WPKH_WIF: &str = "L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k";
+    pub const TEST_P2WPKH_ADDRESS: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+
+    /// 用 P2WPKH 私钥生成 BIP-322 simple 格式签名 (base64)
+    pub fn sign_bip322_p2wpkh(wif: &str, message: &str) -> String {
+        use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+        use bitcoin::{
+            Address, Amount, Network, PrivateKey, Witness,
+            hashes::Hash,
+            secp256k1::{Message, Secp256k1},
+            sighash::{EcdsaSighashType, SighashCache},
+        };
+
+        let secp = Secp256k1::new();
+        let private_key = PrivateKey::from_wif(wif).expect("Invalid WIF");
+        let public_key = private_key.public_key(&secp);
+        let script_pubkey = Address::p2wpkh(&public_key, Network::Bitcoin)
+            .expect("Uncompressed key")
+            .script_pubkey();
+
+        let to_spend = crate::bip322::to_spend_tx(&script_pubkey, message.as_bytes());
+        let to_sign = crate::bip322::to_sign_tx(to_spend.txid(), Witness::new());
+        let sighash = SighashCache::new(&to_sign)
+            .p2wpkh_signature_hash(0, &script_pubkey, Amount::ZERO, EcdsaSighashType::All)
+            .expect("Failed to compute sighash");
+
+        let signature = bitcoin::ecdsa::Signature {
+            sig: secp.sign_ecdsa(&Message::from_digest(sighash.to_byte_array()), &private_key.inner),
+            hash_ty: EcdsaSighashType::All,
+        };
+        let witness = Witness::from_slice(&[signature.to_vec(), public_key.to_bytes()]);
+        BASE64.encode(bitcoin::consensus::serialize(&witness))
+    }
 }
 
 #[cfg(test)]
@@ -84,6 +84,7 @@ mod tests {
     fn test_position_creation() {
         let position = Position::new(
             "position1".to_string(),
+            "pool1".to_string(),
             "user1".to_string(),
             100000, // 0.001 BTC
             75000,  // 75000 Bollar
@@ -91,6 +92,7 @@ mod tests {
         );
         
         assert_eq!(position.id, "position1");
+        assert_eq!(position.pool_address, "pool1");
         assert_eq!(position.owner, "user1");
         assert_eq!(position.btc_collateral, 100000);
         assert_eq!(position.bollar_debt, 75000);
@@ -124,6 +126,7 @@ mod tests {
         // 创建一个健康因子为 133 的头寸
         let mut position = Position::new(
             "position1".to_string(),
+            "pool1".to_string(),
             "user1".to_string(),
             100000, // 0.001 BTC
             2250, // 2250 cents Bollar
@@ -145,4 +148,124 @@ mod tests {
         // 清算阈值为 70，头寸可清算
         assert!(position.is_liquidatable(70));
     }
+    
+    // 测试头寸 ID 按池单调递增且互不冲突
+    #[test]
+    fn test_next_position_id() {
+        let pool_a = "pool_a".to_string();
+        let pool_b = "pool_b".to_string();
+        
+        let first = crate::next_position_id(&pool_a);
+        let second = crate::next_position_id(&pool_a);
+        let other = crate::next_position_id(&pool_b);
+        
+        assert_eq!(first, "pool_a:0");
+        assert_eq!(second, "pool_a:1");
+        assert_eq!(other, "pool_b:0");
+        
+        // 不经过计数器写入的头寸 (如从备份恢复) 不会被重新分配
+        let restored = Position::new(
+            "pool_b:1".to_string(), "pool_b".to_string(), "owner".to_string(), 100_000_000, 1_000_000, 3_000_000,
+        );
+        crate::save_position(restored);
+        assert_eq!(crate::next_position_id(&pool_b), "pool_b:2");
+    }
+    
+    // 测试头寸二级索引随保存和删除同步更新
+    #[test]
+    fn test_position_indexes() {
+        let healthy = Position::new(
+            "pool1:0".to_string(),
+            "pool1".to_string(),
+            "user1".to_string(),
+            100000, // 0.001 BTC
+            1000,   // 健康因子 300
+            3000000,
+        );
+        let risky = Position::new(
+            "pool1:1".to_string(),
+            "pool1".to_string(),
+            "user2".to_string(),
+            100000,
+            2900,   // 健康因子 103
+            3000000,
+        );
+        crate::save_position(healthy.clone());
+        crate::save_position(risky.clone());
+        
+        // 按所有者查询
+        assert_eq!(crate::get_user_positions(&"user1".to_string()), vec![healthy.clone()]);
+        assert_eq!(crate::get_user_positions(&"user2".to_string()), vec![risky.clone()]);
+        
+        // 阈值 110 下只有高风险头寸是候选
+        let candidates = crate::get_liquidation_candidates(3000000, 110);
+        assert!(candidates.iter().any(|p| p.id == risky.id));
+        assert!(!candidates.iter().any(|p| p.id == healthy.id));
+        
+        // 价格下跌 70% 后两个头寸都是候选
+        let candidates = crate::get_liquidation_candidates(900000, 110);
+        assert_eq!(candidates.len(), 2);
+        
+        // 所有者变更后索引同步更新
+        let mut transferred = risky.clone();
+        transferred.owner = "user1".to_string();
+        crate::save_position(transferred);
+        assert!(crate::get_user_positions(&"user2".to_string()).is_empty());
+        assert_eq!(crate::get_user_positions(&"user1".to_string()).len(), 2);
+        
+        // 删除后从索引中移除
+        crate::delete_position(&risky.id);
+        assert_eq!(crate::get_user_positions(&"user1".to_string()), vec![healthy]);
+        assert!(crate::get_liquidation_candidates(3000000, 110).is_empty());
+    }
+
+    #[test]
+    fn test_pool_validate_borrow() {
+        // 测试追加借款交易的池侧校验
+        use crate::{Error, types::{Txid, Utxo}};
+        use ree_types::{CoinBalance, CoinBalances, OutputCoin};
+        
+        let pool_address = "borrowpool".to_string();
+        crate::test_utils::init_test_pool(pool_address.clone());
+        let pool = crate::get_pool(&pool_address).unwrap();
+        let spent = pool.current_state().unwrap().utxo.clone().unwrap();
+        let txid: Txid = "2222222222222222222222222222222222222222222222222222222222222222".parse().unwrap();
+        
+        let utxo = |sats: u64, bollar: u128| {
+            let mut coins = CoinBalances::new();
+            coins.add_coin(&CoinBalance { id: pool.meta.id, value: bollar });
+            Utxo::try_from(format!("{}:0", txid), coins, sats).unwrap()
+        };
+        let outputs = |bollar: u128| vec![OutputCoin {
+            coin: CoinBalance { id: pool.meta.id, value: bollar },
+            to: "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq".to_string(),
+        }];
+        
+        // 正确交易: 花费当前 UTXO，BTC 不变，Bollar 减少转出数量
+        let (state, consumed, amount) = pool.validate_borrow(
+            txid, 0, vec![spent.outpoint.clone()], vec![utxo(100_000_000, 49_500)], vec![], outputs(500),
+        ).unwrap();
+        assert_eq!(amount, 500);
+        assert_eq!(consumed, spent);
+        assert_eq!(state.nonce, 1);
+        assert_eq!(state.id, Some(txid));
+        
+        // 过期 nonce
+        assert!(matches!(
+            pool.validate_borrow(txid, 1, vec![spent.outpoint.clone()], vec![utxo(100_000_000, 49_500)], vec![], outputs(500)),
+            Err(Error::PoolStateExpired(0))
+        ));
+        
+        // 未花费池的当前 UTXO
+        assert!(pool.validate_borrow(txid, 0, vec![format!("{}:1", txid)], vec![utxo(100_000_000, 49_500)], vec![], outputs(500)).is_err());
+        
+        // 池的 Bollar 减少量与转出数量不符
+        assert!(pool.validate_borrow(txid, 0, vec![spent.outpoint.clone()], vec![utxo(100_000_000, 49_000)], vec![], outputs(500)).is_err());
+        
+        // 从池中转走 BTC
+        assert!(pool.validate_borrow(txid, 0, vec![spent.outpoint.clone()], vec![utxo(90_000_000, 49_500)], vec![], outputs(500)).is_err());
+        
+        // 转出超过池余额的 Bollar
+        assert!(pool.validate_borrow(txid, 0, vec![spent.outpoint], vec![utxo(100_000_000, 0)], vec![], outputs(60_000)).is_err());
+    }
 }
\ No newline at end of file
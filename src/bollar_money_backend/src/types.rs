@@ -71,6 +71,8 @@ pub struct PoolState {
 #[derive(CandidType, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Position {
     pub id: String,              // 头寸唯一标识符
+    #[serde(default)]
+    pub pool_address: String,    // 所属资金池地址
     pub owner: String,           // 用户地址
     pub btc_collateral: u64,     // BTC 抵押数量 (satoshis)
     pub bollar_debt: u64,        // 借出的 Bollar 数量
@@ -83,6 +85,7 @@ impl Position {
     // 创建新头寸
     pub fn new(
         id: String,
+        pool_address: String,
         owner: String,
         btc_collateral: u64,
         bollar_debt: u64,
@@ -93,6 +96,7 @@ impl Position {
         
         Self {
             id,
+            pool_address,
             owner,
             btc_collateral,
             bollar_debt,
@@ -139,6 +143,17 @@ pub struct DepositOffer {
     pub max_bollar_mint: u64,    // 可铸造的最大 Bollar 数量
 }
 
+// 追加借款预处理结果
+#[derive(Eq, PartialEq, CandidType, Clone, Debug, Deserialize, Serialize)]
+pub struct BorrowMoreOffer {
+    pub pool_utxo: Utxo,         // 池的当前 UTXO
+    pub nonce: u64,              // 交易 nonce
+    pub btc_price: u64,          // BTC 当前价格
+    pub max_additional_bollar: u64, // 可追加铸造的最大 Bollar 数量
+    pub new_bollar_debt: u64,    // 追加后的 Bollar 债务
+    pub new_health_factor: u64,  // 追加后的健康因子
+}
+
 // 还款预处理结果
 #[derive(Eq, PartialEq, CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct RepayOffer {
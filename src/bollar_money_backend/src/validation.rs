@@ -101,14 +101,21 @@ pub fn validate_psbt_hex(psbt_hex: &str) -> Result<()> {
 pub fn validate_position_id(position_id: &str) -> Result<()> {
     validate_input!(!position_id.is_empty(), "头寸ID不能为空");
     
-    // 头寸ID格式: pool_address:timestamp:user
+    // 头寸ID格式: pool_address:sequence
+    // 迁移前创建的旧头寸保留 pool_address:timestamp:user 格式，两者都接受
     let parts: Vec<&str> = position_id.split(':').collect();
-    validate_input!(parts.len() == 3, "头寸ID格式无效");
-    
-    // 验证各部分
-    validate_bitcoin_address(parts[0])?;
-    validate_input!(parts[1].parse::<u64>().is_ok(), "头寸ID中的时间戳无效");
-    validate_input!(!parts[2].is_empty(), "头寸ID中的用户标识无效");
+    match parts.as_slice() {
+        [pool_address, sequence] => {
+            validate_bitcoin_address(pool_address)?;
+            validate_input!(sequence.parse::<u64>().is_ok(), "头寸ID中的序号无效");
+        }
+        [pool_address, timestamp, user] => {
+            validate_bitcoin_address(pool_address)?;
+            validate_input!(timestamp.parse::<u64>().is_ok(), "头寸ID中的时间戳无效");
+            validate_input!(!user.is_empty(), "头寸ID中的用户标识无效");
+        }
+        _ => return Err(Error::InvalidArgument("头寸ID格式无效".to_string())),
+    }
     
     Ok(())
 }